@@ -67,7 +67,7 @@ export const usersApi = {
 
 export const attendanceApi = {
   sign: (payload: SignAttendanceRequest) => api.post<Message>('/attendance/check-in', payload),
-  adminSign: (userId: string) => api.get<Message>(`/attendance/admin/sign/${userId}`),
+  adminSign: (userId: string) => api.post<Message>(`/attendance/admin/sign/${userId}`),
 };
 
 export const analyticsApi = {
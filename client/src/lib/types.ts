@@ -112,7 +112,7 @@ export interface UserFilter {
 }
 
 export interface ChangePasswordRequest {
-  email: string;
+  current_password: string;
   password: string;
 }
 
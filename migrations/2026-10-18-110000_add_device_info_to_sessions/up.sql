@@ -0,0 +1,5 @@
+-- Your SQL goes here
+ALTER TABLE sessions
+    ADD COLUMN ip_address TEXT,
+    ADD COLUMN user_agent TEXT,
+    ADD COLUMN last_seen_at TIMESTAMP NOT NULL DEFAULT now();
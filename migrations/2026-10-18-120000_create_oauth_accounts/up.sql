@@ -0,0 +1,16 @@
+-- Your SQL goes here
+CREATE TABLE oauth_accounts (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL,
+    provider TEXT NOT NULL,
+    subject TEXT NOT NULL,
+    email TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT now(),
+    CONSTRAINT fk_oauth_accounts_user
+        FOREIGN KEY (user_id)
+        REFERENCES users(id)
+        ON DELETE CASCADE
+);
+
+CREATE UNIQUE INDEX oauth_accounts_provider_subject_idx ON oauth_accounts(provider, subject);
+CREATE INDEX oauth_accounts_user_id_idx ON oauth_accounts(user_id);
@@ -0,0 +1,8 @@
+-- Your SQL goes here
+-- One row per throttled key: `user:<uuid>` for an account, `ip:<address>` for a client.
+CREATE TABLE login_throttles (
+    key TEXT PRIMARY KEY,
+    failures INT NOT NULL DEFAULT 0,
+    locked_until TIMESTAMP,
+    last_failure_at TIMESTAMP NOT NULL DEFAULT now()
+);
@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+DELETE FROM role_permissions WHERE permission = '"settings.manage"';
+ALTER TABLE sessions DROP COLUMN IF EXISTS mfa_verified;
+DROP TABLE IF EXISTS settings;
+DROP TABLE IF EXISTS mfa_recovery_codes;
+DROP TABLE IF EXISTS user_mfa;
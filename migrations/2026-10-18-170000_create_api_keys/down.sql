@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DELETE FROM role_permissions WHERE permission = '"api_keys.manage"';
+DELETE FROM role_permissions WHERE permission = '"attendance.check_in"';
+DROP TABLE IF EXISTS api_keys;
@@ -0,0 +1,24 @@
+-- Your SQL goes here
+CREATE TABLE api_keys (
+    id UUID PRIMARY KEY,
+    name TEXT NOT NULL,
+    prefix TEXT NOT NULL,
+    key_hash TEXT NOT NULL UNIQUE,
+    scopes TEXT[] NOT NULL,
+    created_by UUID NOT NULL,
+    expires_at TIMESTAMP NOT NULL,
+    last_used_at TIMESTAMP,
+    revoked_at TIMESTAMP,
+    created_at TIMESTAMP NOT NULL DEFAULT now(),
+    CONSTRAINT fk_api_keys_created_by
+        FOREIGN KEY (created_by)
+        REFERENCES users(id)
+        ON DELETE CASCADE
+);
+
+-- Grant the new permissions to Admin on databases whose role permissions were already seeded.
+INSERT INTO role_permissions (role, permission)
+SELECT '"Admin"', permission
+FROM (VALUES ('"api_keys.manage"'), ('"attendance.check_in"')) AS granted(permission)
+WHERE EXISTS (SELECT 1 FROM role_permissions)
+ON CONFLICT DO NOTHING;
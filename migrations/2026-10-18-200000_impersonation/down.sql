@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DELETE FROM role_permissions WHERE permission = '"users.impersonate"';
+
+ALTER TABLE sessions DROP COLUMN impersonator_id;
@@ -0,0 +1,9 @@
+-- Your SQL goes here
+-- Sessions an admin opened to view the app as another user. Regular logins leave it null.
+ALTER TABLE sessions ADD COLUMN impersonator_id UUID REFERENCES users(id) ON DELETE CASCADE;
+
+-- Grant the new permission to Admin on databases whose role permissions were already seeded.
+INSERT INTO role_permissions (role, permission)
+SELECT '"Admin"', '"users.impersonate"'
+WHERE EXISTS (SELECT 1 FROM role_permissions)
+ON CONFLICT DO NOTHING;
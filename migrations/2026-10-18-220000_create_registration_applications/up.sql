@@ -0,0 +1,21 @@
+-- Your SQL goes here
+CREATE TABLE registration_applications (
+    id UUID PRIMARY KEY,
+    first_name TEXT NOT NULL,
+    last_name TEXT NOT NULL,
+    email TEXT NOT NULL,
+    dob TIMESTAMP,
+    year_joined TEXT NOT NULL,
+    gender TEXT,
+    phone TEXT,
+    status TEXT NOT NULL DEFAULT '"pending"',
+    rejection_reason TEXT,
+    reviewed_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    reviewed_at TIMESTAMP,
+    -- The account created when the application was approved.
+    user_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_registration_applications_status ON registration_applications(status);
+CREATE INDEX idx_registration_applications_email ON registration_applications(email);
@@ -0,0 +1,16 @@
+-- Your SQL goes here
+CREATE TABLE import_profiles (
+    id UUID PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE,
+    -- What the profile imports: '"users"' or '"roster"'.
+    kind TEXT NOT NULL,
+    -- Field name -> header in the file. Unmapped fields use their own name as header.
+    column_map JSONB NOT NULL DEFAULT '{}',
+    -- chrono format of date columns; detected from the file when null.
+    date_format TEXT,
+    -- Field name -> transforms applied to its values, in order.
+    transforms JSONB NOT NULL DEFAULT '{}',
+    created_by UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
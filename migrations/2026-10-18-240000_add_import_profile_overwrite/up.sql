@@ -0,0 +1,3 @@
+-- Your SQL goes here
+-- Field name -> when an import may overwrite an existing user's value.
+ALTER TABLE import_profiles ADD COLUMN overwrite JSONB NOT NULL DEFAULT '{}';
@@ -0,0 +1,13 @@
+-- Your SQL goes here
+-- The last reg_no code handed out for each year_joined.
+CREATE TABLE reg_no_sequences (
+    year TEXT PRIMARY KEY,
+    last_value BIGINT NOT NULL DEFAULT 0,
+    updated_at TIMESTAMP NOT NULL DEFAULT now()
+);
+
+INSERT INTO reg_no_sequences (year, last_value)
+SELECT year_joined, MAX(substring(reg_no FROM '([0-9]+)$')::BIGINT)
+FROM users
+WHERE reg_no ~ '[0-9]+$'
+GROUP BY year_joined;
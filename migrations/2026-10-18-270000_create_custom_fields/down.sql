@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX users_custom_fields_idx;
+ALTER TABLE users DROP COLUMN emergency_contacts;
+ALTER TABLE users DROP COLUMN custom_fields;
+DROP TABLE custom_fields;
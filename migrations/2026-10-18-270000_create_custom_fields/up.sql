@@ -0,0 +1,24 @@
+-- Your SQL goes here
+CREATE TABLE custom_fields (
+    id UUID PRIMARY KEY,
+    -- Name of the value in users.custom_fields; never changes once created.
+    key TEXT NOT NULL UNIQUE,
+    label TEXT NOT NULL,
+    -- '"text"', '"number"', '"boolean"', '"date"' or '"select"'.
+    field_type TEXT NOT NULL,
+    -- The choices of a select field; empty for the other types.
+    options TEXT[] NOT NULL DEFAULT '{}',
+    required BOOLEAN NOT NULL DEFAULT FALSE,
+    -- Who sees the value: '"public"', '"private"' or '"admin"'.
+    visibility TEXT NOT NULL,
+    position INT NOT NULL DEFAULT 0,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+-- Field key -> value. Cleared fields are removed rather than stored as null.
+ALTER TABLE users ADD COLUMN custom_fields JSONB NOT NULL DEFAULT '{}';
+-- Array of { name, relationship, phone, email, next_of_kin }.
+ALTER TABLE users ADD COLUMN emergency_contacts JSONB NOT NULL DEFAULT '[]';
+
+CREATE INDEX users_custom_fields_idx ON users USING GIN (custom_fields);
@@ -0,0 +1,3 @@
+-- Your SQL goes here
+-- Set once the user's personal data has been scrubbed; anonymised users cannot be restored.
+ALTER TABLE users ADD COLUMN anonymised_at TIMESTAMP;
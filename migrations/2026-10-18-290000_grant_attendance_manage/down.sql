@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+DELETE FROM role_permissions WHERE permission = '"attendance.manage"';
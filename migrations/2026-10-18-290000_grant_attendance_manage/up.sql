@@ -0,0 +1,6 @@
+-- Your SQL goes here
+-- Grant the new permission to Admin on databases whose role permissions were already seeded.
+INSERT INTO role_permissions (role, permission)
+SELECT '"Admin"', '"attendance.manage"'
+WHERE EXISTS (SELECT 1 FROM role_permissions)
+ON CONFLICT DO NOTHING;
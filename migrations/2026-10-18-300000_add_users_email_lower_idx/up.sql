@@ -0,0 +1,3 @@
+-- Your SQL goes here
+-- Emails differing only in case belong to the same person, so they may not be registered twice.
+CREATE UNIQUE INDEX users_email_lower_idx ON users (lower(email));
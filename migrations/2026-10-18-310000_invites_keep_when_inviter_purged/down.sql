@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+DELETE FROM user_invites WHERE invited_by IS NULL;
+ALTER TABLE user_invites DROP CONSTRAINT user_invites_invited_by_fkey;
+ALTER TABLE user_invites
+    ADD CONSTRAINT user_invites_invited_by_fkey
+    FOREIGN KEY (invited_by) REFERENCES users(id) ON DELETE CASCADE;
+ALTER TABLE user_invites ALTER COLUMN invited_by SET NOT NULL;
@@ -0,0 +1,7 @@
+-- Your SQL goes here
+-- Purging the admin who sent an invite must not take the invite away from the invited user.
+ALTER TABLE user_invites ALTER COLUMN invited_by DROP NOT NULL;
+ALTER TABLE user_invites DROP CONSTRAINT user_invites_invited_by_fkey;
+ALTER TABLE user_invites
+    ADD CONSTRAINT user_invites_invited_by_fkey
+    FOREIGN KEY (invited_by) REFERENCES users(id) ON DELETE SET NULL;
@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+DELETE FROM import_profiles WHERE created_by IS NULL;
+ALTER TABLE import_profiles DROP CONSTRAINT import_profiles_created_by_fkey;
+ALTER TABLE import_profiles
+    ADD CONSTRAINT import_profiles_created_by_fkey
+    FOREIGN KEY (created_by) REFERENCES users(id) ON DELETE CASCADE;
+ALTER TABLE import_profiles ALTER COLUMN created_by SET NOT NULL;
@@ -0,0 +1,7 @@
+-- Your SQL goes here
+-- Import profiles are shared, so purging the admin who saved one must not delete it.
+ALTER TABLE import_profiles ALTER COLUMN created_by DROP NOT NULL;
+ALTER TABLE import_profiles DROP CONSTRAINT import_profiles_created_by_fkey;
+ALTER TABLE import_profiles
+    ADD CONSTRAINT import_profiles_created_by_fkey
+    FOREIGN KEY (created_by) REFERENCES users(id) ON DELETE SET NULL;
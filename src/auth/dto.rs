@@ -5,3 +5,54 @@ pub struct LoginPayload {
     pub user: String,
     pub password: String,
 }
+
+/// Query string Google appends when redirecting back to `/auth/google/callback`.
+#[derive(Debug, Serialize, Deserialize, utoipa::IntoParams)]
+pub struct GoogleCallbackQuery {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    pub error: Option<String>,
+}
+
+/// How the reset code is delivered: a clickable link, or a short code to type in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+pub enum ResetMethod {
+    #[default]
+    Link,
+    Otp,
+}
+
+#[derive(Debug, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct ForgotPasswordRequest {
+    pub email: String,
+    #[serde(default)]
+    pub method: ResetMethod,
+}
+
+#[derive(Debug, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct ResetPasswordRequest {
+    pub email: String,
+    /// The OTP or the `token` query parameter of the emailed link.
+    pub code: String,
+    pub password: String,
+}
+
+/// Returned by `/auth/login` instead of the user when a TOTP code is still needed. The
+/// short-lived `mfa_token` cookie set alongside it identifies the pending login.
+#[derive(Debug, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct MfaChallenge {
+    pub mfa_required: bool,
+}
+
+impl Default for MfaChallenge {
+    fn default() -> Self {
+        Self { mfa_required: true }
+    }
+}
+
+#[derive(Debug, Serialize, utoipa::ToSchema)]
+#[serde(untagged)]
+pub enum LoginResponse {
+    User(Box<crate::dto::user::UserDto>),
+    MfaRequired(MfaChallenge),
+}
@@ -14,10 +14,15 @@ pub struct GoogleUserResult {
     pub id: String,
     pub email: String,
     pub verified_email: bool,
+    #[serde(default)]
     pub name: String,
+    #[serde(default)]
     pub given_name: String,
+    #[serde(default)]
     pub family_name: String,
+    #[serde(default)]
     pub picture: String,
+    #[serde(default)]
     pub locale: String,
 }
 
@@ -29,7 +34,6 @@ pub async fn request_token(
     let client_secret = data.google_oauth_client_secret.to_owned();
     let client_id = data.google_oauth_client_id.to_owned();
 
-    let root_url = "https://oauth2.googleapis.com/token";
     let client = Client::new();
 
     let params = [
@@ -39,7 +43,7 @@ pub async fn request_token(
         ("code", authorization_code),
         ("client_secret", client_secret.as_str()),
     ];
-    let response = client.post(root_url).form(&params).send().await?;
+    let response = client.post(&data.token_url).form(&params).send().await?;
     if response.status().is_success() {
         let body = response.text().await?;
         let oauth_response = serde_json::from_str::<OAuthResponse>(&body)?;
@@ -53,9 +57,10 @@ pub async fn request_token(
 pub async fn get_google_user(
     access_token: &str,
     id_token: &str,
+    data: Arc<Config>,
 ) -> Result<GoogleUserResult, Box<dyn Error>> {
     let client = Client::new();
-    let mut url = Url::parse("https://www.googleapis.com/oauth2/v1/userinfo").unwrap();
+    let mut url = Url::parse(&data.userinfo_url)?;
     url.query_pairs_mut().append_pair("alt", "json");
     url.query_pairs_mut()
         .append_pair("access_token", access_token);
@@ -71,3 +76,16 @@ pub async fn get_google_user(
         Err(From::from(message))
     }
 }
+
+/// Builds the Google consent screen URL the browser is sent to from `/auth/google/start`.
+pub fn authorization_url(data: &Config, state: &str) -> Result<Url, Box<dyn Error>> {
+    let mut url = Url::parse(&data.auth_url)?;
+    url.query_pairs_mut()
+        .append_pair("client_id", &data.google_oauth_client_id)
+        .append_pair("redirect_uri", &data.google_oauth_redirect_url)
+        .append_pair("response_type", "code")
+        .append_pair("scope", "openid email profile")
+        .append_pair("prompt", "select_account")
+        .append_pair("state", state);
+    Ok(url)
+}
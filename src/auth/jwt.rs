@@ -1,12 +1,17 @@
-use axum::{extract::FromRequestParts, http::request::Parts};
+use axum::{
+    extract::{FromRef, FromRequestParts},
+    http::request::Parts,
+};
 use axum_extra::extract::CookieJar;
 use chrono::Utc;
-use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation, decode, encode};
+use diesel::OptionalExtension;
+use diesel_async::RunQueryDsl;
 use serde::{Deserialize, Serialize};
-use std::sync::LazyLock;
 use uuid::Uuid;
 
-use crate::models::users::Role;
+use crate::models::{
+    permissions::Permission, refresh_tokens::RefreshToken, sessions::Session, users::Role,
+};
 
 use super::*;
 
@@ -29,48 +34,154 @@ impl AuthBodyDto {
     }
 }
 
-static KEYS: LazyLock<Keys> = LazyLock::new(|| {
-    let secret = std::env::var("JWT_SECRET").expect("JWT_SECRET must be set");
-    Keys::new(secret.as_bytes())
-});
+pub const ACCESS_TOKEN_TTL_HOURS: i64 = 10;
+pub const REFRESH_TOKEN_TTL_HOURS: i64 = 12;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Claims {
     pub user_id: Uuid,
     pub exp: usize,
     pub role: Role,
+    /// Id of the `sessions` row this token was issued under.
+    pub jti: Uuid,
+    /// Whether the session passed a second factor at login.
+    #[serde(default)]
+    pub mfa: bool,
+    /// The admin acting as `user_id`, when this is an impersonation token.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub impersonator_id: Option<Uuid>,
+    /// Impersonation tokens are limited to safe methods and hold no permissions unless the
+    /// admin allowed writes.
+    #[serde(default)]
+    pub read_only: bool,
+    /// Set when the request was authenticated with an API key instead of a session token.
+    #[serde(skip)]
+    pub api_key: Option<ApiKeyGrant>,
 }
 
-pub fn create_session_token(id: Uuid, role: Role) -> Result<AuthBodyDto, ModuleError> {
-    let expiration = Utc::now()
-        .checked_add_signed(chrono::Duration::hours(10))
-        .expect("valid timestamp")
-        .timestamp() as usize;
+/// The key behind API-key `Claims`. Its scopes replace the role's permissions.
+#[derive(Debug, Clone)]
+pub struct ApiKeyGrant {
+    pub id: Uuid,
+    pub scopes: Vec<Permission>,
+}
+
+/// Request extension through which a route admits API keys scoped to the permission.
+/// Without it the `Claims` extractor turns API keys away.
+#[derive(Debug, Clone, Copy)]
+pub struct ApiKeyScope(pub Permission);
+
+/// Request extension marking the route a user with `must_change_password` can still reach.
+#[derive(Debug, Clone, Copy)]
+pub struct PasswordChangeRoute;
+
+/// Claims of the short-lived token handed out between the password and the TOTP step of a
+/// two-factor login. It cannot be decoded as `Claims`, so it grants no access by itself.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MfaClaims {
+    pub user_id: Uuid,
+    pub exp: usize,
+    pub purpose: String,
+}
 
-    let refresh_expiration = Utc::now()
-        .checked_add_signed(chrono::Duration::hours(12))
+pub const MFA_TOKEN_TTL_MINUTES: i64 = 5;
+const MFA_TOKEN_PURPOSE: &str = "mfa";
+
+/// Claims carried by the refresh token. `jti` is the id of the persisted
+/// `refresh_tokens` row, and `family_id` ties every rotation of a login together.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RefreshClaims {
+    pub user_id: Uuid,
+    pub exp: usize,
+    pub jti: Uuid,
+    pub family_id: Uuid,
+}
+
+pub fn create_session_token(
+    id: Uuid,
+    role: Role,
+    refresh: &RefreshToken,
+    mfa: bool,
+) -> Result<AuthBodyDto, ModuleError> {
+    let expiration = Utc::now()
+        .checked_add_signed(chrono::Duration::hours(ACCESS_TOKEN_TTL_HOURS))
         .expect("valid timestamp")
         .timestamp() as usize;
 
-    let mut claims = Claims {
+    let claims = Claims {
         user_id: id,
         exp: expiration,
         role,
+        jti: refresh.family_id,
+        mfa,
+        impersonator_id: None,
+        read_only: false,
+        api_key: None,
     };
 
     // Create the authorization token
-    let token = encode(&Header::default(), &claims, &KEYS.encoding)
-        .map_err(|_| ModuleError::InternalError("Could not create Token".into()))?;
+    let token = super::keys::sign(&claims)?;
 
-    claims.exp = refresh_expiration;
+    let refresh_claims = RefreshClaims {
+        user_id: id,
+        exp: refresh.expires_at.and_utc().timestamp() as usize,
+        jti: refresh.id,
+        family_id: refresh.family_id,
+    };
 
-    let refresh_token = encode(&Header::default(), &claims, &KEYS.encoding)
-        .map_err(|_| ModuleError::InternalError("Could not create Token".into()))?;
+    let refresh_token = super::keys::sign(&refresh_claims)?;
 
     // Send the authorized token
     Ok(AuthBodyDto::new(token, refresh_token, id))
 }
 
+/// Issues the access token of an impersonation session. There is no refresh token; the
+/// session simply ends when the token expires.
+pub fn create_impersonation_token(
+    session: &Session,
+    role: Role,
+    read_only: bool,
+) -> Result<String, ModuleError> {
+    let claims = Claims {
+        user_id: session.user_id,
+        exp: session.expires_at.and_utc().timestamp() as usize,
+        role,
+        jti: session.id,
+        mfa: session.mfa_verified,
+        impersonator_id: session.impersonator_id,
+        read_only,
+        api_key: None,
+    };
+    super::keys::sign(&claims)
+}
+
+/// Decodes an access token. Logout passes `validate_exp = false` so that a session whose
+/// access token already lapsed can still be revoked.
+pub fn decode_access_token(token: &str, validate_exp: bool) -> Result<Claims, ModuleError> {
+    super::keys::verify(token, validate_exp)
+}
+
+pub fn decode_refresh_token(token: &str) -> Result<RefreshClaims, ModuleError> {
+    super::keys::verify(token, true)
+}
+
+pub fn create_mfa_token(user_id: Uuid) -> Result<String, ModuleError> {
+    let claims = MfaClaims {
+        user_id,
+        exp: (Utc::now() + chrono::Duration::minutes(MFA_TOKEN_TTL_MINUTES)).timestamp() as usize,
+        purpose: MFA_TOKEN_PURPOSE.to_string(),
+    };
+    super::keys::sign(&claims)
+}
+
+pub fn decode_mfa_token(token: &str) -> Result<MfaClaims, ModuleError> {
+    let claims: MfaClaims = super::keys::verify(token, true)?;
+    if claims.purpose != MFA_TOKEN_PURPOSE {
+        return Err(ModuleError::InvalidToken);
+    }
+    Ok(claims)
+}
+
 impl std::fmt::Display for Claims {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "User: {}", self.user_id)
@@ -79,39 +190,101 @@ impl std::fmt::Display for Claims {
 
 impl<S> FromRequestParts<S> for Claims
 where
+    Arc<AppState>: FromRef<S>,
     S: Send + Sync,
 {
     type Rejection = ModuleError;
 
     async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        // The auth middleware and the handler both extract claims; only hit the database once.
+        if let Some(claims) = parts.extensions.get::<Claims>() {
+            return Ok(claims.clone());
+        }
+
         let jar = CookieJar::from_request_parts(parts, state)
             .await
             .map_err(|_| ModuleError::InternalError("Could not extract cookies".into()))?;
 
-        let token = jar
-            .get("access_token")
-            .map(|cookie| cookie.value().to_string())
+        // Non-browser clients send the token in the `Authorization` header instead.
+        let token = bearer_token(parts)
+            .or_else(|| {
+                jar.get("access_token")
+                    .map(|cookie| cookie.value().to_string())
+            })
             .ok_or(ModuleError::CouldNotExtractToken(
                 "Could not extract token.",
             ))?;
 
-        let token_data = decode::<Claims>(&token, &KEYS.decoding, &Validation::default())
-            .map_err(|_| ModuleError::InvalidToken)?;
+        let state = Arc::<AppState>::from_ref(state);
+        let mut conn = state
+            .pool
+            .get()
+            .await
+            .map_err(|_| ModuleError::InternalError(POOL_ERROR_MSG.into()))?;
 
-        Ok(token_data.claims)
-    }
-}
+        if crate::services::api_keys::is_api_key(&token) {
+            let Some(ApiKeyScope(scope)) = parts.extensions.get::<ApiKeyScope>().copied() else {
+                return Err(ModuleError::PermissionDenied);
+            };
+            let claims = crate::services::api_keys::authenticate(
+                &mut conn,
+                &state.permissions,
+                &token,
+                scope,
+                parts,
+            )
+            .await?;
+            parts.extensions.insert(claims.clone());
+            return Ok(claims);
+        }
 
-struct Keys {
-    encoding: EncodingKey,
-    decoding: DecodingKey,
-}
+        let claims = decode_access_token(&token, true)?;
+        let (must_change_password, impersonator_id) = schema::sessions::table
+            .inner_join(schema::users::table)
+            .filter(schema::sessions::id.eq(claims.jti))
+            .filter(schema::sessions::user_id.eq(claims.user_id))
+            .filter(schema::sessions::revoked_at.is_null())
+            .filter(schema::users::deleted_at.is_null())
+            .select((
+                schema::users::must_change_password,
+                schema::sessions::impersonator_id,
+            ))
+            .first::<(bool, Option<Uuid>)>(&mut conn)
+            .await
+            .optional()?
+            .ok_or(ModuleError::InvalidToken)?;
+        if impersonator_id != claims.impersonator_id {
+            return Err(ModuleError::InvalidToken);
+        }
 
-impl Keys {
-    fn new(secret: &[u8]) -> Self {
-        Self {
-            encoding: EncodingKey::from_secret(secret),
-            decoding: DecodingKey::from_secret(secret),
+        if let Some(impersonator_id) = impersonator_id {
+            if claims.read_only && !parts.method.is_safe() {
+                return Err(ModuleError::ImpersonationReadOnly);
+            }
+            crate::services::impersonation::record_request(
+                &mut conn,
+                impersonator_id,
+                claims.user_id,
+                parts,
+            )
+            .await?;
+        } else if must_change_password && parts.extensions.get::<PasswordChangeRoute>().is_none() {
+            // An admin viewing as the user cannot change the password for them, so the
+            // placeholder only stops the user themself.
+            return Err(ModuleError::PasswordChangeRequired);
         }
+
+        parts.extensions.insert(claims.clone());
+        Ok(claims)
     }
 }
+
+fn bearer_token(parts: &Parts) -> Option<String> {
+    parts
+        .headers
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(|token| token.trim().to_string())
+        .filter(|token| !token.is_empty())
+}
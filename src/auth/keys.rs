@@ -0,0 +1,349 @@
+//! Ed25519 keys for signing JWTs.
+//!
+//! Each key is a PKCS#8 PEM file named `<kid>.pem` in `JWT_KEYS_DIR` (default `keys`). The
+//! newest key signs; older keys keep verifying until every token they could have signed has
+//! expired, so rotating never logs anybody out.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::{OnceLock, RwLock},
+    time::{Duration, Instant},
+};
+
+use base64::Engine;
+use chrono::{NaiveDateTime, Utc};
+use jsonwebtoken::{
+    Algorithm, DecodingKey, EncodingKey, Header, Validation,
+    jwk::{
+        AlgorithmParameters, CommonParameters, EllipticCurve, Jwk, JwkSet, KeyAlgorithm,
+        OctetKeyPairParameters, OctetKeyPairType, PublicKeyUse,
+    },
+};
+use ring::signature::{Ed25519KeyPair, KeyPair};
+use serde::{Serialize, de::DeserializeOwned};
+use uuid::Uuid;
+
+use super::jwt::REFRESH_TOKEN_TTL_HOURS;
+use crate::ModuleError;
+
+static KEY_RING: OnceLock<RwLock<KeyRing>> = OnceLock::new();
+
+/// How long a superseded key keeps verifying: the lifetime of the longest-lived token.
+const RETENTION_HOURS: i64 = REFRESH_TOKEN_TTL_HOURS;
+
+/// How often an unknown `kid` may make the key directory be read again. Tokens naming a
+/// `kid` nobody issued cost nothing more than a lookup in between.
+const RELOAD_INTERVAL: Duration = Duration::from_secs(30);
+
+struct SigningKey {
+    kid: String,
+    created_at: NaiveDateTime,
+    encoding: EncodingKey,
+    decoding: DecodingKey,
+    /// Base64url public key, the `x` member of the JWK.
+    public_x: String,
+    path: PathBuf,
+}
+
+struct KeyRing {
+    dir: PathBuf,
+    /// Oldest first; the last one signs.
+    keys: Vec<SigningKey>,
+    /// The old `JWT_SECRET`, still accepted for tokens without a `kid` while it is set.
+    legacy: Option<DecodingKey>,
+    /// When the directory was last read.
+    loaded_at: Instant,
+}
+
+#[derive(Debug, Clone, Serialize, serde::Deserialize, utoipa::ToSchema)]
+pub struct SigningKeyDto {
+    pub kid: String,
+    pub created_at: NaiveDateTime,
+    /// Whether new tokens are signed with this key.
+    pub active: bool,
+    /// When the key stops verifying; unset for the active key.
+    pub retires_at: Option<NaiveDateTime>,
+}
+
+impl KeyRing {
+    fn load(dir: PathBuf) -> Result<Self, ModuleError> {
+        std::fs::create_dir_all(&dir)?;
+        let mut keys = Vec::new();
+        for entry in std::fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("pem") {
+                continue;
+            }
+            keys.push(read_key(&path)?);
+        }
+        keys.sort_by_key(|key| key.created_at);
+
+        let legacy = std::env::var("JWT_SECRET")
+            .ok()
+            .filter(|secret| !secret.is_empty())
+            .map(|secret| DecodingKey::from_secret(secret.as_bytes()));
+
+        let mut ring = KeyRing {
+            dir,
+            keys,
+            legacy,
+            loaded_at: Instant::now(),
+        };
+        if ring.keys.is_empty() {
+            tracing::info!("No JWT signing key found, generating one...");
+            ring.generate()?;
+        }
+        Ok(ring)
+    }
+
+    fn generate(&mut self) -> Result<&SigningKey, ModuleError> {
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&ring::rand::SystemRandom::new())
+            .map_err(|_| ModuleError::InternalError("Could not generate a signing key".into()))?;
+        let kid = Uuid::now_v7().to_string();
+        let path = self.dir.join(format!("{kid}.pem"));
+        let pem = pem::encode(&pem::Pem::new("PRIVATE KEY", pkcs8.as_ref().to_vec()));
+        write_private(&path, pem.as_bytes())?;
+        self.keys.push(read_key(&path)?);
+        Ok(self.keys.last().expect("a key was just pushed"))
+    }
+
+    fn active(&self) -> &SigningKey {
+        self.keys.last().expect("the key ring is never empty")
+    }
+
+    /// When the key at `index` stops verifying, counted from when its successor took over.
+    fn retires_at(&self, index: usize) -> Option<NaiveDateTime> {
+        self.keys
+            .get(index + 1)
+            .map(|next| next.created_at + chrono::Duration::hours(RETENTION_HOURS))
+    }
+
+    fn verifying_key(&self, kid: &str) -> Option<&DecodingKey> {
+        let now = Utc::now().naive_utc();
+        self.keys
+            .iter()
+            .enumerate()
+            .find(|(_, key)| key.kid == kid)
+            .filter(|(index, _)| self.retires_at(*index).is_none_or(|at| at > now))
+            .map(|(_, key)| &key.decoding)
+    }
+
+    /// Deletes the files of keys that no longer verify anything.
+    fn prune(&mut self) -> Result<(), ModuleError> {
+        let now = Utc::now().naive_utc();
+        let retired: Vec<usize> = (0..self.keys.len())
+            .filter(|index| self.retires_at(*index).is_some_and(|at| at <= now))
+            .collect();
+        for index in retired.into_iter().rev() {
+            let key = self.keys.remove(index);
+            std::fs::remove_file(&key.path)?;
+            tracing::info!("Removed retired JWT signing key {}", key.kid);
+        }
+        Ok(())
+    }
+}
+
+fn read_key(path: &Path) -> Result<SigningKey, ModuleError> {
+    let invalid = || {
+        ModuleError::InternalError(
+            format!("{} is not a PKCS#8 Ed25519 private key", path.display()).into(),
+        )
+    };
+    let pem = pem::parse(std::fs::read(path)?).map_err(|_| invalid())?;
+    let der = pem.contents();
+    let pair = Ed25519KeyPair::from_pkcs8_maybe_unchecked(der).map_err(|_| invalid())?;
+    let public_key = pair.public_key().as_ref();
+
+    let kid = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .ok_or_else(invalid)?
+        .to_string();
+    // Generated keys are named after a v7 UUID, which carries the creation time. Keys
+    // dropped in by hand fall back to the file's modification time.
+    let created_at = match Uuid::parse_str(&kid).ok().and_then(|id| id.get_timestamp()) {
+        Some(timestamp) => {
+            let (seconds, nanos) = timestamp.to_unix();
+            chrono::DateTime::from_timestamp(seconds as i64, nanos)
+                .map(|at| at.naive_utc())
+                .ok_or_else(invalid)?
+        }
+        None => chrono::DateTime::<Utc>::from(std::fs::metadata(path)?.modified()?).naive_utc(),
+    };
+
+    Ok(SigningKey {
+        kid,
+        created_at,
+        encoding: EncodingKey::from_ed_der(der),
+        decoding: DecodingKey::from_ed_der(public_key),
+        public_x: base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(public_key),
+        path: path.to_path_buf(),
+    })
+}
+
+fn write_private(path: &Path, contents: &[u8]) -> Result<(), ModuleError> {
+    use std::io::Write;
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(path)?.write_all(contents)?;
+    Ok(())
+}
+
+fn key_ring() -> &'static RwLock<KeyRing> {
+    KEY_RING
+        .get()
+        .expect("auth::keys::init must run before tokens are issued")
+}
+
+/// Loads the key directory, generating a first key when it is empty. Call once at startup.
+pub fn init() -> Result<(), ModuleError> {
+    let dir = std::env::var("JWT_KEYS_DIR").unwrap_or("keys".to_string());
+    let ring = KeyRing::load(PathBuf::from(dir))?;
+    tracing::info!(
+        "Loaded {} JWT signing key(s), active kid {}",
+        ring.keys.len(),
+        ring.active().kid
+    );
+    let _ = KEY_RING.set(RwLock::new(ring));
+    Ok(())
+}
+
+/// Signs `claims` with the active key, naming it in the `kid` header.
+pub fn sign<T: Serialize>(claims: &T) -> Result<String, ModuleError> {
+    let ring = key_ring()
+        .read()
+        .map_err(|_| ModuleError::InternalError("Signing keys are unavailable".into()))?;
+    let active = ring.active();
+    let mut header = Header::new(Algorithm::EdDSA);
+    header.kid = Some(active.kid.clone());
+    jsonwebtoken::encode(&header, claims, &active.encoding)
+        .map_err(|_| ModuleError::InternalError("Could not create Token".into()))
+}
+
+/// Verifies a token against the key named in its `kid` header.
+///
+/// A `kid` this process has not seen may come from a rotation done by another instance
+/// sharing the key directory, so the directory is reloaded before giving up, at most once
+/// every `RELOAD_INTERVAL`.
+pub fn verify<T: DeserializeOwned>(token: &str, validate_exp: bool) -> Result<T, ModuleError> {
+    let header = jsonwebtoken::decode_header(token).map_err(|_| ModuleError::InvalidToken)?;
+    let lock = key_ring();
+
+    let Some(kid) = header.kid else {
+        let ring = lock
+            .read()
+            .map_err(|_| ModuleError::InternalError("Signing keys are unavailable".into()))?;
+        let legacy = ring.legacy.as_ref().ok_or(ModuleError::InvalidToken)?;
+        return decode(token, legacy, Algorithm::HS256, validate_exp);
+    };
+
+    {
+        let ring = lock
+            .read()
+            .map_err(|_| ModuleError::InternalError("Signing keys are unavailable".into()))?;
+        if let Some(key) = ring.verifying_key(&kid) {
+            return decode(token, key, Algorithm::EdDSA, validate_exp);
+        }
+        if ring.keys.iter().any(|key| key.kid == kid) {
+            // Known but retired.
+            return Err(ModuleError::InvalidToken);
+        }
+        if ring.loaded_at.elapsed() < RELOAD_INTERVAL {
+            return Err(ModuleError::InvalidToken);
+        }
+    }
+
+    let mut ring = lock
+        .write()
+        .map_err(|_| ModuleError::InternalError("Signing keys are unavailable".into()))?;
+    // Another request may have reloaded while this one waited for the lock.
+    if ring.loaded_at.elapsed() >= RELOAD_INTERVAL {
+        *ring = KeyRing::load(ring.dir.clone())?;
+    }
+    let key = ring.verifying_key(&kid).ok_or(ModuleError::InvalidToken)?;
+    decode(token, key, Algorithm::EdDSA, validate_exp)
+}
+
+fn decode<T: DeserializeOwned>(
+    token: &str,
+    key: &DecodingKey,
+    algorithm: Algorithm,
+    validate_exp: bool,
+) -> Result<T, ModuleError> {
+    let mut validation = Validation::new(algorithm);
+    validation.validate_exp = validate_exp;
+    let data = jsonwebtoken::decode::<T>(token, key, &validation)
+        .map_err(|_| ModuleError::InvalidToken)?;
+    Ok(data.claims)
+}
+
+/// Makes a new key the signing key. Earlier keys keep verifying for `RETENTION_HOURS`, and
+/// keys past that are deleted.
+pub fn rotate() -> Result<SigningKeyDto, ModuleError> {
+    let mut ring = key_ring()
+        .write()
+        .map_err(|_| ModuleError::InternalError("Signing keys are unavailable".into()))?;
+    // Pick up keys added by other instances first, so the new key really is the newest.
+    *ring = KeyRing::load(ring.dir.clone())?;
+    let key = ring.generate()?;
+    let dto = SigningKeyDto {
+        kid: key.kid.clone(),
+        created_at: key.created_at,
+        active: true,
+        retires_at: None,
+    };
+    ring.prune()?;
+    Ok(dto)
+}
+
+/// Every key that still verifies, newest first.
+pub fn list() -> Result<Vec<SigningKeyDto>, ModuleError> {
+    let ring = key_ring()
+        .read()
+        .map_err(|_| ModuleError::InternalError("Signing keys are unavailable".into()))?;
+    let now = Utc::now().naive_utc();
+    let last = ring.keys.len() - 1;
+    Ok(ring
+        .keys
+        .iter()
+        .enumerate()
+        .map(|(index, key)| SigningKeyDto {
+            kid: key.kid.clone(),
+            created_at: key.created_at,
+            active: index == last,
+            retires_at: ring.retires_at(index),
+        })
+        .filter(|key| key.retires_at.is_none_or(|at| at > now))
+        .rev()
+        .collect())
+}
+
+/// The public halves of every key that still verifies, for `/.well-known/jwks.json`.
+pub fn jwks() -> Result<JwkSet, ModuleError> {
+    let ring = key_ring()
+        .read()
+        .map_err(|_| ModuleError::InternalError("Signing keys are unavailable".into()))?;
+    let now = Utc::now().naive_utc();
+    let keys = ring
+        .keys
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| ring.retires_at(*index).is_none_or(|at| at > now))
+        .map(|(_, key)| Jwk {
+            common: CommonParameters {
+                public_key_use: Some(PublicKeyUse::Signature),
+                key_algorithm: Some(KeyAlgorithm::EdDSA),
+                key_id: Some(key.kid.clone()),
+                ..Default::default()
+            },
+            algorithm: AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                key_type: OctetKeyPairType::OctetKeyPair,
+                curve: EllipticCurve::Ed25519,
+                x: key.public_x.clone(),
+            }),
+        })
+        .collect();
+    Ok(JwkSet { keys })
+}
@@ -1,20 +1,35 @@
 #![allow(unused_variables)]
 
-use crate::{auth::jwt::Claims, models::users::Role};
+use crate::{
+    ModuleError,
+    auth::jwt::{ApiKeyScope, Claims, PasswordChangeRoute},
+    models::permissions::Permission,
+};
 use axum::{
     debug_middleware,
-    extract::{Request, State},
+    extract::{FromRequestParts, Request, State},
     http::StatusCode,
     middleware::Next,
     response::Response,
 };
-use std::sync::Arc;
+use ipnet::IpNet;
+use std::{
+    net::IpAddr,
+    sync::{Arc, LazyLock, OnceLock},
+};
 pub use tokio::task_local;
+use uuid::Uuid;
 
 task_local! {
     pub static USER: String;
+    /// The admin behind the current request, once the `Claims` extractor has seen an
+    /// impersonation token.
+    static IMPERSONATOR: Arc<OnceLock<Uuid>>;
 }
 
+/// Response header naming the admin a request was made by while impersonating.
+pub const IMPERSONATED_BY_HEADER: &str = "x-impersonated-by";
+
 #[debug_middleware]
 pub async fn authorize(
     State(state): State<Arc<crate::AppState>>,
@@ -25,41 +40,232 @@ pub async fn authorize(
     Ok(next.run(req).await)
 }
 
-pub async fn admin_authorize(
+/// Rejects the request unless the caller's role has been granted `permission`, and, when
+/// the organisation requires it for the role, the session passed two-factor. API keys are
+/// admitted when `permission` is one of their scopes.
+///
+/// ```ignore
+/// .layer(middleware::from_fn_with_state(
+///     (state.clone(), Permission::UsersManage),
+///     crate::auth::middleware::require_permission,
+/// ))
+/// ```
+pub async fn require_permission(
+    State((state, permission)): State<(Arc<crate::AppState>, Permission)>,
+    req: Request,
+    next: Next,
+) -> Result<Response, ModuleError> {
+    let (mut parts, body) = req.into_parts();
+    parts.extensions.insert(ApiKeyScope(permission));
+    let Claims {
+        role,
+        mfa,
+        api_key,
+        read_only,
+        ..
+    } = Claims::from_request_parts(&mut parts, &state).await?;
+    // A read-only impersonation gets none of the user's permissions, whatever the method.
+    if read_only {
+        return Err(ModuleError::ImpersonationReadOnly);
+    }
+    // The extractor has already checked the key's scopes.
+    if api_key.is_none() {
+        crate::guard!(role, permission, state.permissions);
+        if !crate::services::mfa::is_satisfied(&state.settings, &role, mfa).await {
+            return Err(ModuleError::MfaRequired);
+        }
+    }
+    Ok(next.run(Request::from_parts(parts, body)).await)
+}
+
+/// Like `authorize`, but also admits API keys scoped to `permission`. Session callers need
+/// no particular permission.
+pub async fn authorize_with_api_key(
+    State((state, permission)): State<(Arc<crate::AppState>, Permission)>,
+    req: Request,
+    next: Next,
+) -> Result<Response, ModuleError> {
+    let (mut parts, body) = req.into_parts();
+    parts.extensions.insert(ApiKeyScope(permission));
+    Claims::from_request_parts(&mut parts, &state).await?;
+    Ok(next.run(Request::from_parts(parts, body)).await)
+}
+
+/// Like `authorize`, but also admits users who still have to replace a placeholder
+/// password. Only the change-password route uses it.
+pub async fn authorize_password_change(
     State(state): State<Arc<crate::AppState>>,
-    Claims { role, .. }: Claims,
     req: Request,
     next: Next,
-) -> Result<Response, StatusCode> {
-    if role != Role::Admin {
-        return Err(StatusCode::FORBIDDEN);
+) -> Result<Response, ModuleError> {
+    let (mut parts, body) = req.into_parts();
+    parts.extensions.insert(PasswordChangeRoute);
+    Claims::from_request_parts(&mut parts, &state).await?;
+    Ok(next.run(Request::from_parts(parts, body)).await)
+}
+
+/// Gives each request a slot for the impersonating admin, so activity logs written while
+/// handling it record both identities and the response is marked with
+/// `X-Impersonated-By`.
+pub async fn impersonation_middleware(request: Request, next: Next) -> Response {
+    let impersonator = Arc::new(OnceLock::new());
+    let mut response = IMPERSONATOR
+        .scope(impersonator.clone(), next.run(request))
+        .await;
+    if let Some(impersonator_id) = impersonator.get() {
+        response.headers_mut().insert(
+            IMPERSONATED_BY_HEADER,
+            axum::http::HeaderValue::from_str(&impersonator_id.to_string())
+                .expect("uuid is a valid header value"),
+        );
     }
-    Ok(next.run(req).await)
+    response
+}
+
+pub(crate) fn set_impersonator(impersonator_id: Uuid) {
+    let _ = IMPERSONATOR.try_with(|slot| slot.set(impersonator_id));
+}
+
+/// The admin the current request is being made by, when it carries an impersonation token.
+pub fn current_impersonator() -> Option<Uuid> {
+    IMPERSONATOR
+        .try_with(|slot| slot.get().copied())
+        .ok()
+        .flatten()
 }
 
+/// Proxies whose `X-Forwarded-For` is believed: the comma-separated addresses and CIDR
+/// ranges in `TRUSTED_PROXIES`. Unset, the header is ignored.
+static TRUSTED_PROXIES: LazyLock<Vec<IpNet>> = LazyLock::new(|| {
+    std::env::var("TRUSTED_PROXIES")
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .filter_map(|entry| {
+            let net = entry
+                .parse::<IpNet>()
+                .or_else(|_| entry.parse::<IpAddr>().map(IpNet::from));
+            if net.is_err() {
+                tracing::warn!("Ignoring invalid TRUSTED_PROXIES entry {}", entry);
+            }
+            net.ok()
+        })
+        .collect()
+});
+
+/// The socket peer, unless it is a trusted proxy. Then the `X-Forwarded-For` hops are
+/// walked from the nearest one back, and the first address no trusted proxy owns is the
+/// client; anything the client wrote before that is never reached.
+fn client_ip(headers: &axum::http::HeaderMap, peer: IpAddr, trusted: &[IpNet]) -> IpAddr {
+    let is_trusted_proxy = |ip: &IpAddr| trusted.iter().any(|net| net.contains(ip));
+    let mut client = peer.to_canonical();
+    if !is_trusted_proxy(&client) {
+        return client;
+    }
+    let hops: Vec<&str> = headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .collect();
+    for hop in hops.into_iter().rev() {
+        let Ok(ip) = hop.parse::<IpAddr>() else {
+            break;
+        };
+        client = ip.to_canonical();
+        if !is_trusted_proxy(&client) {
+            break;
+        }
+    }
+    client
+}
+
+/// Records the caller's IP address and user agent for session bookkeeping.
+///
+/// The address comes from the socket, or from `X-Forwarded-For` when the socket peer is
+/// one of the `TRUSTED_PROXIES`, so clients cannot pick it themselves.
 pub async fn connection_info_middleware(
     user_agent: Option<axum_extra::TypedHeader<axum_extra::headers::UserAgent>>,
-    addr: axum::extract::ConnectInfo<core::net::SocketAddr>,
     mut request: Request,
     next: Next,
 ) -> Response {
-    let user_agent = match user_agent {
-        Some(u) => u,
-        None => {
-            return axum::response::IntoResponse::into_response(axum::response::Html(
-                "UserAgent is missing.",
-            ));
-        }
-    };
-    request.extensions_mut().insert(ConnectionInfo {
-        ip: addr.to_string(),
-        user_agent: user_agent.to_string(),
-    });
+    let ip = request
+        .extensions()
+        .get::<axum::extract::ConnectInfo<core::net::SocketAddr>>()
+        .map(|addr| client_ip(request.headers(), addr.ip(), &TRUSTED_PROXIES).to_string())
+        .unwrap_or("unknown".to_string());
+    let user_agent = user_agent
+        .map(|u| u.to_string())
+        .unwrap_or("unknown".to_string());
+
+    request
+        .extensions_mut()
+        .insert(ConnectionInfo { ip, user_agent });
     next.run(request).await
 }
 
-#[derive(Clone)]
+#[derive(Debug, Clone)]
 pub struct ConnectionInfo {
     pub ip: String,
     pub user_agent: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::{HeaderMap, HeaderValue};
+
+    fn forwarded(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    fn ip(value: &str) -> IpAddr {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn untrusted_peer_ignores_forwarded_for() {
+        let trusted = vec!["10.0.0.0/8".parse().unwrap()];
+        let headers = forwarded("1.1.1.1");
+        assert_eq!(client_ip(&headers, ip("8.8.8.8"), &trusted), ip("8.8.8.8"));
+        assert_eq!(client_ip(&headers, ip("8.8.8.8"), &[]), ip("8.8.8.8"));
+    }
+
+    #[test]
+    fn trusted_peer_takes_the_last_untrusted_hop() {
+        let trusted = vec!["10.0.0.0/8".parse().unwrap()];
+        // The client prepended a spoofed address; the proxy appended the real one.
+        let headers = forwarded("6.6.6.6, 203.0.113.7, 10.0.0.2");
+        assert_eq!(
+            client_ip(&headers, ip("10.0.0.1"), &trusted),
+            ip("203.0.113.7")
+        );
+    }
+
+    #[test]
+    fn trusted_peer_without_usable_hops() {
+        let trusted = vec!["10.0.0.1/32".parse().unwrap()];
+        assert_eq!(
+            client_ip(&HeaderMap::new(), ip("10.0.0.1"), &trusted),
+            ip("10.0.0.1")
+        );
+        assert_eq!(
+            client_ip(&forwarded("garbage"), ip("10.0.0.1"), &trusted),
+            ip("10.0.0.1")
+        );
+    }
+
+    #[test]
+    fn mapped_ipv4_peer_is_canonical() {
+        let trusted = vec!["127.0.0.1/32".parse().unwrap()];
+        let headers = forwarded("198.51.100.4");
+        assert_eq!(
+            client_ip(&headers, ip("::ffff:127.0.0.1"), &trusted),
+            ip("198.51.100.4")
+        );
+    }
+}
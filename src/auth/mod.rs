@@ -1,6 +1,7 @@
 pub mod dto;
 pub mod google_oauth;
 pub mod jwt;
+pub mod keys;
 pub mod middleware;
 pub mod service;
 use super::*;
@@ -1,5 +1,18 @@
+use crate::auth::google_oauth;
+use crate::auth::middleware::ConnectionInfo;
+use crate::config::Config;
+use crate::dto::mfa::MfaCodeRequest;
 use crate::dto::user::UserDto;
+use crate::mailer::{
+    types::MailerEvent,
+    worker::{MailerSender, dispatch},
+};
+use crate::models::oauth_accounts::OAuthAccount;
+use crate::models::refresh_tokens::RefreshToken;
+use crate::models::sessions::Session;
+use crate::services::{login_throttles, mfa};
 use axum_extra::extract::CookieJar;
+use chrono::Datelike;
 use cookie::Cookie;
 use diesel_async::RunQueryDsl;
 
@@ -7,11 +20,18 @@ use super::jwt::*;
 use super::*;
 use crate::models::activity_logs::{ActivityLog, ActivityType};
 
+const OAUTH_STATE_COOKIE: &str = "oauth_state";
+const GOOGLE_PROVIDER: &str = "google";
+const MFA_TOKEN_COOKIE: &str = "mfa_token";
+
 pub async fn login(
     jar: CookieJar,
     pool: Arc<Pool>,
+    settings: &crate::SettingsCache,
+    mailer: &MailerSender,
+    connection: ConnectionInfo,
     payload: LoginPayload,
-) -> Result<(CookieJar, UserDto), ModuleError> {
+) -> Result<(CookieJar, LoginResponse), ModuleError> {
     let mut conn = pool
         .get()
         .await
@@ -19,36 +39,55 @@ pub async fn login(
     let user =
         crate::services::users::find_user_by_email_or_username(&mut conn, &payload.user).await?;
 
-    if let Some(user) = user {
+    let throttle_keys = login_throttles::login_keys(user.as_ref().map(|u| u.id), &connection.ip);
+    login_throttles::ensure_not_locked(&mut conn, &throttle_keys).await?;
+
+    if let Some(mut user) = user {
         let is_valid = crate::helpers::password_verfier(&payload.password, &user.password);
         if !is_valid {
+            let failures = login_throttles::record_failure(&mut conn, &throttle_keys).await?;
+            let log = ActivityLog::new(ActivityType::LoginFailed, user.id)
+                .set_target_id(user.id)
+                .set_target_type("User".into())
+                .set_details(serde_json::json!({
+                    "ip_address": connection.ip,
+                    "user_agent": connection.user_agent,
+                    "failures": failures,
+                }))
+                .finish();
+            crate::services::activity_logs::emit_log(log, &mut conn).await?;
             return Err(ModuleError::AuthError);
         }
 
-        let token = create_session_token(user.id, user.role.clone())?;
-
-        let cookie = Cookie::build(("access_token", token.access_token))
-            .http_only(true)
-            .secure(true) // ❌❌❌ change this to true for production
-            .path("/")
-            .max_age(cookie::time::Duration::days(7))
-            .same_site(cookie::SameSite::None)
-            .build();
-
-        let refresh_cookie = Cookie::build(("refresh_token", token.refresh_token))
-            .path("/")
-            .http_only(true)
-            .secure(true) // ❌❌❌ change this to true for production
-            .same_site(cookie::SameSite::None)
-            .max_age(cookie::time::Duration::days(8))
-            .build();
-
-        let updated_jar = jar.add(cookie).add(refresh_cookie);
-
-        diesel::update(schema::users::table.filter(schema::users::id.eq(user.id)))
-            .set(schema::users::last_seen.eq(chrono::Utc::now().naive_utc()))
-            .execute(&mut conn)
-            .await?;
+        login_throttles::clear_user(&mut conn, user.id).await?;
+        if crate::helpers::password_needs_rehash(&user.password) {
+            let password_hash = crate::helpers::password_hasher(&payload.password)?;
+            diesel::update(schema::users::table.find(user.id))
+                .set(schema::users::password_hash.eq(password_hash))
+                .execute(&mut conn)
+                .await?;
+        }
+        // Only the plain password shows whether it is the placeholder or too weak, so
+        // accounts that never went through a forced change are caught here.
+        if !user.must_change_password
+            && (payload.password == crate::helpers::PLACEHOLDER_PASSWORD
+                || crate::helpers::check_password_policy(
+                    &payload.password,
+                    &*settings.read().await,
+                )
+                .is_err())
+        {
+            diesel::update(schema::users::table.find(user.id))
+                .set(schema::users::must_change_password.eq(true))
+                .execute(&mut conn)
+                .await?;
+            user.must_change_password = true;
+        }
+        if mfa::is_enabled(&mut conn, user.id).await? {
+            let jar = set_mfa_cookie(jar, create_mfa_token(user.id)?);
+            return Ok((jar, LoginResponse::MfaRequired(MfaChallenge::default())));
+        }
+        let updated_jar = complete_login(&mut conn, jar, mailer, &connection, &user, false).await?;
 
         let log = ActivityLog::new(ActivityType::UserLogin, user.id)
             .set_target_id(user.id)
@@ -56,8 +95,584 @@ pub async fn login(
             .finish();
         crate::services::activity_logs::emit_log(log, &mut conn).await?;
 
-        Ok((updated_jar, user))
+        Ok((updated_jar, LoginResponse::User(Box::new(user))))
     } else {
+        login_throttles::record_failure(&mut conn, &throttle_keys).await?;
         Err(ModuleError::AuthError)
     }
 }
+
+/// Second step of a two-factor login: checks the TOTP or recovery code against the user in
+/// the `mfa_token` cookie and starts the session.
+///
+/// Wrong codes count towards the same lockout as wrong passwords.
+pub async fn verify_mfa_login(
+    jar: CookieJar,
+    pool: Arc<Pool>,
+    mailer: &MailerSender,
+    connection: ConnectionInfo,
+    payload: MfaCodeRequest,
+) -> Result<(CookieJar, UserDto), ModuleError> {
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|_| ModuleError::InternalError(POOL_ERROR_MSG.into()))?;
+
+    let token = jar
+        .get(MFA_TOKEN_COOKIE)
+        .map(|cookie| cookie.value().to_string())
+        .ok_or(ModuleError::CouldNotExtractToken(
+            "Could not extract two-factor token.",
+        ))?;
+    let claims = decode_mfa_token(&token)?;
+
+    let throttle_keys = login_throttles::login_keys(Some(claims.user_id), &connection.ip);
+    login_throttles::ensure_not_locked(&mut conn, &throttle_keys).await?;
+
+    // The account may have been deactivated or deleted since the password step.
+    let user = schema::users::table
+        .find(claims.user_id)
+        .select(UserDto::as_select())
+        .first::<UserDto>(&mut conn)
+        .await
+        .optional()?
+        .filter(UserDto::can_sign_in)
+        .ok_or(ModuleError::AuthError)?;
+
+    if !mfa::verify_code(&mut conn, claims.user_id, &payload.code).await? {
+        let failures = login_throttles::record_failure(&mut conn, &throttle_keys).await?;
+        let log = ActivityLog::new(ActivityType::LoginFailed, claims.user_id)
+            .set_target_id(claims.user_id)
+            .set_target_type("User".into())
+            .set_details(serde_json::json!({
+                "ip_address": connection.ip,
+                "user_agent": connection.user_agent,
+                "failures": failures,
+                "step": "mfa",
+            }))
+            .finish();
+        crate::services::activity_logs::emit_log(log, &mut conn).await?;
+        return Err(ModuleError::AuthError);
+    }
+    login_throttles::clear_user(&mut conn, claims.user_id).await?;
+
+    let jar = jar.remove(Cookie::build(MFA_TOKEN_COOKIE).path("/"));
+    let jar = complete_login(&mut conn, jar, mailer, &connection, &user, true).await?;
+
+    let log = ActivityLog::new(ActivityType::UserLogin, user.id)
+        .set_target_id(user.id)
+        .set_target_type("User".into())
+        .set_details(serde_json::json!({ "mfa": true }))
+        .finish();
+    crate::services::activity_logs::emit_log(log, &mut conn).await?;
+
+    Ok((jar, user))
+}
+
+/// Marks the current session as having passed two-factor and reissues its cookies, so a
+/// user who just enrolled does not have to log in again.
+pub async fn upgrade_session_mfa(
+    conn: &mut crate::Connection<'_>,
+    jar: CookieJar,
+    claims: &Claims,
+) -> Result<CookieJar, ModuleError> {
+    diesel::update(schema::sessions::table.find(claims.jti))
+        .set(schema::sessions::mfa_verified.eq(true))
+        .execute(conn)
+        .await?;
+    issue_session(
+        conn,
+        jar,
+        claims.user_id,
+        claims.role.clone(),
+        claims.jti,
+        true,
+    )
+    .await
+}
+
+/// Sets the `oauth_state` cookie and returns the Google consent URL to redirect to.
+pub fn google_start(jar: CookieJar, config: &Config) -> Result<(CookieJar, String), ModuleError> {
+    let state = Uuid::new_v4().to_string();
+    let url = google_oauth::authorization_url(config, &state)
+        .map_err(|e| ModuleError::InternalError(e.to_string().into()))?;
+
+    // Lax so the cookie is sent on the top-level redirect back from Google.
+    let cookie = Cookie::build((OAUTH_STATE_COOKIE, state))
+        .http_only(true)
+        .secure(true)
+        .path("/")
+        .max_age(cookie::time::Duration::minutes(10))
+        .same_site(cookie::SameSite::Lax)
+        .build();
+
+    Ok((jar.add(cookie), url.to_string()))
+}
+
+/// Completes a Google sign-in and issues the same cookies as `login`.
+///
+/// The Google account is matched by its subject first and then by verified email, linking it
+/// to the user on first use. Unknown emails are refused unless auto-provisioning is enabled.
+pub async fn google_callback(
+    jar: CookieJar,
+    pool: Arc<Pool>,
+    mailer: &MailerSender,
+    settings: &crate::SettingsCache,
+    config: Arc<Config>,
+    connection: ConnectionInfo,
+    query: GoogleCallbackQuery,
+) -> Result<(CookieJar, bool), ModuleError> {
+    let expected_state = jar
+        .get(OAUTH_STATE_COOKIE)
+        .map(|cookie| cookie.value().to_string());
+    let jar = jar.remove(Cookie::build(OAUTH_STATE_COOKIE).path("/"));
+
+    if let Some(error) = query.error {
+        tracing::warn!("Google sign-in was not completed: {}", error);
+        return Err(ModuleError::AuthError);
+    }
+    match (expected_state, query.state) {
+        (Some(expected), Some(state)) if expected == state => {}
+        _ => return Err(ModuleError::BadRequest("OAuth state mismatch".into())),
+    }
+    let code = query
+        .code
+        .ok_or(ModuleError::BadRequest("Missing authorization code".into()))?;
+
+    let token = google_oauth::request_token(&code, config.clone())
+        .await
+        .map_err(|e| {
+            tracing::error!("Google token exchange failed: {}", e);
+            ModuleError::AuthError
+        })?;
+    let google_user =
+        google_oauth::get_google_user(&token.access_token, &token.id_token, config.clone())
+            .await
+            .map_err(|e| {
+                tracing::error!("Could not fetch Google user: {}", e);
+                ModuleError::AuthError
+            })?;
+    if !google_user.verified_email {
+        return Err(ModuleError::AuthError);
+    }
+
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|_| ModuleError::InternalError(POOL_ERROR_MSG.into()))?;
+
+    let reg_no_format = settings.read().await.reg_no_format.clone();
+    let user = find_or_link_google_user(
+        &mut conn,
+        &google_user,
+        config.google_oauth_auto_provision,
+        &reg_no_format,
+    )
+    .await?;
+    if !user.can_sign_in() {
+        return Err(ModuleError::PermissionDenied);
+    }
+    if mfa::is_enabled(&mut conn, user.id).await? {
+        return Ok((set_mfa_cookie(jar, create_mfa_token(user.id)?), true));
+    }
+
+    let jar = complete_login(&mut conn, jar, mailer, &connection, &user, false).await?;
+
+    let log = ActivityLog::new(ActivityType::UserLogin, user.id)
+        .set_target_id(user.id)
+        .set_target_type("User".into())
+        .set_details(serde_json::json!({ "provider": GOOGLE_PROVIDER }))
+        .finish();
+    crate::services::activity_logs::emit_log(log, &mut conn).await?;
+
+    Ok((jar, false))
+}
+
+async fn find_or_link_google_user(
+    conn: &mut crate::Connection<'_>,
+    google_user: &google_oauth::GoogleUserResult,
+    auto_provision: bool,
+    reg_no_format: &str,
+) -> Result<UserDto, ModuleError> {
+    let linked = schema::oauth_accounts::table
+        .inner_join(schema::users::table)
+        .filter(schema::oauth_accounts::provider.eq(GOOGLE_PROVIDER))
+        .filter(schema::oauth_accounts::subject.eq(&google_user.id))
+        .select(UserDto::as_select())
+        .first::<UserDto>(conn)
+        .await
+        .optional()?;
+    if let Some(user) = linked {
+        return Ok(user);
+    }
+
+    let existing = schema::users::table
+        .filter(crate::services::lower(schema::users::email).eq(google_user.email.to_lowercase()))
+        .select(UserDto::as_select())
+        .first::<UserDto>(conn)
+        .await
+        .optional()?;
+
+    let user = match existing {
+        Some(user) => user,
+        None if auto_provision => provision_google_user(conn, google_user, reg_no_format).await?,
+        None => return Err(ModuleError::AuthError),
+    };
+
+    let account = OAuthAccount::new(
+        user.id,
+        GOOGLE_PROVIDER,
+        google_user.id.clone(),
+        google_user.email.clone(),
+    );
+    diesel::insert_into(schema::oauth_accounts::table)
+        .values(&account)
+        .execute(conn)
+        .await?;
+
+    Ok(user)
+}
+
+async fn provision_google_user(
+    conn: &mut crate::Connection<'_>,
+    google_user: &google_oauth::GoogleUserResult,
+    reg_no_format: &str,
+) -> Result<UserDto, ModuleError> {
+    // The account can only be used through Google until the user sets a password.
+    let unusable_password = Uuid::new_v4().to_string();
+    let new_user = crate::dto::user::NewUser {
+        first_name: google_user.given_name.clone(),
+        last_name: google_user.family_name.clone(),
+        email: google_user.email.clone(),
+        password: unusable_password,
+        dob: None,
+        year_joined: chrono::Local::now().year().to_string(),
+        is_active: true,
+        role: crate::models::users::Role::User,
+        gender: None,
+        phone: None,
+    };
+    let mut user: crate::models::users::User = new_user.try_into()?;
+    if !google_user.picture.is_empty() {
+        user.avatar_url = Some(google_user.picture.clone());
+    }
+    crate::services::users::insert_user(conn, &mut user, reg_no_format).await?;
+
+    let log = ActivityLog::new(ActivityType::UserCreated, user.id)
+        .set_target_id(user.id)
+        .set_target_type("User".into())
+        .set_details(serde_json::json!({ "provider": GOOGLE_PROVIDER }))
+        .finish();
+    crate::services::activity_logs::emit_log(log, conn).await?;
+
+    let user = schema::users::table
+        .find(user.id)
+        .select(UserDto::as_select())
+        .first::<UserDto>(conn)
+        .await?;
+    Ok(user)
+}
+
+/// Starts a session for an authenticated user, emailing them when the device is new.
+///
+/// A device counts as new when no earlier session of the user had its user agent or its IP,
+/// so a browser update at home or the same laptop on another network does not raise an
+/// alert, while an unknown browser on an unknown network does. Impersonation sessions carry
+/// the admin's device and are left out.
+async fn complete_login(
+    conn: &mut crate::Connection<'_>,
+    jar: CookieJar,
+    mailer: &MailerSender,
+    connection: &ConnectionInfo,
+    user: &UserDto,
+    mfa_verified: bool,
+) -> Result<CookieJar, ModuleError> {
+    let is_known_device = schema::sessions::table
+        .filter(schema::sessions::user_id.eq(user.id))
+        .filter(schema::sessions::impersonator_id.is_null())
+        .filter(
+            schema::sessions::user_agent
+                .eq(&connection.user_agent)
+                .or(schema::sessions::ip_address.eq(&connection.ip)),
+        )
+        .count()
+        .get_result::<i64>(conn)
+        .await?
+        > 0;
+
+    let session = start_session(conn, user.id, connection, mfa_verified).await?;
+    if !is_known_device {
+        dispatch(
+            mailer,
+            MailerEvent::SendSessionStartEmail {
+                name: user.first_name.clone(),
+                to: user.email.clone(),
+                ip_address: connection.ip.clone(),
+                user_agent: connection.user_agent.clone(),
+            },
+        );
+    }
+    let jar = issue_session(
+        conn,
+        jar,
+        user.id,
+        user.role.clone(),
+        session.id,
+        mfa_verified,
+    )
+    .await?;
+
+    diesel::update(schema::users::table.filter(schema::users::id.eq(user.id)))
+        .set(schema::users::last_seen.eq(chrono::Utc::now().naive_utc()))
+        .execute(conn)
+        .await?;
+
+    Ok(jar)
+}
+
+/// Exchanges the `refresh_token` cookie for a fresh pair of tokens.
+///
+/// Refresh tokens are single use: the presented token is marked as used and a new one is
+/// minted in the same family. Presenting a token that was already used means it has been
+/// replayed, so every token in its family is revoked and the caller has to log in again.
+pub async fn refresh(
+    jar: CookieJar,
+    pool: Arc<Pool>,
+    connection: ConnectionInfo,
+) -> Result<CookieJar, ModuleError> {
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|_| ModuleError::InternalError(POOL_ERROR_MSG.into()))?;
+
+    let token = jar
+        .get("refresh_token")
+        .map(|cookie| cookie.value().to_string())
+        .ok_or(ModuleError::CouldNotExtractToken(
+            "Could not extract refresh token.",
+        ))?;
+    let claims = decode_refresh_token(&token)?;
+    let now = chrono::Utc::now().naive_utc();
+
+    // Claim the token atomically so two concurrent refreshes cannot both succeed.
+    let claimed = diesel::update(
+        schema::refresh_tokens::table
+            .filter(schema::refresh_tokens::id.eq(claims.jti))
+            .filter(schema::refresh_tokens::used_at.is_null())
+            .filter(schema::refresh_tokens::revoked_at.is_null())
+            .filter(schema::refresh_tokens::expires_at.gt(now)),
+    )
+    .set(schema::refresh_tokens::used_at.eq(now))
+    .returning(RefreshToken::as_returning())
+    .get_result::<RefreshToken>(&mut conn)
+    .await
+    .optional()?;
+
+    let claimed = match claimed {
+        Some(token) => token,
+        None => {
+            let existing = schema::refresh_tokens::table
+                .find(claims.jti)
+                .select(RefreshToken::as_select())
+                .first::<RefreshToken>(&mut conn)
+                .await
+                .optional()?;
+            if let Some(existing) = existing
+                && existing.used_at.is_some()
+            {
+                tracing::warn!(
+                    "Refresh token reuse detected for user {}, revoking family {}",
+                    existing.user_id,
+                    existing.family_id
+                );
+                revoke_session(&mut conn, existing.family_id).await?;
+                let log = ActivityLog::new(ActivityType::RefreshTokenReused, existing.user_id)
+                    .set_target_id(existing.user_id)
+                    .set_target_type("User".into())
+                    .set_details(serde_json::json!({ "family_id": existing.family_id }))
+                    .finish();
+                crate::services::activity_logs::emit_log(log, &mut conn).await?;
+            }
+            return Err(ModuleError::InvalidToken);
+        }
+    };
+
+    let user = schema::users::table
+        .find(claimed.user_id)
+        .select(UserDto::as_select())
+        .first::<UserDto>(&mut conn)
+        .await
+        .optional()?;
+
+    let session = schema::sessions::table
+        .find(claimed.family_id)
+        .filter(schema::sessions::revoked_at.is_null())
+        .select(Session::as_select())
+        .first::<Session>(&mut conn)
+        .await
+        .optional()?;
+
+    let (user, session) = match (user, session) {
+        (Some(user), Some(session)) if user.can_sign_in() => (user, session),
+        _ => {
+            revoke_session(&mut conn, claimed.family_id).await?;
+            return Err(ModuleError::AuthError);
+        }
+    };
+
+    diesel::update(schema::sessions::table.find(session.id))
+        .set((
+            schema::sessions::expires_at.eq(refresh_expiry()),
+            schema::sessions::last_seen_at.eq(chrono::Utc::now().naive_utc()),
+            schema::sessions::ip_address.eq(connection.ip),
+        ))
+        .execute(&mut conn)
+        .await?;
+
+    issue_session(
+        &mut conn,
+        jar,
+        user.id,
+        user.role,
+        session.id,
+        session.mfa_verified,
+    )
+    .await
+}
+
+/// Revokes the session behind the current cookies and clears them.
+///
+/// The access token is decoded without checking `exp`, so a user whose access token has
+/// already lapsed can still end the session. Missing or invalid cookies are not an error;
+/// the cookies are cleared either way.
+pub async fn logout(jar: CookieJar, pool: Arc<Pool>) -> Result<CookieJar, ModuleError> {
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|_| ModuleError::InternalError(POOL_ERROR_MSG.into()))?;
+
+    let session = jar
+        .get("access_token")
+        .and_then(|cookie| decode_access_token(cookie.value(), false).ok())
+        .map(|claims| (claims.jti, claims.user_id))
+        .or_else(|| {
+            jar.get("refresh_token")
+                .and_then(|cookie| decode_refresh_token(cookie.value()).ok())
+                .map(|claims| (claims.family_id, claims.user_id))
+        });
+
+    if let Some((session_id, user_id)) = session {
+        revoke_session(&mut conn, session_id).await?;
+
+        let log = ActivityLog::new(ActivityType::UserLogout, user_id)
+            .set_target_id(user_id)
+            .set_target_type("User".into())
+            .finish();
+        crate::services::activity_logs::emit_log(log, &mut conn).await?;
+    }
+
+    Ok(clear_auth_cookies(jar))
+}
+
+pub async fn start_session(
+    conn: &mut crate::Connection<'_>,
+    user_id: Uuid,
+    connection: &ConnectionInfo,
+    mfa_verified: bool,
+) -> Result<Session, ModuleError> {
+    let session = Session::new(user_id, refresh_expiry())
+        .set_connection_info(connection)
+        .set_mfa_verified(mfa_verified)
+        .finish();
+    diesel::insert_into(schema::sessions::table)
+        .values(&session)
+        .execute(conn)
+        .await?;
+    Ok(session)
+}
+
+fn refresh_expiry() -> chrono::NaiveDateTime {
+    chrono::Utc::now().naive_utc() + chrono::Duration::hours(REFRESH_TOKEN_TTL_HOURS)
+}
+
+/// Persists a new refresh token for `session_id` and returns the jar with both auth cookies set.
+pub async fn issue_session(
+    conn: &mut crate::Connection<'_>,
+    jar: CookieJar,
+    user_id: Uuid,
+    role: crate::models::users::Role,
+    session_id: Uuid,
+    mfa: bool,
+) -> Result<CookieJar, ModuleError> {
+    let refresh_token = RefreshToken::new(user_id, session_id, refresh_expiry());
+
+    diesel::insert_into(schema::refresh_tokens::table)
+        .values(&refresh_token)
+        .execute(conn)
+        .await?;
+
+    let token = create_session_token(user_id, role, &refresh_token, mfa)?;
+    Ok(set_auth_cookies(jar, token))
+}
+
+/// Revokes a session together with every refresh token rotated under it.
+pub async fn revoke_session(
+    conn: &mut crate::AsyncPgConnection,
+    session_id: Uuid,
+) -> Result<(), ModuleError> {
+    let now = chrono::Utc::now().naive_utc();
+    diesel::update(
+        schema::sessions::table
+            .find(session_id)
+            .filter(schema::sessions::revoked_at.is_null()),
+    )
+    .set(schema::sessions::revoked_at.eq(now))
+    .execute(conn)
+    .await?;
+
+    diesel::update(
+        schema::refresh_tokens::table
+            .filter(schema::refresh_tokens::family_id.eq(session_id))
+            .filter(schema::refresh_tokens::revoked_at.is_null()),
+    )
+    .set(schema::refresh_tokens::revoked_at.eq(now))
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+fn set_auth_cookies(jar: CookieJar, token: AuthBodyDto) -> CookieJar {
+    let cookie = Cookie::build(("access_token", token.access_token))
+        .http_only(true)
+        .secure(true) // ❌❌❌ change this to true for production
+        .path("/")
+        .max_age(cookie::time::Duration::days(7))
+        .same_site(cookie::SameSite::None)
+        .build();
+
+    let refresh_cookie = Cookie::build(("refresh_token", token.refresh_token))
+        .path("/")
+        .http_only(true)
+        .secure(true) // ❌❌❌ change this to true for production
+        .same_site(cookie::SameSite::None)
+        .max_age(cookie::time::Duration::days(8))
+        .build();
+
+    jar.add(cookie).add(refresh_cookie)
+}
+
+fn set_mfa_cookie(jar: CookieJar, token: String) -> CookieJar {
+    let cookie = Cookie::build((MFA_TOKEN_COOKIE, token))
+        .http_only(true)
+        .secure(true)
+        .path("/")
+        .max_age(cookie::time::Duration::minutes(MFA_TOKEN_TTL_MINUTES))
+        .same_site(cookie::SameSite::None)
+        .build();
+    jar.add(cookie)
+}
+
+fn clear_auth_cookies(jar: CookieJar) -> CookieJar {
+    jar.remove(Cookie::build("access_token").path("/"))
+        .remove(Cookie::build("refresh_token").path("/"))
+}
@@ -7,9 +7,11 @@ pub struct Config {
     pub client_origin: String,
     pub token_url: String,
     pub auth_url: String,
+    pub userinfo_url: String,
     pub google_oauth_client_id: String,
     pub google_oauth_client_secret: String,
     pub google_oauth_redirect_url: String, // redirect on your server
+    pub google_oauth_auto_provision: bool, // create accounts for unknown verified emails
 }
 
 impl Config {
@@ -19,17 +21,24 @@ impl Config {
             .unwrap_or("https://www.googleapis.com/oauth2/v4/token".into());
         let auth_url = std::env::var("AUTH_URL")
             .unwrap_or("https://accounts.google.com/o/oauth2/v2/auth".to_string());
+        let userinfo_url = std::env::var("USERINFO_URL")
+            .unwrap_or("https://www.googleapis.com/oauth2/v1/userinfo".to_string());
         let google_oauth_client_id = std::env::var("GOOGLE_OAUTH_CLIENT_ID")?;
         let google_oauth_client_secret = std::env::var("GOOGLE_OAUTH_CLIENT_SECRET")?;
         let google_oauth_redirect_url = std::env::var("GOOGLE_OAUTH_REDIRECT_URL")?;
+        let google_oauth_auto_provision = std::env::var("GOOGLE_OAUTH_AUTO_PROVISION")
+            .map(|value| value == "true")
+            .unwrap_or(false);
 
         Ok(Config {
             client_origin,
             token_url,
             auth_url,
+            userinfo_url,
             google_oauth_client_id,
             google_oauth_client_secret,
             google_oauth_redirect_url,
+            google_oauth_auto_provision,
         })
     }
 }
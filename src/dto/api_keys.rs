@@ -0,0 +1,32 @@
+use super::*;
+use crate::models::permissions::Permission;
+
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct CreateApiKeyRequest {
+    pub name: String,
+    /// Must be permissions the issuing admin's role holds.
+    pub scopes: Vec<Permission>,
+    /// Between 1 and 365.
+    pub expires_in_days: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Selectable, Queryable, utoipa::ToSchema)]
+#[diesel(table_name = crate::schema::api_keys)]
+pub struct ApiKeyDto {
+    pub id: uuid::Uuid,
+    pub name: String,
+    pub prefix: String,
+    pub scopes: Vec<Permission>,
+    pub created_by: uuid::Uuid,
+    pub expires_at: NaiveDateTime,
+    pub last_used_at: Option<NaiveDateTime>,
+    pub revoked_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct CreatedApiKeyDto {
+    /// The full key, sent as `Authorization: Bearer <key>`. It is shown only once.
+    pub key: String,
+    pub api_key: ApiKeyDto,
+}
@@ -53,3 +53,13 @@ pub struct SignAttendanceRequest {
 pub struct AdminSignAttendanceRequest {
     pub attendance_type: AttendanceType,
 }
+
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema, utoipa::IntoParams)]
+pub struct AttendanceExportQuery {
+    /// First day, inclusive.
+    pub from: NaiveDate,
+    /// Last day, inclusive.
+    pub to: NaiveDate,
+    #[serde(default)]
+    pub format: crate::dto::exports::ExportFormat,
+}
@@ -0,0 +1,22 @@
+use super::*;
+use crate::models::custom_fields::{CustomFieldType, FieldVisibility};
+
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct CustomFieldRequest {
+    /// Lowercase letters, digits and underscores, starting with a letter. Fixed once the
+    /// field is created.
+    pub key: String,
+    pub label: String,
+    pub field_type: CustomFieldType,
+    /// The choices of a `select` field.
+    #[serde(default)]
+    pub options: Vec<String>,
+    /// Required fields cannot be cleared, and profile updates that set custom fields must
+    /// fill them.
+    #[serde(default)]
+    pub required: bool,
+    pub visibility: FieldVisibility,
+    /// Fields are listed in ascending position; 0 when left out.
+    #[serde(default)]
+    pub position: i32,
+}
@@ -0,0 +1,34 @@
+use super::*;
+
+/// File format of an export.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, utoipa::ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    /// Excel workbook with typed date cells and a frozen header row.
+    Xlsx,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, utoipa::ToSchema, utoipa::IntoParams)]
+pub struct ExportQuery {
+    #[serde(default)]
+    pub format: ExportFormat,
+}
+
+/// Format of a personal data download.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, utoipa::ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum PersonalDataFormat {
+    /// One JSON file per section.
+    #[default]
+    Zip,
+    /// Every section in a single JSON document.
+    Json,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, utoipa::ToSchema, utoipa::IntoParams)]
+pub struct PersonalDataQuery {
+    #[serde(default)]
+    pub format: PersonalDataFormat,
+}
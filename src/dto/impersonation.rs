@@ -0,0 +1,35 @@
+use super::*;
+
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct StartImpersonationRequest {
+    /// Why the admin needs to see the app as this user. Kept in the activity log.
+    pub reason: String,
+    /// Between 1 and 60. Defaults to 15.
+    pub duration_minutes: Option<i64>,
+    /// Lets the session change data. Without it only GET requests are allowed.
+    #[serde(default)]
+    pub allow_writes: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct ImpersonationDto {
+    /// Sent as `Authorization: Bearer <token>`, leaving the admin's own session untouched.
+    pub access_token: String,
+    pub token_type: String,
+    pub session_id: uuid::Uuid,
+    pub user_id: uuid::Uuid,
+    pub impersonator_id: uuid::Uuid,
+    pub read_only: bool,
+    pub expires_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Selectable, Queryable, utoipa::ToSchema)]
+#[diesel(table_name = crate::schema::sessions)]
+pub struct ImpersonationSessionDto {
+    pub id: uuid::Uuid,
+    pub user_id: uuid::Uuid,
+    pub impersonator_id: Option<uuid::Uuid>,
+    pub ip_address: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+}
@@ -0,0 +1,297 @@
+use std::collections::HashMap;
+
+use super::*;
+use crate::models::import_profiles::{ImportKind, ImportProfile};
+use crate::models::users::{Role, User};
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportMode {
+    /// Validate every row and report what would happen, without writing anything.
+    DryRun,
+    /// Import the valid rows and hand back the rejected ones.
+    #[default]
+    Commit,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, utoipa::ToSchema, utoipa::IntoParams)]
+pub struct ImportUsersQuery {
+    #[serde(default)]
+    pub mode: ImportMode,
+    /// Layout of the file. Without it the Google Form export layout is assumed.
+    pub profile_id: Option<Uuid>,
+    /// Update users the rows match by email or reg_no instead of rejecting those rows.
+    #[serde(default)]
+    pub update_existing: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, utoipa::ToSchema, utoipa::IntoParams)]
+pub struct ImportRosterQuery {
+    /// Layout of the file. Without it the headers are `first_name`, `last_name` and `hall`.
+    pub profile_id: Option<Uuid>,
+}
+
+/// A clean-up step applied to a value read from an import file. Values are always trimmed
+/// first.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ValueTransform {
+    Lowercase,
+    Uppercase,
+    /// Capitalises the first letter of every word and lowercases the rest.
+    TitleCase,
+    StripPrefix(String),
+    StripSuffix(String),
+    Replace {
+        from: String,
+        to: String,
+    },
+}
+
+impl ValueTransform {
+    pub fn apply(&self, value: String) -> String {
+        match self {
+            ValueTransform::Lowercase => value.to_lowercase(),
+            ValueTransform::Uppercase => value.to_uppercase(),
+            ValueTransform::TitleCase => value
+                .split_whitespace()
+                .map(|word| {
+                    let mut chars = word.chars();
+                    match chars.next() {
+                        Some(first) => {
+                            first.to_uppercase().collect::<String>()
+                                + &chars.as_str().to_lowercase()
+                        }
+                        None => String::new(),
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(" "),
+            ValueTransform::StripPrefix(prefix) => value
+                .strip_prefix(prefix.as_str())
+                .map(str::to_string)
+                .unwrap_or(value),
+            ValueTransform::StripSuffix(suffix) => value
+                .strip_suffix(suffix.as_str())
+                .map(str::to_string)
+                .unwrap_or(value),
+            ValueTransform::Replace { from, to } => value.replace(from.as_str(), to),
+        }
+        .trim()
+        .to_string()
+    }
+}
+
+/// When an update import may replace a value an existing user already has. Empty values in
+/// the file never replace anything.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OverwriteRule {
+    /// Whenever the file's value differs.
+    Always,
+    /// Only when the user has no value yet.
+    IfEmpty,
+    Never,
+}
+
+impl OverwriteRule {
+    /// The fields an update import can change. `reg_no` only matches rows to users.
+    pub const UPDATABLE_FIELDS: &'static [&'static str] = &[
+        "first_name",
+        "last_name",
+        "email",
+        "dob",
+        "year_joined",
+        "gender",
+        "phone",
+    ];
+
+    /// The rule of fields a profile leaves out. `year_joined` is kept because the reg_no
+    /// was derived from it.
+    pub fn default_for(field: &str) -> Self {
+        match field {
+            "year_joined" => OverwriteRule::Never,
+            _ => OverwriteRule::Always,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct ImportProfileRequest {
+    pub name: String,
+    pub kind: ImportKind,
+    /// Field name to the header that holds it. Fields left out are read from a header with
+    /// their own name. Headers match case-insensitively.
+    #[serde(default)]
+    pub columns: HashMap<String, String>,
+    /// chrono format such as `%d/%m/%Y`. Detected from the file when unset.
+    pub date_format: Option<String>,
+    /// Field name to the transforms applied to its values, in order.
+    #[serde(default)]
+    pub transforms: HashMap<String, Vec<ValueTransform>>,
+    /// Field name to its rule in update imports. Users only.
+    #[serde(default)]
+    pub overwrite: HashMap<String, OverwriteRule>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct ImportProfileDto {
+    /// Unset for the built-in defaults.
+    pub id: Option<Uuid>,
+    pub name: String,
+    pub kind: ImportKind,
+    pub columns: HashMap<String, String>,
+    pub date_format: Option<String>,
+    pub transforms: HashMap<String, Vec<ValueTransform>>,
+    pub overwrite: HashMap<String, OverwriteRule>,
+    pub created_at: Option<NaiveDateTime>,
+    pub updated_at: Option<NaiveDateTime>,
+}
+
+impl ImportProfileDto {
+    /// The layout assumed when an import names no profile: the Google Form export for users,
+    /// which prefixes the year with "Year " and writes dates as MM/DD/YY, and plain field
+    /// names for rosters.
+    pub fn default_for(kind: ImportKind) -> Self {
+        let transforms = match kind {
+            ImportKind::Users => HashMap::from([(
+                "year_joined".to_string(),
+                vec![ValueTransform::StripPrefix("Year ".to_string())],
+            )]),
+            ImportKind::Roster => HashMap::new(),
+        };
+        Self {
+            id: None,
+            name: "default".to_string(),
+            kind,
+            columns: HashMap::new(),
+            date_format: None,
+            transforms,
+            overwrite: HashMap::new(),
+            created_at: None,
+            updated_at: None,
+        }
+    }
+}
+
+impl ImportProfileDto {
+    pub fn overwrite_rule(&self, field: &str) -> OverwriteRule {
+        self.overwrite
+            .get(field)
+            .copied()
+            .unwrap_or_else(|| OverwriteRule::default_for(field))
+    }
+}
+
+impl TryFrom<ImportProfile> for ImportProfileDto {
+    type Error = ModuleError;
+    fn try_from(value: ImportProfile) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: Some(value.id),
+            name: value.name,
+            kind: value.kind,
+            columns: serde_json::from_value(value.column_map)?,
+            date_format: value.date_format,
+            transforms: serde_json::from_value(value.transforms)?,
+            overwrite: serde_json::from_value(value.overwrite)?,
+            created_at: Some(value.created_at),
+            updated_at: Some(value.updated_at),
+        })
+    }
+}
+
+/// A user as read from one row of an import file.
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct ImportedUserDto {
+    pub first_name: String,
+    pub last_name: String,
+    pub email: String,
+    pub dob: Option<NaiveDateTime>,
+    pub year_joined: String,
+    pub gender: Option<String>,
+    pub phone: Option<String>,
+}
+
+impl ImportedUserDto {
+    pub fn into_user(self, password_hash: String) -> User {
+        let now = chrono::Local::now().naive_local();
+        User {
+            id: Uuid::now_v7(),
+            reg_no: "N/A".to_string(),
+            first_name: self.first_name,
+            last_name: self.last_name,
+            email: self.email,
+            password: password_hash,
+            dob: self.dob,
+            avatar_url: None,
+            created_at: now,
+            updated_at: now,
+            year_joined: self.year_joined,
+            current_roster_hall: None,
+            current_roster_allocation: None,
+            last_seen: Some(now),
+            is_active: true,
+            role: Role::User,
+            device_id: None,
+            username: None,
+            gender: self.gender,
+            phone: self.phone,
+            address: None,
+            city: None,
+            state: None,
+            country: None,
+            hall_derivation: 0,
+            // Imported accounts all start with the same placeholder password.
+            must_change_password: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct ImportRowReport {
+    /// Line of the row in the file; the header is line 1.
+    pub row: usize,
+    /// Unset when the row could not be read at all.
+    pub user: Option<ImportedUserDto>,
+    /// The reg_no the row receives, or the matched user's. Only rows without errors get one.
+    pub reg_no: Option<String>,
+    /// What the row does. Unset on rejected rows.
+    pub action: Option<ImportAction>,
+    /// The existing user the row matched, in update imports.
+    pub user_id: Option<Uuid>,
+    /// Values the row changes on the matched user.
+    pub changes: Vec<FieldChange>,
+    /// Problems that keep the row from being imported.
+    pub errors: Vec<String>,
+    /// Problems the row is imported despite.
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, utoipa::ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportAction {
+    Create,
+    Update,
+    /// Matched a user the row would not change.
+    Unchanged,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct FieldChange {
+    pub field: String,
+    pub before: serde_json::Value,
+    pub after: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct ImportReport {
+    /// The date format the file was read with, detected unless the profile sets one.
+    pub date_format: Option<String>,
+    pub total: usize,
+    pub valid: usize,
+    pub rejected: usize,
+    pub created: usize,
+    pub updated: usize,
+    pub unchanged: usize,
+    pub rows: Vec<ImportRowReport>,
+}
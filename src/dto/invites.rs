@@ -0,0 +1,86 @@
+use super::*;
+use crate::models::users::{Role, User};
+
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct InviteUserRequest {
+    pub first_name: String,
+    pub last_name: String,
+    pub email: String,
+    pub dob: Option<NaiveDateTime>,
+    pub year_joined: String,
+    pub role: Role,
+    pub gender: Option<String>,
+    pub phone: Option<String>,
+}
+
+/// Stored as the password hash of invited users. It is not a valid PHC string, so no
+/// password verifies against it until the invite is accepted.
+const NO_PASSWORD: &str = "!";
+
+impl From<InviteUserRequest> for User {
+    fn from(value: InviteUserRequest) -> Self {
+        User {
+            id: Uuid::now_v7(),
+            reg_no: "N/A".to_string(),
+            first_name: value.first_name,
+            last_name: value.last_name,
+            email: value.email,
+            password: NO_PASSWORD.to_string(),
+            dob: value.dob,
+            avatar_url: None,
+            created_at: chrono::Local::now().naive_local(),
+            updated_at: chrono::Local::now().naive_local(),
+            year_joined: value.year_joined,
+            current_roster_hall: None,
+            current_roster_allocation: None,
+            last_seen: None,
+            is_active: true,
+            role: value.role,
+            device_id: None,
+            username: None,
+            gender: value.gender,
+            phone: value.phone,
+            address: None,
+            city: None,
+            state: None,
+            country: None,
+            hall_derivation: 0,
+            must_change_password: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct AcceptInviteRequest {
+    /// The token from the invitation link.
+    pub token: String,
+    pub password: String,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, utoipa::ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum InviteStatus {
+    Pending,
+    Accepted,
+    Expired,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema, utoipa::IntoParams)]
+pub struct InviteFilter {
+    pub status: Option<InviteStatus>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct InviteDto {
+    pub id: uuid::Uuid,
+    pub user_id: uuid::Uuid,
+    pub email: String,
+    pub first_name: String,
+    pub last_name: String,
+    pub status: InviteStatus,
+    /// None once the admin who sent the invite has been purged.
+    pub invited_by: Option<uuid::Uuid>,
+    pub expires_at: NaiveDateTime,
+    pub accepted_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
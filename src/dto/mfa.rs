@@ -0,0 +1,21 @@
+use super::*;
+
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct MfaSetupDto {
+    /// Base32 secret, for authenticator apps that cannot scan the URI.
+    pub secret: String,
+    /// `otpauth://` provisioning URI, usually rendered as a QR code.
+    pub otpauth_uri: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct MfaCodeRequest {
+    /// A 6-digit TOTP code, or one of the recovery codes.
+    pub code: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct RecoveryCodesDto {
+    /// Shown once; only their hashes are kept.
+    pub recovery_codes: Vec<String>,
+}
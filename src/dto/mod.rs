@@ -1,9 +1,20 @@
 pub mod analytics;
+pub mod api_keys;
 pub mod attendance;
+pub mod custom_fields;
 pub mod events;
+pub mod exports;
+pub mod impersonation;
+pub mod imports;
+pub mod invites;
 pub mod logs;
+pub mod mfa;
 pub mod pagination;
+pub mod permissions;
+pub mod registrations;
 pub mod roster;
+pub mod session;
+pub mod settings;
 pub mod user;
 use super::{schema::*, *};
 //use diesel::sql_types::{Bool, Int8, Nullable, Text, Timestamp, Uuid as SqlUuid};
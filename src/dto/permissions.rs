@@ -0,0 +1,20 @@
+use super::*;
+use crate::models::{permissions::Permission, users::Role};
+
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct RolePermissionsDto {
+    pub role: Role,
+    pub permissions: Vec<Permission>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct PermissionCatalogueDto {
+    /// Every permission a route can require.
+    pub permissions: Vec<Permission>,
+    pub roles: Vec<RolePermissionsDto>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct UpdateRolePermissionsRequest {
+    pub permissions: Vec<Permission>,
+}
@@ -0,0 +1,24 @@
+use super::*;
+use crate::models::registration_applications::ApplicationStatus;
+
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct RegistrationRequest {
+    pub first_name: String,
+    pub last_name: String,
+    pub email: String,
+    pub dob: Option<NaiveDateTime>,
+    pub year_joined: String,
+    pub gender: Option<String>,
+    pub phone: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct RejectApplicationRequest {
+    /// Sent to the applicant in the rejection email.
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema, utoipa::IntoParams)]
+pub struct ApplicationFilter {
+    pub status: Option<ApplicationStatus>,
+}
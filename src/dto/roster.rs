@@ -118,12 +118,13 @@ pub struct RosterAssignmentDto {
     pub hall: Hall,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
-pub struct CsvUserRoster {
-    pub first_name: String,
-    pub last_name: String,
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema, utoipa::IntoParams)]
+pub struct RosterHallExportQuery {
     pub hall: Option<Hall>,
+    #[serde(default)]
+    pub format: crate::dto::exports::ExportFormat,
 }
+
 #[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
 pub struct UpdateUserHallRequest {
     pub user_id: Uuid,
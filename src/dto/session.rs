@@ -0,0 +1,42 @@
+use super::*;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Selectable, Queryable, utoipa::ToSchema)]
+#[diesel(table_name = sessions)]
+pub struct SessionRow {
+    pub id: uuid::Uuid,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub last_seen_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+    pub impersonator_id: Option<uuid::Uuid>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct SessionDto {
+    pub id: uuid::Uuid,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub last_seen_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+    /// Set when an admin opened the session to view the app as the user.
+    pub impersonator_id: Option<uuid::Uuid>,
+    /// True for the session the request was made with.
+    pub current: bool,
+}
+
+impl SessionDto {
+    pub fn from_row(row: SessionRow, current_session: Option<uuid::Uuid>) -> Self {
+        Self {
+            current: current_session == Some(row.id),
+            id: row.id,
+            ip_address: row.ip_address,
+            user_agent: row.user_agent,
+            created_at: row.created_at,
+            last_seen_at: row.last_seen_at,
+            expires_at: row.expires_at,
+            impersonator_id: row.impersonator_id,
+        }
+    }
+}
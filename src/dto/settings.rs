@@ -0,0 +1,43 @@
+use super::*;
+
+pub const DEFAULT_REG_NO_FORMAT: &str = "{year}/KUD/{seq:03}";
+
+/// Organisation-wide settings. Each field is stored as its own row in `settings`; fields
+/// without a row take their default.
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(default)]
+pub struct OrganisationSettings {
+    /// Admin and Technical accounts must pass TOTP to use their privileged routes.
+    pub mfa_required_for_privileged_roles: bool,
+    /// Shortest password a user can pick.
+    pub password_min_length: usize,
+    /// Refuse passwords from the built-in list of commonly breached passwords.
+    pub password_reject_common: bool,
+    /// Template for new reg_nos; see `User::format_reg_no`.
+    pub reg_no_format: String,
+}
+
+impl Default for OrganisationSettings {
+    fn default() -> Self {
+        Self {
+            mfa_required_for_privileged_roles: false,
+            password_min_length: 8,
+            password_reject_common: true,
+            reg_no_format: DEFAULT_REG_NO_FORMAT.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct UpdateSettingsRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mfa_required_for_privileged_roles: Option<bool>,
+    /// Between 8 and 128.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub password_min_length: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub password_reject_common: Option<bool>,
+    /// Needs `{seq}` or `{seq:0N}`, and `{year}` or `{yy}` since codes restart every year.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reg_no_format: Option<String>,
+}
@@ -1,5 +1,3 @@
-use chrono::NaiveDate;
-
 use super::*;
 use crate::models::users::*;
 
@@ -31,6 +29,48 @@ pub struct UserDto {
     pub country: Option<String>,
     pub is_active: bool,
     pub device_id: Option<String>,
+    /// When true, the client should send the user to change their password; every other
+    /// protected route answers `403` until they do.
+    pub must_change_password: bool,
+    /// Set once the user is deleted. Deleted users keep their history but are left out of
+    /// lists, sign-in and allocation until restored.
+    pub deleted_at: Option<NaiveDateTime>,
+    pub deleted_by: Option<uuid::Uuid>,
+    /// Set once the user's personal data has been scrubbed. Anonymised users stay deleted.
+    pub anonymised_at: Option<NaiveDateTime>,
+}
+
+impl UserDto {
+    /// Whether the account may hold a session.
+    pub fn can_sign_in(&self) -> bool {
+        self.is_active && self.deleted_at.is_none()
+    }
+}
+
+/// Somebody to call when something happens to the user.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, utoipa::ToSchema)]
+pub struct EmergencyContact {
+    pub name: String,
+    /// How they are related to the user, such as "Mother" or "Flatmate".
+    pub relationship: String,
+    pub phone: String,
+    pub email: Option<String>,
+    /// At most one contact is the next of kin.
+    #[serde(default)]
+    pub next_of_kin: bool,
+}
+
+/// A user with their custom fields and emergency contacts, trimmed to what the viewer may see.
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct UserProfileDto {
+    #[serde(flatten)]
+    pub user: UserDto,
+    /// Custom field key to value.
+    #[schema(value_type = Object)]
+    pub custom_fields: serde_json::Map<String, serde_json::Value>,
+    /// Left out unless the viewer is the user or a user manager.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub emergency_contacts: Option<Vec<EmergencyContact>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
@@ -77,6 +117,7 @@ impl TryFrom<NewUser> for User {
             state: None,
             country: None,
             hall_derivation: 0,
+            must_change_password: false,
         })
     }
 }
@@ -98,6 +139,12 @@ pub struct UpdateUserRequest {
     pub city: Option<String>,
     pub state: Option<String>,
     pub country: Option<String>,
+    /// Custom field key to its new value; `null` or `""` clears the field. Fields left out
+    /// keep their value. Admin-only fields cannot be set here.
+    #[schema(value_type = Option<Object>)]
+    pub custom_fields: Option<serde_json::Map<String, serde_json::Value>>,
+    /// Replaces every emergency contact; at most five.
+    pub emergency_contacts: Option<Vec<EmergencyContact>>,
 }
 
 impl UpdateUserRequest {
@@ -111,6 +158,8 @@ impl UpdateUserRequest {
             && self.city.is_none()
             && self.state.is_none()
             && self.country.is_none()
+            && self.custom_fields.is_none()
+            && self.emergency_contacts.is_none()
     }
 }
 
@@ -124,11 +173,174 @@ pub struct UserFilter {
     pub page: Option<i64>,
     pub limit: Option<i64>,
     pub search: Option<String>,
+    /// When true, lists the deleted users instead of the current ones.
+    pub deleted: Option<bool>,
+    pub role: Option<Role>,
+    /// Matched without regard to case.
+    pub gender: Option<String>,
+    pub year_joined: Option<String>,
+    pub is_active: Option<bool>,
+    /// The hall of the user's current roster allocation.
+    pub hall: Option<crate::models::roster::Hall>,
+    /// Seen on or after this day.
+    pub last_seen_from: Option<chrono::NaiveDate>,
+    /// Seen on or before this day.
+    pub last_seen_to: Option<chrono::NaiveDate>,
+    /// Comma-separated fields the user has no value for: `phone`, `dob`, `gender`, `address`,
+    /// `avatar`, `hall`, `last_seen`, `emergency_contact` or the key of a custom field.
+    pub missing: Option<String>,
+    /// Comma-separated `key:value` pairs of custom fields. Text and select values match
+    /// without regard to case.
+    pub custom: Option<String>,
+    /// Comma-separated sort keys, applied in order; prefix one with `-` to sort descending.
+    /// Keys: `first_name`, `last_name`, `reg_no`, `year_joined`, `created_at`, `last_seen`, `dob`.
+    pub sort: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UserSortKey {
+    FirstName,
+    LastName,
+    RegNo,
+    YearJoined,
+    CreatedAt,
+    LastSeen,
+    Dob,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MissingField {
+    Phone,
+    Dob,
+    Gender,
+    Address,
+    Avatar,
+    Hall,
+    LastSeen,
+    EmergencyContact,
+    /// The key of a custom field.
+    Custom(String),
+}
+
+/// Splits a comma-separated parameter, skipping blanks.
+fn list(value: &Option<String>) -> impl Iterator<Item = &str> {
+    value
+        .as_deref()
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+}
+
+impl UserFilter {
+    /// The sort keys in order, each with whether it sorts descending.
+    pub fn sort_keys(&self) -> Result<Vec<(UserSortKey, bool)>, ModuleError> {
+        list(&self.sort)
+            .map(|item| {
+                let (name, descending) = match item.strip_prefix('-') {
+                    Some(name) => (name, true),
+                    None => (item, false),
+                };
+                let key = match name {
+                    "first_name" => UserSortKey::FirstName,
+                    "last_name" => UserSortKey::LastName,
+                    "reg_no" => UserSortKey::RegNo,
+                    "year_joined" => UserSortKey::YearJoined,
+                    "created_at" => UserSortKey::CreatedAt,
+                    "last_seen" => UserSortKey::LastSeen,
+                    "dob" => UserSortKey::Dob,
+                    _ => {
+                        return Err(ModuleError::BadRequest(
+                            format!("Cannot sort users by '{name}'").into(),
+                        ));
+                    }
+                };
+                Ok((key, descending))
+            })
+            .collect()
+    }
+
+    /// The fields of `missing`; `fields` are the custom field definitions.
+    pub fn missing_fields(
+        &self,
+        fields: &[crate::models::custom_fields::CustomField],
+    ) -> Result<Vec<MissingField>, ModuleError> {
+        list(&self.missing)
+            .map(|name| match name {
+                "phone" => Ok(MissingField::Phone),
+                "dob" => Ok(MissingField::Dob),
+                "gender" => Ok(MissingField::Gender),
+                "address" => Ok(MissingField::Address),
+                "avatar" => Ok(MissingField::Avatar),
+                "hall" => Ok(MissingField::Hall),
+                "last_seen" => Ok(MissingField::LastSeen),
+                "emergency_contact" => Ok(MissingField::EmergencyContact),
+                _ if fields.iter().any(|field| field.key == name) => {
+                    Ok(MissingField::Custom(name.to_string()))
+                }
+                _ => Err(ModuleError::BadRequest(
+                    format!("Unknown field '{name}' in missing").into(),
+                )),
+            })
+            .collect()
+    }
+
+    /// The `key:value` pairs of `custom`, unchecked.
+    pub fn custom_values(&self) -> Result<Vec<(&str, &str)>, ModuleError> {
+        list(&self.custom)
+            .map(|item| {
+                item.split_once(':')
+                    .map(|(key, value)| (key.trim(), value.trim()))
+                    .ok_or(ModuleError::BadRequest(
+                        format!("Custom filter '{item}' is not key:value").into(),
+                    ))
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema, utoipa::IntoParams)]
+pub struct TypeaheadQuery {
+    /// The start of a reg_no, first name, last name or full name.
+    pub q: String,
+    /// At most 25; 10 when left out.
+    pub limit: Option<i64>,
+}
+
+/// Everything kept about one user, as handed to them on request.
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct PersonalDataExport {
+    pub generated_at: NaiveDateTime,
+    pub profile: UserProfileDto,
+    /// Oldest first.
+    pub attendance: Vec<crate::dto::attendance::UserAttendanceDto>,
+    /// Newest first.
+    pub rosters: Vec<crate::dto::roster::UserRosterHistoryDto>,
+    /// What the user did and what was done to their account, oldest first.
+    pub activity: Vec<crate::models::activity_logs::ActivityLog>,
+}
+
+/// Just enough of a user to pick them out of a list.
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Selectable, utoipa::ToSchema)]
+#[diesel(table_name = users)]
+pub struct UserSuggestion {
+    pub id: uuid::Uuid,
+    pub reg_no: String,
+    pub first_name: String,
+    pub last_name: String,
+    pub avatar_url: Option<String>,
+    pub current_roster_hall: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default, utoipa::ToSchema)]
 pub struct ChangePasswordRequest {
-    pub email: String,
+    pub current_password: String,
+    pub password: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, utoipa::ToSchema)]
+pub struct SetPasswordRequest {
+    /// The user has to replace it with one of their own at their next sign-in.
     pub password: String,
 }
 
@@ -146,49 +358,6 @@ pub struct NewUserAdmin {
     pub phone: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct CsvUser {
-    pub timestamp: String,
-    pub first_name: String,
-    pub last_name: String,
-    pub email: String,
-    pub username: String,
-    pub dob: String,
-    pub year_joined: String,
-    pub gender: String,
-    pub phone: String,
-}
-
-impl CsvUser {
-    pub fn validate(&mut self) {
-        let cleaned_year = self.year_joined.replace("Year ", "").trim().to_string();
-        if cleaned_year.parse::<i32>().is_ok() {
-            self.year_joined = cleaned_year;
-        } else {
-            self.year_joined = "2025".to_string();
-        }
-    }
-
-    pub fn to_new_user(&self) -> Result<NewUser, ModuleError> {
-        let dob = self.dob.clone();
-        let dob = NaiveDate::parse_from_str(&dob, "%m/%d/%y")
-            .map_err(|_| ModuleError::Error("Invalid date format".to_string().into()))?;
-        let dob = NaiveDateTime::from(dob);
-        Ok(NewUser {
-            first_name: self.first_name.clone().trim().to_string(),
-            last_name: self.last_name.clone().trim().to_string(),
-            email: self.email.clone().to_lowercase().trim().to_string(),
-            password: "password".to_string(),
-            dob: Some(dob),
-            year_joined: self.year_joined.clone(),
-            is_active: true,
-            role: Role::User,
-            gender: Some(self.gender.clone()),
-            phone: Some(self.phone.clone()),
-        })
-    }
-}
-
 #[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
 pub struct AdminUpdateUserRequest {
     pub first_name: Option<String>,
@@ -204,6 +373,12 @@ pub struct AdminUpdateUserRequest {
     pub role: Option<Role>,
     pub year_joined: Option<String>,
     pub password: Option<String>,
+    /// Custom field key to its new value; `null` or `""` clears the field. Fields left out
+    /// keep their value.
+    #[schema(value_type = Option<Object>)]
+    pub custom_fields: Option<serde_json::Map<String, serde_json::Value>>,
+    /// Replaces every emergency contact; at most five.
+    pub emergency_contacts: Option<Vec<EmergencyContact>>,
 }
 
 impl AdminUpdateUserRequest {
@@ -219,5 +394,186 @@ impl AdminUpdateUserRequest {
             && self.country.is_none()
             && self.email.is_none()
             && self.role.is_none()
+            && self.custom_fields.is_none()
+            && self.emergency_contacts.is_none()
+    }
+}
+
+/// A stored avatar. Every size is a square JPEG; `avatar_url` is the largest and is also what
+/// `UserDto.avatar_url` holds.
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct AvatarDto {
+    pub avatar_url: String,
+    pub thumbnails: Vec<AvatarThumbnail>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct AvatarThumbnail {
+    /// Width and height in pixels.
+    pub size: u32,
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct RenumberRequest {
+    /// The `year_joined` whose users are renumbered.
+    pub year: String,
+    /// Template to renumber with; the `reg_no_format` setting when left out.
+    pub format: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct RegNoChange {
+    pub user_id: uuid::Uuid,
+    pub first_name: String,
+    pub last_name: String,
+    pub before: String,
+    pub after: String,
+}
+
+/// What a renumbering does, or did: users of the year get codes 1, 2, 3… in the order they
+/// were created.
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct RenumberReport {
+    pub year: String,
+    pub format: String,
+    pub changes: Vec<RegNoChange>,
+    /// Users whose reg_no already matches.
+    pub unchanged: usize,
+}
+
+/// What a bulk request does to each selected user.
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BulkUserAction {
+    Activate,
+    Deactivate,
+    ChangeRole {
+        role: Role,
+    },
+    ResetDevice,
+    /// Moves users to another hall in the active roster.
+    ChangeHall {
+        hall: crate::models::roster::Hall,
+    },
+    /// Soft delete, as Delete User.
+    Delete,
+}
+
+/// Pick the users with exactly one of `user_ids` or `filter`. The filter's `page`, `limit`
+/// and `sort` are ignored.
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct BulkUserRequest {
+    pub user_ids: Option<Vec<uuid::Uuid>>,
+    pub filter: Option<UserFilter>,
+    pub action: BulkUserAction,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BulkOutcome {
+    Done,
+    /// The user was already in the requested state.
+    Unchanged,
+    /// The action does not apply to this user; `message` says why.
+    Skipped,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct BulkUserResult {
+    pub user_id: uuid::Uuid,
+    pub outcome: BulkOutcome,
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct BulkUserReport {
+    pub action: BulkUserAction,
+    pub done: usize,
+    pub unchanged: usize,
+    pub skipped: usize,
+    pub results: Vec<BulkUserResult>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::custom_fields::{CustomField, CustomFieldType, FieldVisibility};
+
+    fn filter(sort: Option<&str>, missing: Option<&str>) -> UserFilter {
+        UserFilter {
+            sort: sort.map(str::to_string),
+            missing: missing.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    fn custom_field(key: &str) -> CustomField {
+        let now = chrono::Local::now().naive_local();
+        CustomField {
+            id: uuid::Uuid::nil(),
+            key: key.to_string(),
+            label: key.to_string(),
+            field_type: CustomFieldType::Text,
+            options: vec![],
+            required: false,
+            visibility: FieldVisibility::Public,
+            position: 0,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    #[test]
+    fn sort_keys_keep_order_and_direction() {
+        let keys = filter(Some("last_name, -year_joined,,first_name"), None)
+            .sort_keys()
+            .unwrap();
+        assert_eq!(
+            keys,
+            vec![
+                (UserSortKey::LastName, false),
+                (UserSortKey::YearJoined, true),
+                (UserSortKey::FirstName, false),
+            ]
+        );
+        assert!(filter(None, None).sort_keys().unwrap().is_empty());
+    }
+
+    #[test]
+    fn sort_keys_reject_unknown_keys() {
+        assert!(filter(Some("reg_no,password"), None).sort_keys().is_err());
+        assert!(filter(Some("--dob"), None).sort_keys().is_err());
+    }
+
+    #[test]
+    fn missing_fields_resolve_builtin_and_custom_fields() {
+        let fields = [custom_field("department")];
+        let missing = filter(None, Some("phone,department, emergency_contact"))
+            .missing_fields(&fields)
+            .unwrap();
+        assert_eq!(
+            missing,
+            vec![
+                MissingField::Phone,
+                MissingField::Custom("department".into()),
+                MissingField::EmergencyContact,
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_fields_reject_unknown_fields() {
+        let fields = [custom_field("department")];
+        assert!(
+            filter(None, Some("phone,shoe_size"))
+                .missing_fields(&fields)
+                .is_err()
+        );
+        assert!(
+            filter(None, Some("department"))
+                .missing_fields(&[])
+                .is_err()
+        );
     }
 }
@@ -69,6 +69,18 @@ pub enum ModuleError {
 
     #[error("{0}")]
     ResourceNotFound(Cow<'static, str>),
+
+    #[error("{0}")]
+    TooManyRequests(Cow<'static, str>),
+
+    #[error("Two-factor authentication is required for your role")]
+    MfaRequired,
+
+    #[error("You must change your password before continuing")]
+    PasswordChangeRequired,
+
+    #[error("This impersonation session is read-only")]
+    ImpersonationReadOnly,
 }
 
 #[derive(Debug, Default, Error, Serialize, Deserialize)]
@@ -99,7 +111,10 @@ impl IntoResponse for ModuleError {
                 let message = ErrorMessage::default().build(self.to_string(), 401);
                 (axum::http::StatusCode::UNAUTHORIZED, axum::Json(message)).into_response()
             }
-            Self::PermissionDenied => {
+            Self::PermissionDenied
+            | Self::MfaRequired
+            | Self::PasswordChangeRequired
+            | Self::ImpersonationReadOnly => {
                 let message = ErrorMessage::default().build(self.to_string(), 403);
                 (axum::http::StatusCode::FORBIDDEN, axum::Json(message)).into_response()
             }
@@ -123,6 +138,14 @@ impl IntoResponse for ModuleError {
                 let message = ErrorMessage::default().build(self.to_string(), 404);
                 (axum::http::StatusCode::NOT_FOUND, axum::Json(message)).into_response()
             }
+            Self::TooManyRequests(_) => {
+                let message = ErrorMessage::default().build(self.to_string(), 429);
+                (
+                    axum::http::StatusCode::TOO_MANY_REQUESTS,
+                    axum::Json(message),
+                )
+                    .into_response()
+            }
             _ => {
                 let message = ErrorMessage::default().build(self.to_string(), 500);
                 (
@@ -15,8 +15,8 @@ pub fn analytics_routes(state: Arc<AppState>) -> Router {
         .route("/users-on-day", get(get_users_present_on_day))
         .route("/attendance-rates", get(get_attendance_rates))
         .layer(ServiceBuilder::new().layer(middleware::from_fn_with_state(
-            state.clone(),
-            crate::auth::middleware::admin_authorize,
+            (state.clone(), Permission::AnalyticsRead),
+            crate::auth::middleware::require_permission,
         )))
         .route("/users-on-day/{date}", get(get_users_present_on_day))
         .route("/user-attendance/{id}", get(get_user_attendance))
@@ -0,0 +1,96 @@
+use super::*;
+use crate::dto::api_keys::*;
+use crate::dto::*;
+
+pub fn routes(state: Arc<AppState>) -> Router {
+    let routes = api_key_routes(state.clone());
+    let api = Router::new().nest("/api-keys", routes);
+    Router::new().merge(api)
+}
+
+pub fn api_key_routes(state: Arc<AppState>) -> Router {
+    Router::new()
+        .route("/", get(get_api_keys).post(create_api_key))
+        .route("/{id}", delete(revoke_api_key))
+        .layer(ServiceBuilder::new().layer(middleware::from_fn_with_state(
+            (state.clone(), Permission::ApiKeysManage),
+            crate::auth::middleware::require_permission,
+        )))
+        .with_state(state)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/api-keys/",
+    responses(
+        (status = 200, description = "Every API key, newest first", body = Vec<ApiKeyDto>)
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn get_api_keys(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<ApiKeyDto>>, ModuleError> {
+    let response = services::api_keys::get_api_keys(state.pool.clone()).await?;
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/api-keys/",
+    request_body = CreateApiKeyRequest,
+    responses(
+        (status = 200, description = "API key created; the key is only shown here", body = CreatedApiKeyDto),
+        (status = 400, description = "Bad request"),
+        (status = 403, description = "A scope the issuer's role does not hold")
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn create_api_key(
+    Claims {
+        user_id: performer_id,
+        role,
+        ..
+    }: Claims,
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<CreateApiKeyRequest>,
+) -> Result<Json<CreatedApiKeyDto>, ModuleError> {
+    let response = services::api_keys::create_api_key(
+        state.pool.clone(),
+        &state.permissions,
+        role,
+        payload,
+        performer_id,
+    )
+    .await?;
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/api-keys/{id}",
+    params(
+        ("id" = uuid::Uuid, Path, description = "API key ID")
+    ),
+    responses(
+        (status = 200, description = "API key revoked", body = MessageEmpty),
+        (status = 404, description = "API key not found or already revoked")
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn revoke_api_key(
+    Claims {
+        user_id: performer_id,
+        ..
+    }: Claims,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<uuid::Uuid>,
+) -> Result<Json<Message<()>>, ModuleError> {
+    let response = services::api_keys::revoke_api_key(state.pool.clone(), id, performer_id).await?;
+    Ok(Json(response))
+}
@@ -1,7 +1,11 @@
+use axum::Extension;
 use axum_extra::extract::CookieJar;
 
 use super::*;
-use crate::{auth::*, dto::user::UserDto};
+use crate::{
+    auth::jwt::Claims, auth::middleware::ConnectionInfo, auth::*,
+    dto::invites::AcceptInviteRequest, dto::mfa::*, dto::user::UserDto, dto::*,
+};
 
 pub fn routes(state: Arc<AppState>) -> Router {
     let routes = auth_routes(state.clone());
@@ -10,7 +14,21 @@ pub fn routes(state: Arc<AppState>) -> Router {
 }
 
 pub fn auth_routes(state: Arc<AppState>) -> Router {
-    Router::new().route("/login", post(auth)).with_state(state)
+    Router::new()
+        .route("/login", post(auth))
+        .route("/refresh", post(refresh))
+        .route("/logout", post(logout))
+        .route("/forgot-password", post(forgot_password))
+        .route("/reset-password", post(reset_password))
+        .route("/accept-invite", post(accept_invite))
+        .route("/mfa/verify", post(verify_mfa))
+        .route("/mfa/setup", post(setup_mfa))
+        .route("/mfa/enable", post(enable_mfa))
+        .route("/mfa/disable", post(disable_mfa))
+        .route("/mfa/recovery-codes", post(regenerate_recovery_codes))
+        .route("/google/start", get(google_start))
+        .route("/google/callback", get(google_callback))
+        .with_state(state)
 }
 
 #[utoipa::path(
@@ -18,15 +36,298 @@ pub fn auth_routes(state: Arc<AppState>) -> Router {
     path = "/api/v1/auth/login",
     request_body = LoginPayload,
     responses(
-        (status = 200, description = "Login successful", body = UserDto),
-        (status = 401, description = "Unauthorized")
+        (status = 200, description = "Login successful, or a TOTP code is still required", body = LoginResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 429, description = "Too many failed attempts")
     )
 )]
 pub async fn auth(
     jar: CookieJar,
     State(state): State<Arc<AppState>>,
+    Extension(connection): Extension<ConnectionInfo>,
     Json(payload): Json<LoginPayload>,
-) -> Result<(CookieJar, Json<UserDto>), ModuleError> {
-    let response = auth::service::login(jar, state.pool.clone(), payload).await?;
+) -> Result<(CookieJar, Json<LoginResponse>), ModuleError> {
+    let response = auth::service::login(
+        jar,
+        state.pool.clone(),
+        &state.settings,
+        &state.mailer,
+        connection,
+        payload,
+    )
+    .await?;
     Ok((response.0, Json(response.1)))
 }
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/refresh",
+    responses(
+        (status = 200, description = "Session refreshed", body = MessageEmpty),
+        (status = 401, description = "Refresh token is missing, expired, revoked or reused")
+    )
+)]
+pub async fn refresh(
+    jar: CookieJar,
+    State(state): State<Arc<AppState>>,
+    Extension(connection): Extension<ConnectionInfo>,
+) -> Result<(CookieJar, Json<Message<()>>), ModuleError> {
+    let jar = auth::service::refresh(jar, state.pool.clone(), connection).await?;
+    Ok((jar, Json("Session refreshed successfully".into())))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/logout",
+    responses(
+        (status = 200, description = "Logged out and session revoked", body = MessageEmpty)
+    )
+)]
+pub async fn logout(
+    jar: CookieJar,
+    State(state): State<Arc<AppState>>,
+) -> Result<(CookieJar, Json<Message<()>>), ModuleError> {
+    let jar = auth::service::logout(jar, state.pool.clone()).await?;
+    Ok((jar, Json("Logged out successfully".into())))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/auth/google/start",
+    responses(
+        (status = 303, description = "Redirect to the Google consent screen"),
+        (status = 404, description = "Google sign-in is not enabled")
+    )
+)]
+pub async fn google_start(
+    jar: CookieJar,
+    State(state): State<Arc<AppState>>,
+) -> Result<(CookieJar, Redirect), ModuleError> {
+    let config = state.oauth.as_ref().ok_or(ModuleError::ResourceNotFound(
+        "Google sign-in is not enabled".into(),
+    ))?;
+    let (jar, url) = auth::service::google_start(jar, config)?;
+    Ok((jar, Redirect::to(&url)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/auth/google/callback",
+    params(GoogleCallbackQuery),
+    responses(
+        (status = 303, description = "Signed in, or `?mfa_required=true` when a TOTP code is still needed; redirect to the client app"),
+        (status = 400, description = "Missing code or state mismatch"),
+        (status = 401, description = "Google account is not linked to a user"),
+        (status = 403, description = "User is deactivated")
+    )
+)]
+pub async fn google_callback(
+    jar: CookieJar,
+    State(state): State<Arc<AppState>>,
+    Extension(connection): Extension<ConnectionInfo>,
+    Query(query): Query<GoogleCallbackQuery>,
+) -> Result<(CookieJar, Redirect), ModuleError> {
+    let config = state.oauth.clone().ok_or(ModuleError::ResourceNotFound(
+        "Google sign-in is not enabled".into(),
+    ))?;
+    let mut redirect = config.client_origin.clone();
+    let (jar, mfa_required) = auth::service::google_callback(
+        jar,
+        state.pool.clone(),
+        &state.mailer,
+        &state.settings,
+        config,
+        connection,
+        query,
+    )
+    .await?;
+    if mfa_required {
+        redirect.push_str("?mfa_required=true");
+    }
+    Ok((jar, Redirect::to(&redirect)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/forgot-password",
+    request_body = ForgotPasswordRequest,
+    responses(
+        (status = 200, description = "Reset code sent if the account exists", body = MessageEmpty)
+    )
+)]
+pub async fn forgot_password(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<ForgotPasswordRequest>,
+) -> Result<Json<Message<()>>, ModuleError> {
+    let response = services::password_resets::request_password_reset(
+        state.pool.clone(),
+        &state.mailer,
+        payload,
+    )
+    .await?;
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/reset-password",
+    request_body = ResetPasswordRequest,
+    responses(
+        (status = 200, description = "Password reset", body = MessageEmpty),
+        (status = 400, description = "Reset code is invalid or has expired")
+    )
+)]
+pub async fn reset_password(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<ResetPasswordRequest>,
+) -> Result<Json<Message<()>>, ModuleError> {
+    let response = services::password_resets::reset_password(
+        state.pool.clone(),
+        &state.mailer,
+        &state.settings,
+        payload,
+    )
+    .await?;
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/accept-invite",
+    request_body = AcceptInviteRequest,
+    responses(
+        (status = 200, description = "Password set; the user can now log in", body = MessageEmpty),
+        (status = 400, description = "Invite is invalid or has expired")
+    )
+)]
+pub async fn accept_invite(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<AcceptInviteRequest>,
+) -> Result<Json<Message<()>>, ModuleError> {
+    let response = services::invites::accept_invite(
+        state.pool.clone(),
+        &state.mailer,
+        &state.settings,
+        payload,
+    )
+    .await?;
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/mfa/verify",
+    request_body = MfaCodeRequest,
+    responses(
+        (status = 200, description = "Second factor accepted, session started", body = UserDto),
+        (status = 401, description = "Invalid code or missing two-factor token"),
+        (status = 429, description = "Too many failed attempts")
+    )
+)]
+pub async fn verify_mfa(
+    jar: CookieJar,
+    State(state): State<Arc<AppState>>,
+    Extension(connection): Extension<ConnectionInfo>,
+    Json(payload): Json<MfaCodeRequest>,
+) -> Result<(CookieJar, Json<UserDto>), ModuleError> {
+    let (jar, user) = auth::service::verify_mfa_login(
+        jar,
+        state.pool.clone(),
+        &state.mailer,
+        connection,
+        payload,
+    )
+    .await?;
+    Ok((jar, Json(user)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/mfa/setup",
+    responses(
+        (status = 200, description = "New TOTP secret and provisioning URI", body = MfaSetupDto),
+        (status = 400, description = "Two-factor is already enabled")
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn setup_mfa(
+    Claims { user_id, .. }: Claims,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<MfaSetupDto>, ModuleError> {
+    let response = services::mfa::setup(state.pool.clone(), user_id).await?;
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/mfa/enable",
+    request_body = MfaCodeRequest,
+    responses(
+        (status = 200, description = "Two-factor enabled; recovery codes are shown once", body = RecoveryCodesDto),
+        (status = 400, description = "Invalid code or setup not started")
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn enable_mfa(
+    jar: CookieJar,
+    claims: Claims,
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<MfaCodeRequest>,
+) -> Result<(CookieJar, Json<RecoveryCodesDto>), ModuleError> {
+    let response = services::mfa::enable(state.pool.clone(), claims.user_id, payload).await?;
+    let mut conn = state
+        .pool
+        .get()
+        .await
+        .map_err(|_| ModuleError::InternalError(POOL_ERROR_MSG.into()))?;
+    let jar = auth::service::upgrade_session_mfa(&mut conn, jar, &claims).await?;
+    Ok((jar, Json(response)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/mfa/disable",
+    request_body = MfaCodeRequest,
+    responses(
+        (status = 200, description = "Two-factor disabled", body = MessageEmpty),
+        (status = 400, description = "Invalid code, or two-factor is mandatory for the role")
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn disable_mfa(
+    Claims { user_id, role, .. }: Claims,
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<MfaCodeRequest>,
+) -> Result<Json<Message<()>>, ModuleError> {
+    let response =
+        services::mfa::disable(state.pool.clone(), &state.settings, user_id, role, payload).await?;
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/mfa/recovery-codes",
+    request_body = MfaCodeRequest,
+    responses(
+        (status = 200, description = "Previous recovery codes replaced", body = RecoveryCodesDto),
+        (status = 400, description = "Invalid code")
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn regenerate_recovery_codes(
+    Claims { user_id, .. }: Claims,
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<MfaCodeRequest>,
+) -> Result<Json<RecoveryCodesDto>, ModuleError> {
+    let response =
+        services::mfa::regenerate_recovery_codes(state.pool.clone(), user_id, payload).await?;
+    Ok(Json(response))
+}
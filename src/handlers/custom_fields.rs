@@ -0,0 +1,135 @@
+use super::*;
+use crate::dto::custom_fields::*;
+use crate::dto::*;
+use crate::models::custom_fields::CustomField;
+
+pub fn routes(state: Arc<AppState>) -> Router {
+    let routes = custom_field_routes(state.clone());
+    let api = Router::new().nest("/custom-fields", routes);
+    Router::new().merge(api)
+}
+
+/// Everybody signed in can read the definitions to render profile forms; only user managers
+/// can change them.
+pub fn custom_field_routes(state: Arc<AppState>) -> Router {
+    let manage_routes = Router::new()
+        .route("/", post(create_custom_field))
+        .route(
+            "/{id}",
+            put(update_custom_field).delete(delete_custom_field),
+        )
+        .layer(ServiceBuilder::new().layer(middleware::from_fn_with_state(
+            (state.clone(), Permission::UsersManage),
+            crate::auth::middleware::require_permission,
+        )));
+
+    Router::new()
+        .route("/", get(get_custom_fields))
+        .layer(ServiceBuilder::new().layer(middleware::from_fn_with_state(
+            state.clone(),
+            crate::auth::middleware::authorize,
+        )))
+        .merge(manage_routes)
+        .with_state(state)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/custom-fields/",
+    responses(
+        (status = 200, description = "Custom profile fields, in display order", body = Vec<CustomField>)
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn get_custom_fields(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<CustomField>>, ModuleError> {
+    let response = services::custom_fields::get_custom_fields(state.pool.clone()).await?;
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/custom-fields/",
+    request_body = CustomFieldRequest,
+    responses(
+        (status = 200, description = "Custom field created", body = CustomField),
+        (status = 400, description = "Invalid key, label or options, or key already taken")
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn create_custom_field(
+    Claims {
+        user_id: performer_id,
+        ..
+    }: Claims,
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<CustomFieldRequest>,
+) -> Result<Json<CustomField>, ModuleError> {
+    let response =
+        services::custom_fields::create_custom_field(state.pool.clone(), payload, performer_id)
+            .await?;
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/custom-fields/{id}",
+    params(
+        ("id" = uuid::Uuid, Path, description = "Custom field ID")
+    ),
+    request_body = CustomFieldRequest,
+    responses(
+        (status = 200, description = "Custom field replaced", body = CustomField),
+        (status = 400, description = "Invalid field, changed key, or a type or option change while values depend on it"),
+        (status = 404, description = "Custom field not found")
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn update_custom_field(
+    Claims {
+        user_id: performer_id,
+        ..
+    }: Claims,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<uuid::Uuid>,
+    Json(payload): Json<CustomFieldRequest>,
+) -> Result<Json<CustomField>, ModuleError> {
+    let response =
+        services::custom_fields::update_custom_field(state.pool.clone(), id, payload, performer_id)
+            .await?;
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/custom-fields/{id}",
+    params(
+        ("id" = uuid::Uuid, Path, description = "Custom field ID")
+    ),
+    responses(
+        (status = 200, description = "Custom field and every user's value for it deleted", body = MessageEmpty),
+        (status = 404, description = "Custom field not found")
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn delete_custom_field(
+    Claims {
+        user_id: performer_id,
+        ..
+    }: Claims,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<uuid::Uuid>,
+) -> Result<Json<Message<()>>, ModuleError> {
+    let response =
+        services::custom_fields::delete_custom_field(state.pool.clone(), id, performer_id).await?;
+    Ok(Json(response))
+}
@@ -13,19 +13,30 @@ pub fn routes(state: Arc<AppState>) -> Router {
 }
 
 pub fn event_routes(state: Arc<AppState>) -> Router {
-    Router::new()
+    let admin_routes = Router::new()
         .route("/create", post(create_event))
         .route("/update", patch(update_event))
         .route("/delete/{event_id}", delete(delete_event))
         .layer(ServiceBuilder::new().layer(middleware::from_fn_with_state(
-            state.clone(),
-            crate::auth::middleware::admin_authorize,
-        )))
+            (state.clone(), Permission::EventsManage),
+            crate::auth::middleware::require_permission,
+        )));
+
+    // Kiosks check users in with an API key scoped to `attendance.check_in`.
+    let check_in_routes = Router::new()
         .route("/attendance/check-in", post(check_into_event))
         .route(
             "/attendance/check-in-identifier",
             post(check_into_event_with_identifier),
         )
+        .layer(ServiceBuilder::new().layer(middleware::from_fn_with_state(
+            (state.clone(), Permission::AttendanceCheckIn),
+            crate::auth::middleware::authorize_with_api_key,
+        )));
+
+    // Routes that take API keys authenticate in their own middleware, so they are merged
+    // outside `authorize`, which only admits session tokens.
+    Router::new()
         .route("/upcoming", get(get_upcoming_events))
         .route("/past", get(get_past_events))
         .route("/", get(get_events))
@@ -35,6 +46,8 @@ pub fn event_routes(state: Arc<AppState>) -> Router {
             state.clone(),
             crate::auth::middleware::authorize,
         )))
+        .merge(admin_routes)
+        .merge(check_in_routes)
         .with_state(state)
 }
 
@@ -76,16 +89,19 @@ pub async fn update_event(
     )
 )]
 pub async fn check_into_event(
-    Claims {
-        user_id: performer_id,
-        role,
-        ..
-    }: Claims,
+    claims: Claims,
     State(state): State<Arc<AppState>>,
     Json(payload): Json<CheckIntoEventRequest>,
 ) -> Result<Json<Message<()>>, ModuleError> {
-    let response =
-        services::events::check_into_event(state.pool.clone(), payload, role, performer_id).await?;
+    let can_mark_attendance =
+        services::permissions::claims_allow(&state, &claims, Permission::AttendanceMark).await;
+    let response = services::events::check_into_event(
+        state.pool.clone(),
+        payload,
+        can_mark_attendance,
+        claims.user_id,
+    )
+    .await?;
     Ok(Json(response))
 }
 
@@ -102,17 +118,19 @@ pub async fn check_into_event(
     )
 )]
 pub async fn check_into_event_with_identifier(
-    Claims {
-        user_id: performer_id,
-        role,
-        ..
-    }: Claims,
+    claims: Claims,
     State(state): State<Arc<AppState>>,
     Json(payload): Json<crate::dto::events::CheckInWithIdentifierRequest>,
 ) -> Result<Json<Message<()>>, ModuleError> {
-    let response =
-        services::events::check_in_with_identifier(state.pool.clone(), payload, role, performer_id)
-            .await?;
+    let can_mark_attendance =
+        services::permissions::claims_allow(&state, &claims, Permission::AttendanceMark).await;
+    let response = services::events::check_in_with_identifier(
+        state.pool.clone(),
+        payload,
+        can_mark_attendance,
+        claims.user_id,
+    )
+    .await?;
     Ok(Json(response))
 }
 
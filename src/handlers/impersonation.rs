@@ -0,0 +1,111 @@
+use axum::Extension;
+
+use super::*;
+use crate::auth::middleware::ConnectionInfo;
+use crate::dto::impersonation::*;
+use crate::dto::*;
+
+pub fn routes(state: Arc<AppState>) -> Router {
+    let routes = impersonation_routes(state.clone());
+    let api = Router::new().nest("/impersonation", routes);
+    Router::new().merge(api)
+}
+
+pub fn impersonation_routes(state: Arc<AppState>) -> Router {
+    Router::new()
+        .route("/", get(get_active_impersonations))
+        .route("/{id}", post(start_impersonation).delete(end_impersonation))
+        .layer(ServiceBuilder::new().layer(middleware::from_fn_with_state(
+            (state.clone(), Permission::UsersImpersonate),
+            crate::auth::middleware::require_permission,
+        )))
+        .with_state(state)
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/impersonation/{id}",
+    params(
+        ("id" = uuid::Uuid, Path, description = "ID of the user to view the app as")
+    ),
+    request_body = StartImpersonationRequest,
+    responses(
+        (status = 200, description = "Impersonation session opened", body = ImpersonationDto),
+        (status = 400, description = "Bad request, or the user is an admin or deactivated"),
+        (status = 404, description = "User not found")
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn start_impersonation(
+    Claims {
+        user_id: performer_id,
+        mfa,
+        impersonator_id,
+        ..
+    }: Claims,
+    State(state): State<Arc<AppState>>,
+    Extension(connection): Extension<ConnectionInfo>,
+    Path(id): Path<uuid::Uuid>,
+    Json(payload): Json<StartImpersonationRequest>,
+) -> Result<Json<ImpersonationDto>, ModuleError> {
+    // An impersonation session must not be used to open another one.
+    if impersonator_id.is_some() {
+        return Err(ModuleError::PermissionDenied);
+    }
+    let response = services::impersonation::start_impersonation(
+        state.pool.clone(),
+        id,
+        payload,
+        performer_id,
+        mfa,
+        &connection,
+    )
+    .await?;
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/impersonation/",
+    responses(
+        (status = 200, description = "Impersonation sessions still in progress", body = Vec<ImpersonationSessionDto>)
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn get_active_impersonations(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<ImpersonationSessionDto>>, ModuleError> {
+    let response = services::impersonation::list_active_impersonations(state.pool.clone()).await?;
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/impersonation/{id}",
+    params(
+        ("id" = uuid::Uuid, Path, description = "Impersonation session ID")
+    ),
+    responses(
+        (status = 200, description = "Impersonation ended", body = MessageEmpty),
+        (status = 404, description = "Impersonation session not found or already ended")
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn end_impersonation(
+    Claims {
+        user_id: performer_id,
+        ..
+    }: Claims,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<uuid::Uuid>,
+) -> Result<Json<Message<()>>, ModuleError> {
+    let response =
+        services::impersonation::end_impersonation(state.pool.clone(), id, performer_id).await?;
+    Ok(Json(response))
+}
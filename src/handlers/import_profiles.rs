@@ -0,0 +1,129 @@
+use super::*;
+use crate::dto::imports::*;
+use crate::dto::*;
+
+pub fn routes(state: Arc<AppState>) -> Router {
+    let routes = import_profile_routes(state.clone());
+    let api = Router::new().nest("/import-profiles", routes);
+    Router::new().merge(api)
+}
+
+pub fn import_profile_routes(state: Arc<AppState>) -> Router {
+    Router::new()
+        .route("/", get(get_import_profiles).post(create_import_profile))
+        .route(
+            "/{id}",
+            put(update_import_profile).delete(delete_import_profile),
+        )
+        .layer(ServiceBuilder::new().layer(middleware::from_fn_with_state(
+            (state.clone(), Permission::UsersManage),
+            crate::auth::middleware::require_permission,
+        )))
+        .with_state(state)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/import-profiles/",
+    responses(
+        (status = 200, description = "Saved import profiles, by name", body = Vec<ImportProfileDto>)
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn get_import_profiles(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<ImportProfileDto>>, ModuleError> {
+    let response = services::import_profiles::get_import_profiles(state.pool.clone()).await?;
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/import-profiles/",
+    request_body = ImportProfileRequest,
+    responses(
+        (status = 200, description = "Import profile created", body = ImportProfileDto),
+        (status = 400, description = "Unknown field, invalid date format or name already taken")
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn create_import_profile(
+    Claims {
+        user_id: performer_id,
+        ..
+    }: Claims,
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<ImportProfileRequest>,
+) -> Result<Json<ImportProfileDto>, ModuleError> {
+    let response =
+        services::import_profiles::create_import_profile(state.pool.clone(), payload, performer_id)
+            .await?;
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/import-profiles/{id}",
+    params(
+        ("id" = uuid::Uuid, Path, description = "Import profile ID")
+    ),
+    request_body = ImportProfileRequest,
+    responses(
+        (status = 200, description = "Import profile replaced", body = ImportProfileDto),
+        (status = 400, description = "Unknown field, invalid date format or name already taken"),
+        (status = 404, description = "Import profile not found")
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn update_import_profile(
+    Claims {
+        user_id: performer_id,
+        ..
+    }: Claims,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<uuid::Uuid>,
+    Json(payload): Json<ImportProfileRequest>,
+) -> Result<Json<ImportProfileDto>, ModuleError> {
+    let response = services::import_profiles::update_import_profile(
+        state.pool.clone(),
+        id,
+        payload,
+        performer_id,
+    )
+    .await?;
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/import-profiles/{id}",
+    params(
+        ("id" = uuid::Uuid, Path, description = "Import profile ID")
+    ),
+    responses(
+        (status = 200, description = "Import profile deleted", body = MessageEmpty),
+        (status = 404, description = "Import profile not found")
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn delete_import_profile(
+    Claims {
+        user_id: performer_id,
+        ..
+    }: Claims,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<uuid::Uuid>,
+) -> Result<Json<Message<()>>, ModuleError> {
+    let response =
+        services::import_profiles::delete_import_profile(state.pool.clone(), id, performer_id)
+            .await?;
+    Ok(Json(response))
+}
@@ -0,0 +1,70 @@
+use super::*;
+use crate::auth::keys::SigningKeyDto;
+
+pub fn routes(state: Arc<AppState>) -> Router {
+    let routes = key_routes(state.clone());
+    let api = Router::new().nest("/keys", routes);
+    Router::new().merge(api)
+}
+
+pub fn key_routes(state: Arc<AppState>) -> Router {
+    Router::new()
+        .route("/", get(get_signing_keys))
+        .route("/rotate", post(rotate_signing_key))
+        .layer(ServiceBuilder::new().layer(middleware::from_fn_with_state(
+            (state.clone(), Permission::SigningKeysManage),
+            crate::auth::middleware::require_permission,
+        )))
+        .with_state(state)
+}
+
+/// Served from the site root rather than `/api/v1`, where verifiers look for it.
+pub fn well_known_routes() -> Router {
+    Router::new().route("/.well-known/jwks.json", get(jwks))
+}
+
+#[utoipa::path(
+    get,
+    path = "/.well-known/jwks.json",
+    responses(
+        (status = 200, description = "Public keys that verify our tokens, as a JWK set", body = Object)
+    )
+)]
+pub async fn jwks() -> Result<Json<jsonwebtoken::jwk::JwkSet>, ModuleError> {
+    Ok(Json(crate::auth::keys::jwks()?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/keys/",
+    responses(
+        (status = 200, description = "Signing keys that still verify tokens, newest first", body = Vec<SigningKeyDto>)
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn get_signing_keys() -> Result<Json<Vec<SigningKeyDto>>, ModuleError> {
+    Ok(Json(crate::auth::keys::list()?))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/keys/rotate",
+    responses(
+        (status = 200, description = "New signing key; earlier keys keep verifying until their tokens expire", body = SigningKeyDto)
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn rotate_signing_key(
+    Claims {
+        user_id: performer_id,
+        ..
+    }: Claims,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<SigningKeyDto>, ModuleError> {
+    let key = services::signing_keys::rotate_signing_key(state.pool.clone(), performer_id).await?;
+    Ok(Json(key))
+}
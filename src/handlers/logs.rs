@@ -4,6 +4,7 @@ use crate::{
     dto::pagination::{PaginatedResult, PaginationWithContext},
     errors::ModuleError,
     models::activity_logs::{ActivityLog, ActivityLogResponse},
+    models::permissions::Permission,
     services,
 };
 use axum::extract::Query;
@@ -25,6 +26,10 @@ pub fn log_routes(state: Arc<AppState>) -> Router {
     Router::new()
         .route("/", crate::log_route!(get, "/", get_logs))
         .route("/{id}", get(get_user_activity))
+        .layer(axum::middleware::from_fn_with_state(
+            (state.clone(), Permission::LogsRead),
+            crate::auth::middleware::require_permission,
+        ))
         .with_state(state)
 }
 
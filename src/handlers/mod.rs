@@ -1,14 +1,23 @@
 pub mod analytics;
+pub mod api_keys;
 pub mod auth;
+pub mod custom_fields;
 pub mod events;
+pub mod impersonation;
+pub mod import_profiles;
+pub mod keys;
 pub mod logs;
+pub mod permissions;
+pub mod registrations;
 pub mod roster;
+pub mod settings;
 pub mod user_attendance;
 pub mod users;
 
 pub use super::*;
 
 use crate::auth::jwt::Claims;
+use crate::models::permissions::Permission;
 use axum::middleware;
 pub use axum::{
     extract::{Json, Path, Query, State},
@@ -26,4 +35,18 @@ pub fn get_routes(state: Arc<AppState>) -> Router {
         .merge(analytics::routes(state.clone()))
         .merge(logs::routes(state.clone()))
         .merge(roster::routes(state.clone()))
+        .merge(permissions::routes(state.clone()))
+        .merge(settings::routes(state.clone()))
+        .merge(api_keys::routes(state.clone()))
+        .merge(keys::routes(state.clone()))
+        .merge(impersonation::routes(state.clone()))
+        .merge(registrations::routes(state.clone()))
+        .merge(import_profiles::routes(state.clone()))
+        .merge(custom_fields::routes(state.clone()))
+        .layer(middleware::from_fn(
+            crate::auth::middleware::impersonation_middleware,
+        ))
+        .layer(middleware::from_fn(
+            crate::auth::middleware::connection_info_middleware,
+        ))
 }
@@ -0,0 +1,73 @@
+use super::*;
+use crate::dto::permissions::*;
+use crate::dto::*;
+use crate::models::users::Role;
+
+pub fn routes(state: Arc<AppState>) -> Router {
+    let routes = permission_routes(state.clone());
+    let api = Router::new().nest("/permissions", routes);
+    Router::new().merge(api)
+}
+
+pub fn permission_routes(state: Arc<AppState>) -> Router {
+    Router::new()
+        .route("/", get(get_permissions))
+        .route("/{role}", put(update_role_permissions))
+        .layer(ServiceBuilder::new().layer(middleware::from_fn_with_state(
+            (state.clone(), Permission::PermissionsManage),
+            crate::auth::middleware::require_permission,
+        )))
+        .with_state(state)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/permissions/",
+    responses(
+        (status = 200, description = "Permission catalogue and the permissions of each role", body = PermissionCatalogueDto)
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn get_permissions(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<PermissionCatalogueDto>, ModuleError> {
+    let response = services::permissions::get_permissions(&state.permissions).await;
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/permissions/{role}",
+    params(
+        ("role" = Role, Path, description = "Role to update")
+    ),
+    request_body = UpdateRolePermissionsRequest,
+    responses(
+        (status = 200, description = "Role permissions replaced", body = MessageEmpty),
+        (status = 400, description = "Admin would lose permissions.manage")
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn update_role_permissions(
+    Claims {
+        user_id: performer_id,
+        ..
+    }: Claims,
+    State(state): State<Arc<AppState>>,
+    Path(role): Path<Role>,
+    Json(payload): Json<UpdateRolePermissionsRequest>,
+) -> Result<Json<Message<()>>, ModuleError> {
+    let response = services::permissions::update_role_permissions(
+        state.pool.clone(),
+        &state.permissions,
+        role,
+        payload,
+        performer_id,
+    )
+    .await?;
+    Ok(Json(response))
+}
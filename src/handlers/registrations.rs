@@ -0,0 +1,140 @@
+use super::*;
+use crate::auth::middleware::ConnectionInfo;
+use crate::dto::registrations::*;
+use crate::dto::*;
+use crate::models::registration_applications::RegistrationApplication;
+use axum::Extension;
+
+pub fn routes(state: Arc<AppState>) -> Router {
+    let routes = registration_routes(state.clone());
+    let api = Router::new().nest("/registrations", routes);
+    Router::new().merge(api)
+}
+
+pub fn registration_routes(state: Arc<AppState>) -> Router {
+    let admin_routes = Router::new()
+        .route("/", get(get_applications))
+        .route("/{id}/approve", post(approve_application))
+        .route("/{id}/reject", post(reject_application))
+        .layer(ServiceBuilder::new().layer(middleware::from_fn_with_state(
+            (state.clone(), Permission::UsersManage),
+            crate::auth::middleware::require_permission,
+        )));
+
+    Router::new()
+        .route("/apply", post(submit_application))
+        .merge(admin_routes)
+        .with_state(state)
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/registrations/apply",
+    request_body = RegistrationRequest,
+    responses(
+        (status = 200, description = "Application queued for review, or dropped because the email already has an account or pending application", body = MessageEmpty),
+        (status = 400, description = "Missing name or email, or an invalid year"),
+        (status = 429, description = "Too many applications from this IP")
+    )
+)]
+pub async fn submit_application(
+    State(state): State<Arc<AppState>>,
+    Extension(connection): Extension<ConnectionInfo>,
+    Json(payload): Json<RegistrationRequest>,
+) -> Result<Json<Message<()>>, ModuleError> {
+    let response =
+        services::registrations::submit_application(state.pool.clone(), connection, payload)
+            .await?;
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/registrations/",
+    params(
+        ApplicationFilter
+    ),
+    responses(
+        (status = 200, description = "Registration applications, newest first", body = [RegistrationApplication])
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn get_applications(
+    State(state): State<Arc<AppState>>,
+    Query(filter): Query<ApplicationFilter>,
+) -> Result<Json<Vec<RegistrationApplication>>, ModuleError> {
+    let response = services::registrations::get_applications(state.pool.clone(), filter).await?;
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/registrations/{id}/approve",
+    params(
+        ("id" = uuid::Uuid, Path, description = "Application ID")
+    ),
+    responses(
+        (status = 200, description = "User created and emailed a link to set their password", body = MessageEmpty),
+        (status = 400, description = "A user with this email already exists"),
+        (status = 404, description = "Application not found or already reviewed")
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn approve_application(
+    Claims {
+        user_id: performer_id,
+        ..
+    }: Claims,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<uuid::Uuid>,
+) -> Result<Json<Message<()>>, ModuleError> {
+    let response = services::registrations::approve_application(
+        state.pool.clone(),
+        &state.mailer,
+        &state.settings,
+        id,
+        performer_id,
+    )
+    .await?;
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/registrations/{id}/reject",
+    params(
+        ("id" = uuid::Uuid, Path, description = "Application ID")
+    ),
+    request_body = RejectApplicationRequest,
+    responses(
+        (status = 200, description = "Application rejected and the applicant notified", body = MessageEmpty),
+        (status = 400, description = "Bad request"),
+        (status = 404, description = "Application not found or already reviewed")
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn reject_application(
+    Claims {
+        user_id: performer_id,
+        ..
+    }: Claims,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<uuid::Uuid>,
+    Json(payload): Json<RejectApplicationRequest>,
+) -> Result<Json<Message<()>>, ModuleError> {
+    let response = services::registrations::reject_application(
+        state.pool.clone(),
+        &state.mailer,
+        id,
+        payload,
+        performer_id,
+    )
+    .await?;
+    Ok(Json(response))
+}
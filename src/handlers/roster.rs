@@ -1,8 +1,8 @@
-use std::collections::HashMap;
-
 use axum::extract::Multipart;
 
 use super::*;
+use crate::dto::exports::ExportQuery;
+use crate::dto::imports::ImportRosterQuery;
 use crate::dto::roster::*;
 use crate::dto::*;
 use crate::models::roster::{Hall, Roster};
@@ -14,14 +14,20 @@ pub fn routes(state: Arc<AppState>) -> Router {
 }
 
 pub fn user_routes(state: Arc<AppState>) -> Router {
+    let activation_routes = Router::new()
+        .route("/activate/{id}", patch(activate_roster))
+        .route("/activate-gendered/{id}", patch(activate_roster_gendered))
+        .route("/share/{id}", patch(share_roster))
+        .layer(ServiceBuilder::new().layer(middleware::from_fn_with_state(
+            (state.clone(), Permission::RosterActivate),
+            crate::auth::middleware::require_permission,
+        )));
+
     Router::new()
         .route("/create", post(create_roster))
         .route("/{id}", get(get_roster))
         .route("/update", patch(update_roster))
         .route("/all", get(get_all_rosters))
-        .route("/activate/{id}", patch(activate_roster))
-        .route("/activate-gendered/{id}", patch(activate_roster_gendered))
-        .route("/share/{id}", patch(share_roster))
         .route("/{id}", delete(delete_roster))
         .route("/{id}/assignments", get(view_roster_assignments))
         .route("/export/{id}", get(export_roster))
@@ -33,9 +39,10 @@ pub fn user_routes(state: Arc<AppState>) -> Router {
         .route("/add-user", post(add_user_to_roster))
         .route("/history/{user_id}", get(get_user_roster_history))
         .layer(ServiceBuilder::new().layer(middleware::from_fn_with_state(
-            state.clone(),
-            crate::auth::middleware::admin_authorize,
+            (state.clone(), Permission::RosterManage),
+            crate::auth::middleware::require_permission,
         )))
+        .merge(activation_routes)
         // .layer(ServiceBuilder::new().layer(middleware::from_fn_with_state(
         //     state.clone(),
         //     crate::auth::middleware::authorize,
@@ -226,21 +233,22 @@ pub async fn view_roster_assignments(
 pub async fn export_roster(
     Path(id): Path<uuid::Uuid>,
     State(state): State<Arc<AppState>>,
+    Query(query): Query<ExportQuery>,
 ) -> Result<(axum::http::HeaderMap, Vec<u8>), ModuleError> {
-    let response = services::roster::export_roster(id, state.pool.clone()).await?;
+    let response = services::roster::export_roster(id, state.pool.clone(), query.format).await?;
     Ok(response)
 }
 
 pub async fn export_roster_by_hall(
     Path(id): Path<uuid::Uuid>,
-    Query(hall): Query<HashMap<String, Hall>>,
+    Query(query): Query<RosterHallExportQuery>,
     State(state): State<Arc<AppState>>,
 ) -> Result<(axum::http::HeaderMap, Vec<u8>), ModuleError> {
-    let hall = hall
-        .get("hall")
+    let hall = query
+        .hall
         .ok_or(ModuleError::BadRequest("Hall not found".into()))?;
     let response =
-        services::roster::export_roster_by_hall(id, state.pool.clone(), hall.clone()).await?;
+        services::roster::export_roster_by_hall(id, state.pool.clone(), hall, query.format).await?;
     Ok(response)
 }
 
@@ -248,14 +256,18 @@ pub async fn import_roster(
     Claims { user_id, .. }: Claims,
     State(state): State<Arc<AppState>>,
     Path(roster_id): Path<uuid::Uuid>,
+    Query(query): Query<ImportRosterQuery>,
     multipart: Multipart,
 ) -> Result<Json<Message<()>>, ModuleError> {
-    let response =
-        services::roster::import_roster(state.pool.clone(), roster_id, multipart, user_id).await?;
-    Ok(Json(Message::new(
-        "Roster uploaded successfully",
-        Some(response),
-    )))
+    services::roster::import_roster(
+        state.pool.clone(),
+        roster_id,
+        multipart,
+        query.profile_id,
+        user_id,
+    )
+    .await?;
+    Ok(Json(Message::new("Roster uploaded successfully", None)))
 }
 
 #[utoipa::path(
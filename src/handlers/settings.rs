@@ -0,0 +1,64 @@
+use super::*;
+use crate::dto::settings::*;
+
+pub fn routes(state: Arc<AppState>) -> Router {
+    let routes = setting_routes(state.clone());
+    let api = Router::new().nest("/settings", routes);
+    Router::new().merge(api)
+}
+
+pub fn setting_routes(state: Arc<AppState>) -> Router {
+    Router::new()
+        .route("/", get(get_settings).patch(update_settings))
+        .layer(ServiceBuilder::new().layer(middleware::from_fn_with_state(
+            (state.clone(), Permission::SettingsManage),
+            crate::auth::middleware::require_permission,
+        )))
+        .with_state(state)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/settings/",
+    responses(
+        (status = 200, description = "Organisation settings", body = OrganisationSettings)
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn get_settings(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<OrganisationSettings>, ModuleError> {
+    let response = services::settings::get_settings(&state.settings).await;
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/api/v1/settings/",
+    request_body = UpdateSettingsRequest,
+    responses(
+        (status = 200, description = "Updated organisation settings", body = OrganisationSettings)
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn update_settings(
+    Claims {
+        user_id: performer_id,
+        ..
+    }: Claims,
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<UpdateSettingsRequest>,
+) -> Result<Json<OrganisationSettings>, ModuleError> {
+    let response = services::settings::update_settings(
+        state.pool.clone(),
+        &state.settings,
+        payload,
+        performer_id,
+    )
+    .await?;
+    Ok(Json(response))
+}
@@ -1,4 +1,4 @@
-use crate::dto::attendance::{AttendanceWithUser, SignAttendanceRequest};
+use crate::dto::attendance::{AttendanceExportQuery, AttendanceWithUser, SignAttendanceRequest};
 use crate::dto::*;
 
 use super::*;
@@ -10,19 +10,23 @@ pub fn routes(state: Arc<AppState>) -> Router {
 }
 
 pub fn user_routes(state: Arc<AppState>) -> Router {
-    Router::new()
-        .route("/admin/sign/{id}", get(admin_sign_attendance))
+    let admin_routes = Router::new()
+        .route("/admin/sign/{id}", post(admin_sign_attendance))
         .route("/admin/revoke/{id}", delete(revoke_attendance))
+        .route("/admin/export", get(export_attendance))
         .layer(ServiceBuilder::new().layer(middleware::from_fn_with_state(
-            state.clone(),
-            crate::auth::middleware::admin_authorize,
-        )))
+            (state.clone(), Permission::AttendanceManage),
+            crate::auth::middleware::require_permission,
+        )));
+
+    Router::new()
         .route("/check-in", post(sign_attendance))
         .route("/on-day/{date}", get(get_attendance_on_day))
         .layer(ServiceBuilder::new().layer(middleware::from_fn_with_state(
             state.clone(),
             crate::auth::middleware::authorize,
         )))
+        .merge(admin_routes)
         .with_state(state)
 }
 
@@ -49,7 +53,7 @@ pub async fn sign_attendance(
 }
 
 #[utoipa::path(
-    get,
+    post,
     path = "/api/v1/attendance/admin/sign/{id}",
     params(
         ("id" = uuid::Uuid, Path, description = "User ID")
@@ -94,6 +98,28 @@ pub async fn get_attendance_on_day(
     Ok(Json(response))
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/v1/attendance/admin/export",
+    params(
+        AttendanceExportQuery
+    ),
+    responses(
+        (status = 200, description = "Attendance between the two days as a CSV file or workbook", content_type = "application/octet-stream"),
+        (status = 400, description = "`from` is after `to`")
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn export_attendance(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<AttendanceExportQuery>,
+) -> Result<(axum::http::HeaderMap, Vec<u8>), ModuleError> {
+    let response = services::user_attendance::export_attendance(state.pool.clone(), query).await?;
+    Ok(response)
+}
+
 #[utoipa::path(
     delete,
     path = "/api/v1/attendance/admin/revoke/{id}",
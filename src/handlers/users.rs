@@ -1,9 +1,14 @@
 #![allow(deprecated)]
 use super::*;
 use crate::auth::middleware as auth_middleware;
+use crate::dto::exports::{ExportQuery, PersonalDataQuery};
+use crate::dto::imports::*;
+use crate::dto::invites::*;
+use crate::dto::session::SessionDto;
 use crate::dto::user::*;
 use crate::dto::*;
-use axum::extract::Multipart;
+use crate::services::custom_fields::Viewer;
+use axum::extract::{DefaultBodyLimit, Multipart};
 use axum::middleware as axum_middleware;
 
 pub fn routes(state: Arc<AppState>) -> Router {
@@ -13,35 +18,101 @@ pub fn routes(state: Arc<AppState>) -> Router {
 }
 
 pub fn user_routes(state: Arc<AppState>) -> Router {
-    Router::new()
+    let admin_routes = Router::new()
         .route("/admin/get_all", get(get_all_users))
         .route("/admin/delete/{id}", delete(delete_user))
+        .route("/admin/restore/{id}", patch(restore_user))
+        .route("/admin/purge/{id}", delete(purge_user))
+        .route("/admin/anonymise/{id}", patch(anonymise_user))
+        .route("/admin/data/{id}", get(admin_export_personal_data))
         .route("/admin/deactivate/{id}", patch(deactivate_user))
         .route("/admin/activate/{id}", patch(activate_user))
         .route("/admin/update-role/{id}", patch(update_user_role))
         .route("/admin/reset-device-id/{id}", patch(reset_user_device_id))
+        .route("/admin/set-password/{id}", patch(set_user_password))
+        .route("/admin/unlock/{id}", patch(unlock_user))
+        .route("/admin/mfa/{id}", delete(reset_user_mfa))
         .route("/admin/register", post(register_user))
+        .route("/admin/invites", get(get_invites).post(invite_user))
+        .route("/admin/invites/{id}/resend", post(resend_invite))
         .route("/admin/import", post(import_users))
         .route("/admin/export", get(export_users))
+        .route("/admin/bulk", post(bulk_update_users))
+        .route("/admin/reg-nos/preview", post(preview_renumber))
+        .route("/admin/reg-nos/renumber", post(renumber))
         .route("/admin/update/{id}", patch(admin_update_user))
+        .route(
+            "/admin/avatar/{id}",
+            put(admin_upload_avatar)
+                .delete(admin_remove_avatar)
+                .layer(avatar_body_limit()),
+        )
+        .route(
+            "/admin/sessions/{id}",
+            get(admin_get_user_sessions).delete(admin_revoke_user_sessions),
+        )
+        .route(
+            "/admin/sessions/{id}/{session_id}",
+            delete(admin_revoke_user_session),
+        )
+        .layer(
+            ServiceBuilder::new().layer(axum_middleware::from_fn_with_state(
+                (state.clone(), Permission::UsersManage),
+                auth_middleware::require_permission,
+            )),
+        );
+
+    let typeahead_routes =
+        Router::new()
+            .route("/typeahead", get(typeahead))
+            .layer(
+                ServiceBuilder::new().layer(axum_middleware::from_fn_with_state(
+                    (state.clone(), Permission::AttendanceMark),
+                    auth_middleware::require_permission,
+                )),
+            );
+
+    let password_routes = Router::new()
+        .route("/change-password", patch(change_password))
         .layer(
             ServiceBuilder::new().layer(axum_middleware::from_fn_with_state(
                 state.clone(),
-                auth_middleware::admin_authorize,
+                auth_middleware::authorize_password_change,
             )),
-        )
+        );
+
+    Router::new()
         .route("/get/{id}", get(get_user))
         .route("/update", patch(update_user))
-        .route("/change-password", patch(change_password))
+        .route(
+            "/avatar",
+            put(upload_avatar)
+                .delete(remove_avatar)
+                .layer(avatar_body_limit()),
+        )
+        .route(
+            "/sessions",
+            get(get_my_sessions).delete(revoke_other_sessions),
+        )
+        .route("/sessions/{session_id}", delete(revoke_my_session))
+        .route("/data", get(export_personal_data))
         .layer(
             ServiceBuilder::new().layer(axum_middleware::from_fn_with_state(
                 state.clone(),
                 auth_middleware::authorize,
             )),
         )
+        .merge(admin_routes)
+        .merge(typeahead_routes)
+        .merge(password_routes)
         .with_state(state)
 }
 
+/// Room for the multipart framing around the largest image accepted.
+fn avatar_body_limit() -> DefaultBodyLimit {
+    DefaultBodyLimit::max(services::avatars::MAX_AVATAR_BYTES + 64 * 1024)
+}
+
 #[utoipa::path(
     post,
     path = "/api/v1/users/admin/register",
@@ -60,7 +131,9 @@ pub async fn register_user(
     State(state): State<Arc<AppState>>,
     Json(payload): Json<NewUser>,
 ) -> Result<Json<Message<()>>, ModuleError> {
-    let response = services::users::register_user(state.pool.clone(), payload, user_id).await?;
+    let response =
+        services::users::register_user(state.pool.clone(), &state.settings, payload, user_id)
+            .await?;
     Ok(Json(response))
 }
 
@@ -71,7 +144,7 @@ pub async fn register_user(
         ("id" = uuid::Uuid, Path, description = "User ID")
     ),
     responses(
-        (status = 200, description = "User details", body = UserDto),
+        (status = 200, description = "User details, with the custom fields and emergency contacts the caller may see", body = UserProfileDto),
         (status = 404, description = "User not found")
     ),
     security(
@@ -79,10 +152,100 @@ pub async fn register_user(
     )
 )]
 pub async fn get_user(
+    claims: Claims,
     Path(id): Path<uuid::Uuid>,
     State(state): State<Arc<AppState>>,
-) -> Result<Json<UserDto>, ModuleError> {
-    let response = services::users::get_user(state.pool.clone(), id).await?;
+) -> Result<Json<UserProfileDto>, ModuleError> {
+    let viewer =
+        if services::permissions::claims_allow(&state, &claims, Permission::UsersManage).await {
+            Viewer::Manager
+        } else if claims.user_id == id {
+            Viewer::Owner
+        } else {
+            Viewer::Other
+        };
+    let response = services::users::get_user(state.pool.clone(), id, viewer).await?;
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/users/admin/invites",
+    request_body = InviteUserRequest,
+    responses(
+        (status = 200, description = "User created and invitation sent", body = MessageEmpty),
+        (status = 400, description = "Bad request")
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn invite_user(
+    Claims {
+        user_id: performer_id,
+        ..
+    }: Claims,
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<InviteUserRequest>,
+) -> Result<Json<Message<()>>, ModuleError> {
+    let response = services::invites::invite_user(
+        state.pool.clone(),
+        &state.mailer,
+        &state.settings,
+        payload,
+        performer_id,
+    )
+    .await?;
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/users/admin/invites",
+    params(
+        InviteFilter
+    ),
+    responses(
+        (status = 200, description = "Current invite of every invited user", body = [InviteDto])
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn get_invites(
+    State(state): State<Arc<AppState>>,
+    Query(filter): Query<InviteFilter>,
+) -> Result<Json<Vec<InviteDto>>, ModuleError> {
+    let response = services::invites::get_invites(state.pool.clone(), filter).await?;
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/users/admin/invites/{id}/resend",
+    params(
+        ("id" = uuid::Uuid, Path, description = "User ID")
+    ),
+    responses(
+        (status = 200, description = "A new invitation was sent", body = MessageEmpty),
+        (status = 400, description = "Invite has already been accepted"),
+        (status = 404, description = "User has no invite")
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn resend_invite(
+    Claims {
+        user_id: performer_id,
+        ..
+    }: Claims,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<uuid::Uuid>,
+) -> Result<Json<Message<()>>, ModuleError> {
+    let response =
+        services::invites::resend_invite(state.pool.clone(), &state.mailer, id, performer_id)
+            .await?;
     Ok(Json(response))
 }
 
@@ -93,7 +256,8 @@ pub async fn get_user(
         UserFilter
     ),
     responses(
-        (status = 200, description = "List of users", body = [UserDto])
+        (status = 200, description = "List of users", body = [UserProfileDto]),
+        (status = 400, description = "Unknown sort key, missing field or custom field")
     ),
     security(
         ("jwt" = [])
@@ -102,11 +266,32 @@ pub async fn get_user(
 pub async fn get_all_users(
     State(state): State<Arc<AppState>>,
     Query(payload): Query<UserFilter>,
-) -> Result<Json<Vec<UserDto>>, ModuleError> {
+) -> Result<Json<Vec<UserProfileDto>>, ModuleError> {
     let response = services::users::get_all_users(state.pool.clone(), payload).await?;
     Ok(Json(response))
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/v1/users/typeahead",
+    params(
+        TypeaheadQuery
+    ),
+    responses(
+        (status = 200, description = "Active users whose reg_no or name starts with `q`", body = [UserSuggestion])
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn typeahead(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<TypeaheadQuery>,
+) -> Result<Json<Vec<UserSuggestion>>, ModuleError> {
+    let response = services::users::typeahead(state.pool.clone(), query).await?;
+    Ok(Json(response))
+}
+
 #[utoipa::path(
     patch,
     path = "/api/v1/users/update",
@@ -136,8 +321,9 @@ pub async fn update_user(
         ("id" = uuid::Uuid, Path, description = "User ID")
     ),
     responses(
-        (status = 200, description = "User deleted successfully", body = MessageEmpty),
-        (status = 404, description = "User not found")
+        (status = 200, description = "User deleted; their history is kept and they can be restored", body = MessageEmpty),
+        (status = 400, description = "Tried to delete your own account"),
+        (status = 404, description = "User not found or already deleted")
     ),
     security(
         ("jwt" = [])
@@ -155,6 +341,94 @@ pub async fn delete_user(
     Ok(Json(response))
 }
 
+#[utoipa::path(
+    patch,
+    path = "/api/v1/users/admin/restore/{id}",
+    params(
+        ("id" = uuid::Uuid, Path, description = "User ID")
+    ),
+    responses(
+        (status = 200, description = "User restored", body = MessageEmpty),
+        (status = 404, description = "No deleted user with this id")
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn restore_user(
+    Claims {
+        user_id: performer_id,
+        ..
+    }: Claims,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<uuid::Uuid>,
+) -> Result<Json<Message<()>>, ModuleError> {
+    let response = services::users::restore_user(state.pool.clone(), id, performer_id).await?;
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/users/admin/purge/{id}",
+    params(
+        ("id" = uuid::Uuid, Path, description = "User ID")
+    ),
+    responses(
+        (status = 200, description = "User and everything that belongs to them removed for good", body = MessageEmpty),
+        (status = 400, description = "The user has not been deleted first"),
+        (status = 404, description = "User not found")
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn purge_user(
+    Claims {
+        user_id: performer_id,
+        ..
+    }: Claims,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<uuid::Uuid>,
+) -> Result<Json<Message<()>>, ModuleError> {
+    let response =
+        services::users::purge_user(state.pool.clone(), state.storage.clone(), id, performer_id)
+            .await?;
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/api/v1/users/admin/anonymise/{id}",
+    params(
+        ("id" = uuid::Uuid, Path, description = "User ID")
+    ),
+    responses(
+        (status = 200, description = "Personal data scrubbed and the user deleted for good; attendance is kept", body = MessageEmpty),
+        (status = 400, description = "The user is already anonymised, or is the caller"),
+        (status = 404, description = "User not found")
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn anonymise_user(
+    Claims {
+        user_id: performer_id,
+        ..
+    }: Claims,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<uuid::Uuid>,
+) -> Result<Json<Message<()>>, ModuleError> {
+    let response = services::personal_data::anonymise_user(
+        state.pool.clone(),
+        state.storage.clone(),
+        id,
+        performer_id,
+    )
+    .await?;
+    Ok(Json(response))
+}
+
 #[utoipa::path(
     patch,
     path = "/api/v1/users/admin/deactivate/{id}",
@@ -236,33 +510,135 @@ pub async fn update_user_role(
     Ok(Json(response))
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/v1/users/admin/import",
+    params(
+        ImportUsersQuery
+    ),
+    request_body(content = String, description = "CSV or XLSX file", content_type = "multipart/form-data"),
+    responses(
+        (status = 200, description = "`dry_run`: the report of every row. `commit`: the valid rows are imported or applied to the users they match and the rejected ones returned as CSV", body = ImportReport),
+        (status = 400, description = "No file or an unreadable header row")
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
 pub async fn import_users(
     Claims {
         user_id: performer_id,
         ..
     }: Claims,
     State(state): State<Arc<AppState>>,
+    Query(query): Query<ImportUsersQuery>,
     multipart: Multipart,
-) -> Result<Json<Message<()>>, ModuleError> {
-    let response =
-        services::users::import_users(state.pool.clone(), multipart, performer_id).await?;
-    Ok(Json(response))
+) -> Result<axum::response::Response, ModuleError> {
+    match query.mode {
+        ImportMode::DryRun => {
+            let report = services::user_import::dry_run(
+                state.pool.clone(),
+                &state.settings,
+                multipart,
+                query,
+            )
+            .await?;
+            Ok(Json(report).into_response())
+        }
+        ImportMode::Commit => {
+            let (headers, file_data) = services::user_import::commit(
+                state.pool.clone(),
+                &state.settings,
+                multipart,
+                query,
+                performer_id,
+            )
+            .await?;
+            Ok((headers, file_data).into_response())
+        }
+    }
 }
 
 pub async fn export_users(
     State(state): State<Arc<AppState>>,
+    Query(query): Query<ExportQuery>,
 ) -> Result<(axum::http::HeaderMap, Vec<u8>), ModuleError> {
-    let (headers, file_data) = services::users::export_users(state.pool.clone()).await?;
+    let (headers, file_data) =
+        services::users::export_users(state.pool.clone(), query.format).await?;
     Ok((headers, file_data))
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/v1/users/data",
+    params(
+        PersonalDataQuery
+    ),
+    responses(
+        (status = 200, description = "The caller's profile, attendance, roster history and activity as a ZIP archive or a JSON file", content_type = "application/octet-stream")
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn export_personal_data(
+    Claims { user_id, .. }: Claims,
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<PersonalDataQuery>,
+) -> Result<(axum::http::HeaderMap, Vec<u8>), ModuleError> {
+    let response = services::personal_data::export_personal_data(
+        state.pool.clone(),
+        user_id,
+        query.format,
+        Viewer::Owner,
+        user_id,
+    )
+    .await?;
+    Ok(response)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/users/admin/data/{id}",
+    params(
+        ("id" = uuid::Uuid, Path, description = "User ID"),
+        PersonalDataQuery
+    ),
+    responses(
+        (status = 200, description = "The user's profile, attendance, roster history and activity as a ZIP archive or a JSON file", content_type = "application/octet-stream"),
+        (status = 404, description = "User not found")
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn admin_export_personal_data(
+    Claims {
+        user_id: performer_id,
+        ..
+    }: Claims,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<uuid::Uuid>,
+    Query(query): Query<PersonalDataQuery>,
+) -> Result<(axum::http::HeaderMap, Vec<u8>), ModuleError> {
+    let response = services::personal_data::export_personal_data(
+        state.pool.clone(),
+        id,
+        query.format,
+        Viewer::Manager,
+        performer_id,
+    )
+    .await?;
+    Ok(response)
+}
+
 #[utoipa::path(
     patch,
     path = "/api/v1/users/change-password",
     request_body = ChangePasswordRequest,
     responses(
         (status = 200, description = "Password changed successfully", body = MessageEmpty),
-        (status = 400, description = "Bad request")
+        (status = 400, description = "Wrong current password, or the new one is unchanged or breaks the password policy")
     ),
     security(
         ("jwt" = [])
@@ -276,8 +652,49 @@ pub async fn change_password(
     State(state): State<Arc<AppState>>,
     Json(payload): Json<ChangePasswordRequest>,
 ) -> Result<Json<Message<()>>, ModuleError> {
-    let response =
-        services::users::change_password(state.pool.clone(), payload, performer_id).await?;
+    let response = services::users::change_password(
+        state.pool.clone(),
+        &state.settings,
+        payload,
+        performer_id,
+    )
+    .await?;
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/api/v1/users/admin/set-password/{id}",
+    params(
+        ("id" = uuid::Uuid, Path, description = "User ID")
+    ),
+    request_body = SetPasswordRequest,
+    responses(
+        (status = 200, description = "Password set; the user has to change it at their next sign-in", body = MessageEmpty),
+        (status = 400, description = "The password breaks the password policy"),
+        (status = 404, description = "User not found")
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn set_user_password(
+    Claims {
+        user_id: performer_id,
+        ..
+    }: Claims,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<uuid::Uuid>,
+    Json(payload): Json<SetPasswordRequest>,
+) -> Result<Json<Message<()>>, ModuleError> {
+    let response = services::users::set_user_password(
+        state.pool.clone(),
+        &state.settings,
+        id,
+        payload,
+        performer_id,
+    )
+    .await?;
     Ok(Json(response))
 }
 
@@ -336,3 +753,389 @@ pub async fn admin_update_user(
         services::users::admin_update_user(state.pool.clone(), payload, id, performer_id).await?;
     Ok(Json(response))
 }
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/users/sessions",
+    responses(
+        (status = 200, description = "Active sessions of the current user", body = [SessionDto])
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn get_my_sessions(
+    Claims { user_id, jti, .. }: Claims,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<SessionDto>>, ModuleError> {
+    let response =
+        services::sessions::list_active_sessions(state.pool.clone(), user_id, Some(jti)).await?;
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/users/sessions/{session_id}",
+    params(
+        ("session_id" = uuid::Uuid, Path, description = "Session ID")
+    ),
+    responses(
+        (status = 200, description = "Session signed out", body = MessageEmpty),
+        (status = 404, description = "Session not found")
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn revoke_my_session(
+    Claims { user_id, .. }: Claims,
+    State(state): State<Arc<AppState>>,
+    Path(session_id): Path<uuid::Uuid>,
+) -> Result<Json<Message<()>>, ModuleError> {
+    let response =
+        services::sessions::revoke_user_session(state.pool.clone(), user_id, session_id, user_id)
+            .await?;
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/users/sessions",
+    responses(
+        (status = 200, description = "Signed out of every other device", body = MessageEmpty)
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn revoke_other_sessions(
+    Claims { user_id, jti, .. }: Claims,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Message<()>>, ModuleError> {
+    let response =
+        services::sessions::revoke_user_sessions(state.pool.clone(), user_id, Some(jti), user_id)
+            .await?;
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/users/admin/sessions/{id}",
+    params(
+        ("id" = uuid::Uuid, Path, description = "User ID")
+    ),
+    responses(
+        (status = 200, description = "Active sessions of the user", body = [SessionDto])
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn admin_get_user_sessions(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<uuid::Uuid>,
+) -> Result<Json<Vec<SessionDto>>, ModuleError> {
+    let response = services::sessions::list_active_sessions(state.pool.clone(), id, None).await?;
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/users/admin/sessions/{id}",
+    params(
+        ("id" = uuid::Uuid, Path, description = "User ID")
+    ),
+    responses(
+        (status = 200, description = "Signed the user out of every device", body = MessageEmpty)
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn admin_revoke_user_sessions(
+    Claims {
+        user_id: performer_id,
+        ..
+    }: Claims,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<uuid::Uuid>,
+) -> Result<Json<Message<()>>, ModuleError> {
+    let response =
+        services::sessions::revoke_user_sessions(state.pool.clone(), id, None, performer_id)
+            .await?;
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/users/admin/sessions/{id}/{session_id}",
+    params(
+        ("id" = uuid::Uuid, Path, description = "User ID"),
+        ("session_id" = uuid::Uuid, Path, description = "Session ID")
+    ),
+    responses(
+        (status = 200, description = "Session signed out", body = MessageEmpty),
+        (status = 404, description = "Session not found")
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn admin_revoke_user_session(
+    Claims {
+        user_id: performer_id,
+        ..
+    }: Claims,
+    State(state): State<Arc<AppState>>,
+    Path((id, session_id)): Path<(uuid::Uuid, uuid::Uuid)>,
+) -> Result<Json<Message<()>>, ModuleError> {
+    let response =
+        services::sessions::revoke_user_session(state.pool.clone(), id, session_id, performer_id)
+            .await?;
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/api/v1/users/admin/unlock/{id}",
+    params(
+        ("id" = uuid::Uuid, Path, description = "User ID")
+    ),
+    responses(
+        (status = 200, description = "Failed login attempts cleared", body = MessageEmpty),
+        (status = 404, description = "User not found")
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn unlock_user(
+    Claims {
+        user_id: performer_id,
+        ..
+    }: Claims,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<uuid::Uuid>,
+) -> Result<Json<Message<()>>, ModuleError> {
+    let response =
+        services::login_throttles::unlock_user(state.pool.clone(), id, performer_id).await?;
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/users/admin/mfa/{id}",
+    params(
+        ("id" = uuid::Uuid, Path, description = "User ID")
+    ),
+    responses(
+        (status = 200, description = "Two-factor enrollment removed", body = MessageEmpty),
+        (status = 404, description = "User has no two-factor enrollment")
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn reset_user_mfa(
+    Claims {
+        user_id: performer_id,
+        ..
+    }: Claims,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<uuid::Uuid>,
+) -> Result<Json<Message<()>>, ModuleError> {
+    let response = services::mfa::admin_reset(state.pool.clone(), id, performer_id).await?;
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/users/avatar",
+    request_body(content = String, description = "A JPEG, PNG or WebP image of at most 5 MiB", content_type = "multipart/form-data"),
+    responses(
+        (status = 200, description = "Avatar stored, with its thumbnails", body = AvatarDto),
+        (status = 400, description = "Missing, oversized or unsupported image")
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn upload_avatar(
+    Claims { user_id, .. }: Claims,
+    State(state): State<Arc<AppState>>,
+    multipart: Multipart,
+) -> Result<Json<AvatarDto>, ModuleError> {
+    let response = services::avatars::upload_avatar(
+        state.pool.clone(),
+        state.storage.clone(),
+        multipart,
+        user_id,
+        user_id,
+    )
+    .await?;
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/users/avatar",
+    responses(
+        (status = 200, description = "Avatar removed", body = MessageEmpty)
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn remove_avatar(
+    Claims { user_id, .. }: Claims,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Message<()>>, ModuleError> {
+    let response = services::avatars::remove_avatar(
+        state.pool.clone(),
+        state.storage.clone(),
+        user_id,
+        user_id,
+    )
+    .await?;
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/users/admin/avatar/{id}",
+    params(
+        ("id" = uuid::Uuid, Path, description = "User ID")
+    ),
+    request_body(content = String, description = "A JPEG, PNG or WebP image of at most 5 MiB", content_type = "multipart/form-data"),
+    responses(
+        (status = 200, description = "Avatar stored, with its thumbnails", body = AvatarDto),
+        (status = 400, description = "Missing, oversized or unsupported image"),
+        (status = 404, description = "User not found")
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn admin_upload_avatar(
+    Claims {
+        user_id: performer_id,
+        ..
+    }: Claims,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<uuid::Uuid>,
+    multipart: Multipart,
+) -> Result<Json<AvatarDto>, ModuleError> {
+    let response = services::avatars::upload_avatar(
+        state.pool.clone(),
+        state.storage.clone(),
+        multipart,
+        id,
+        performer_id,
+    )
+    .await?;
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/users/admin/avatar/{id}",
+    params(
+        ("id" = uuid::Uuid, Path, description = "User ID")
+    ),
+    responses(
+        (status = 200, description = "Avatar removed", body = MessageEmpty),
+        (status = 404, description = "User not found")
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn admin_remove_avatar(
+    Claims {
+        user_id: performer_id,
+        ..
+    }: Claims,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<uuid::Uuid>,
+) -> Result<Json<Message<()>>, ModuleError> {
+    let response = services::avatars::remove_avatar(
+        state.pool.clone(),
+        state.storage.clone(),
+        id,
+        performer_id,
+    )
+    .await?;
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/users/admin/reg-nos/preview",
+    request_body = RenumberRequest,
+    responses(
+        (status = 200, description = "The reg_no changes a renumbering would make", body = RenumberReport),
+        (status = 400, description = "Invalid format, or new reg_nos already held by users of other years")
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn preview_renumber(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<RenumberRequest>,
+) -> Result<Json<RenumberReport>, ModuleError> {
+    let response =
+        services::reg_nos::preview_renumber(state.pool.clone(), &state.settings, payload).await?;
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/users/admin/reg-nos/renumber",
+    request_body = RenumberRequest,
+    responses(
+        (status = 200, description = "The reg_no changes made", body = RenumberReport),
+        (status = 400, description = "Invalid format, or new reg_nos already held by users of other years")
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn renumber(
+    Claims {
+        user_id: performer_id,
+        ..
+    }: Claims,
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<RenumberRequest>,
+) -> Result<Json<RenumberReport>, ModuleError> {
+    let response =
+        services::reg_nos::renumber(state.pool.clone(), &state.settings, payload, performer_id)
+            .await?;
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/users/admin/bulk",
+    request_body = BulkUserRequest,
+    responses(
+        (status = 200, description = "What happened to each selected user", body = BulkUserReport),
+        (status = 400, description = "No users selected, both or neither of user_ids and filter, or more than 1000 users")
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+pub async fn bulk_update_users(
+    Claims {
+        user_id: performer_id,
+        ..
+    }: Claims,
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<BulkUserRequest>,
+) -> Result<Json<BulkUserReport>, ModuleError> {
+    let response =
+        services::user_bulk::bulk_update(state.pool.clone(), payload, performer_id).await?;
+    Ok(Json(response))
+}
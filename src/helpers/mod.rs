@@ -2,25 +2,129 @@ use crate::dto::attendance::GeoPoint;
 
 use super::*;
 use argon2::{
-    Argon2,
+    Algorithm, Argon2, Params, Version,
     password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString, rand_core::OsRng},
 };
+use std::sync::LazyLock;
 
+/// Argon2id cost parameters for new hashes. `ARGON2_MEMORY_KIB`, `ARGON2_ITERATIONS` and
+/// `ARGON2_PARALLELISM` override the crate defaults; existing hashes are upgraded on login.
+static ARGON2_PARAMS: LazyLock<Params> = LazyLock::new(|| {
+    let var = |name: &str, default: u32| {
+        std::env::var(name)
+            .ok()
+            .and_then(|value| value.parse::<u32>().ok())
+            .unwrap_or(default)
+    };
+    Params::new(
+        var("ARGON2_MEMORY_KIB", Params::DEFAULT_M_COST),
+        var("ARGON2_ITERATIONS", Params::DEFAULT_T_COST),
+        var("ARGON2_PARALLELISM", Params::DEFAULT_P_COST),
+        None,
+    )
+    .unwrap_or_else(|e| {
+        tracing::warn!("Invalid Argon2 parameters, using the defaults: {}", e);
+        Params::default()
+    })
+});
+
+fn argon2() -> Argon2<'static> {
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, ARGON2_PARAMS.clone())
+}
+
+/// Verifies against the parameters stored in `hash`, so hashes made with older parameters
+/// keep working.
 pub fn password_verfier(password: &str, hash: &str) -> bool {
-    let argon2 = Argon2::default();
-    let hash = PasswordHash::new(hash).unwrap();
-    argon2.verify_password(password.as_bytes(), &hash).is_ok()
+    let Ok(hash) = PasswordHash::new(hash) else {
+        return false;
+    };
+    argon2().verify_password(password.as_bytes(), &hash).is_ok()
+}
+
+/// Whether `hash` was made with other Argon2 parameters than the current ones.
+pub fn password_needs_rehash(hash: &str) -> bool {
+    let Ok(hash) = PasswordHash::new(hash) else {
+        return false;
+    };
+    let Ok(params) = Params::try_from(&hash) else {
+        return true;
+    };
+    hash.algorithm != Algorithm::Argon2id.ident()
+        || hash.version != Some(Version::V0x13.into())
+        || params.m_cost() != ARGON2_PARAMS.m_cost()
+        || params.t_cost() != ARGON2_PARAMS.t_cost()
+        || params.p_cost() != ARGON2_PARAMS.p_cost()
 }
 
 pub fn password_hasher(password: &str) -> Result<String, ModuleError> {
     let salt = SaltString::generate(&mut OsRng);
-    let argon2 = Argon2::default();
-    let password_hash = argon2
+    let password_hash = argon2()
         .hash_password(password.as_bytes(), &salt)
         .map_err(|_| ModuleError::InternalError("Could not has password for some reason".into()))?;
     Ok(password_hash.to_string())
 }
 
+/// Password of the seeded admin and of imported accounts. Signing in with it sets
+/// `must_change_password`.
+pub const PLACEHOLDER_PASSWORD: &str = "password";
+
+/// Most common passwords from public breach corpora, lowercased, one per line.
+const COMMON_PASSWORDS: &str = include_str!("common_passwords.txt");
+
+/// Checks a password a user picked against the organisation's password policy.
+pub fn check_password_policy(
+    password: &str,
+    settings: &crate::dto::settings::OrganisationSettings,
+) -> Result<(), ModuleError> {
+    if password.chars().count() < settings.password_min_length {
+        return Err(ModuleError::BadRequest(
+            format!(
+                "Password must be at least {} characters long",
+                settings.password_min_length
+            )
+            .into(),
+        ));
+    }
+    if settings.password_reject_common {
+        let lowered = password.to_lowercase();
+        if COMMON_PASSWORDS.lines().any(|common| common == lowered) {
+            return Err(ModuleError::BadRequest(
+                "Password is too common, please choose another one".into(),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Hashes a long random token (API key, invite) for storage. The tokens carry enough entropy
+/// that a fast unsalted hash is enough, and it lets them be looked up directly.
+pub fn token_hash(token: &str) -> String {
+    use sha2::{Digest, Sha256};
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+/// A random alphanumeric string of `length` characters.
+pub fn random_token(length: usize) -> String {
+    use rand::Rng;
+    rand::rng()
+        .sample_iter(rand::distr::Alphanumeric)
+        .take(length)
+        .map(char::from)
+        .collect()
+}
+
+/// Escapes `%`, `_` and `\` so user input matches literally inside a `LIKE` pattern.
+pub fn escape_like(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
 pub fn parse_time_stamp(date_str: &str, time: &str) -> Result<NaiveDateTime, ModuleError> {
     let naive_date = chrono::NaiveDate::parse_from_str(date_str, "%Y-%m-%d")
         .map_err(|_| ModuleError::Error("Invalid date format".into()))?;
@@ -81,3 +185,44 @@ pub fn haversine_meters(a: GeoPoint, b: GeoPoint) -> f64 {
 
     2.0 * EARTH_RADIUS_METERS * h.sqrt().atan2((1.0 - h).sqrt())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dto::settings::OrganisationSettings;
+
+    #[test]
+    fn password_policy_enforces_min_length_in_characters() {
+        let settings = OrganisationSettings {
+            password_min_length: 10,
+            ..Default::default()
+        };
+        assert!(check_password_policy("short pass", &settings).is_ok());
+        assert!(check_password_policy("short pas", &settings).is_err());
+        // Ten characters, more than ten bytes.
+        assert!(check_password_policy("ñandú-ñoño", &settings).is_ok());
+    }
+
+    #[test]
+    fn password_policy_rejects_common_passwords_ignoring_case() {
+        let settings = OrganisationSettings::default();
+        assert!(check_password_policy("Password1", &settings).is_err());
+        assert!(check_password_policy("QWERTY123", &settings).is_err());
+        assert!(check_password_policy("correct horse battery", &settings).is_ok());
+    }
+
+    #[test]
+    fn password_policy_can_allow_common_passwords() {
+        let settings = OrganisationSettings {
+            password_reject_common: false,
+            ..Default::default()
+        };
+        assert!(check_password_policy("password1", &settings).is_ok());
+    }
+
+    #[test]
+    fn placeholder_password_fails_the_default_policy() {
+        let settings = OrganisationSettings::default();
+        assert!(check_password_policy(PLACEHOLDER_PASSWORD, &settings).is_err());
+    }
+}
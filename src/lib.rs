@@ -9,6 +9,7 @@ pub mod mailer;
 pub mod models;
 pub mod schema;
 pub mod services;
+pub mod storage;
 pub mod swagger;
 
 use crate::dto::attendance::GeoPoint;
@@ -41,6 +42,9 @@ pub use diesel_async::AsyncPgConnection;
 pub use diesel_async::pooled_connection::AsyncDieselConnectionManager;
 pub use tracing::info;
 pub type Pool = bb8::Pool<AsyncDieselConnectionManager<AsyncPgConnection>>;
+
+/// Organisation settings, loaded at startup and reloaded whenever an admin edits them.
+pub type SettingsCache = Arc<tokio::sync::RwLock<dto::settings::OrganisationSettings>>;
 pub type Connection<'a> =
     bb8::PooledConnection<'a, AsyncDieselConnectionManager<AsyncPgConnection>>;
 pub const POOL_ERROR_MSG: &str = "Could not get connection from the database pool";
@@ -50,7 +54,25 @@ pub static HOME_LOCATION_CHECKIN_RADIUS: OnceLock<GeoPoint> = OnceLock::new();
 #[derive(Clone)]
 pub struct AppState {
     pub pool: Arc<Pool>,
+    pub mailer: mailer::worker::MailerSender,
+    /// Google OAuth settings; `None` when the `GOOGLE_OAUTH_*` variables are not set.
+    pub oauth: Option<Arc<config::Config>>,
+    pub permissions: PermissionCache,
+    pub settings: SettingsCache,
+    /// Where uploaded files such as avatars are kept.
+    pub storage: Arc<dyn storage::Storage>,
 }
+
+/// Permissions granted to each role, loaded from `role_permissions` at startup and reloaded
+/// whenever an admin edits them.
+pub type PermissionCache = Arc<
+    tokio::sync::RwLock<
+        std::collections::HashMap<
+            models::users::Role,
+            std::collections::HashSet<models::permissions::Permission>,
+        >,
+    >,
+>;
 // ==================================================================================================================================================================
 // ==================================================================================================================================================================
 // ================================================================== Other Libraries ===============================================================================
@@ -18,46 +18,13 @@ macro_rules! log_route {
     }};
 }
 
+/// Returns `ModuleError::PermissionDenied` from the enclosing function unless `$role` holds
+/// `$permission` in the permission cache.
 #[macro_export]
 macro_rules! guard {
-    ($key:expr, $permission:ident, $cache:expr ) => {{
-        let validate = async || -> Result<(), ModuleError> {
-            let cache = $cache.read().await;
-            let cached_user = cache.get(&$key).ok_or(ModuleError::Error(
-                "User not found in cache, please login again".into(),
-            ))?;
-            if !cached_user.permissions.contains(&$permission) {
-                return Err(ModuleError::PermissionDenied.into());
-            }
-            Ok(())
-        };
-        validate().await?;
+    ($role:expr, $permission:expr, $cache:expr) => {{
+        if !$crate::services::permissions::has_permission(&$cache, &$role, $permission).await {
+            return Err($crate::ModuleError::PermissionDenied);
+        }
     }};
 }
-
-// macro_rules! guard {
-//     ($key:expr, $permission:ident, $pool:expr ) => {{
-//         use crate::dto::auth::RedisUser;
-//         use redis::AsyncCommands;
-//         let key: String = $key.into();
-//         let mut conn = $pool
-//             .get()
-//             .await
-//             .map_err(|e| ModuleError::InternalError(e.to_string()))?;
-//         let response: String = conn
-//             .get(key)
-//             .await
-//             .map_err(|_| ModuleError::InternalError("Could not get User from Redis".into()))?;
-//         let redis_user: RedisUser = serde_json::from_str(&response).map_err(|_| {
-//             ModuleError::InternalError("Could not deserialze string into Redis User".into())
-//         })?;
-//         match redis_user
-//             .permissions
-//             .iter()
-//             .any(|permissions| $permission.eq(permissions))
-//         {
-//             true => (),
-//             false => return Err(ModuleError::PermissionDenied),
-//         }
-//     }};
-// }
@@ -2,3 +2,4 @@ pub mod config;
 pub mod email;
 
 pub mod types;
+pub mod worker;
@@ -36,6 +36,8 @@ pub enum MailerEvent {
     SendSessionStartEmail {
         name: String,
         to: String,
+        ip_address: String,
+        user_agent: String,
     },
     SendSessionAddedEmail {
         name: String,
@@ -46,6 +48,16 @@ pub enum MailerEvent {
         to: String,
         otp: String,
     },
+    SendApplicationApprovedEmail {
+        name: String,
+        url: String,
+        to: String,
+    },
+    SendApplicationRejectedEmail {
+        name: String,
+        to: String,
+        reason: String,
+    },
 }
 
 impl MailerEvent {
@@ -65,6 +77,8 @@ impl MailerEvent {
             MailerEvent::SendSessionStartEmail { .. } => "SendSessionStartEmail".to_string(),
             MailerEvent::SendSessionAddedEmail { .. } => "SendSessionAddedEmail".to_string(),
             Self::SendOtp { .. } => "SendOtp".to_string(),
+            Self::SendApplicationApprovedEmail { .. } => "SendApplicationApprovedEmail".to_string(),
+            Self::SendApplicationRejectedEmail { .. } => "SendApplicationRejectedEmail".to_string(),
         }
     }
 }
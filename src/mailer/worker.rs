@@ -0,0 +1,117 @@
+use serde_json::json;
+
+use super::config::Config;
+use super::email::{Email, Receiptent};
+use super::types::MailerEvent;
+
+pub type MailerSender = async_channel::Sender<MailerEvent>;
+
+/// Creates the mailer channel and spawns the task that drains it.
+///
+/// Without SMTP configuration the worker still drains the channel so that producers never
+/// block; events are logged and dropped.
+pub fn start() -> MailerSender {
+    let (tx, rx) = async_channel::unbounded::<MailerEvent>();
+    let config = match Config::init() {
+        Ok(config) => Some(config),
+        Err(e) => {
+            tracing::warn!("Mailer is not configured, emails will not be sent: {}", e);
+            None
+        }
+    };
+    tokio::spawn(async move {
+        while let Ok(event) = rx.recv().await {
+            let Some(config) = config.clone() else {
+                tracing::info!("Dropping {} event, mailer is not configured", event.name());
+                continue;
+            };
+            let Some((to, template, subject, data)) = envelope(&event) else {
+                tracing::warn!("Dropping {} event, it has no email template", event.name());
+                continue;
+            };
+            let email = Email::new(to, config);
+            if let Err(e) = email.send_email(template, subject, data).await {
+                tracing::error!("Could not send {} email: {}", event.name(), e);
+            }
+        }
+    });
+    tx
+}
+
+/// Queues an email without failing the caller; a closed channel is only logged.
+pub fn dispatch(mailer: &MailerSender, event: MailerEvent) {
+    if let Err(e) = mailer.try_send(event) {
+        tracing::error!("Could not queue email: {}", e);
+    }
+}
+
+/// The recipient, template, subject and template data of `event`; None for events that have
+/// no template yet.
+fn envelope(
+    event: &MailerEvent,
+) -> Option<(Receiptent, &'static str, &'static str, serde_json::Value)> {
+    let receiptent = |name: &str, to: &str| Receiptent {
+        name: name.to_string(),
+        email: to.to_string(),
+    };
+    let envelope = match event {
+        MailerEvent::SendInvitationEmail { name, url, to } => (
+            receiptent(name, to),
+            "invitation",
+            "You have been invited to KUD",
+            json!({ "name": name, "url": url }),
+        ),
+        MailerEvent::SendWelcomeEmail { name, to } => (
+            receiptent(name, to),
+            "welcome",
+            "Welcome to KUD",
+            json!({ "name": name }),
+        ),
+        MailerEvent::SendPasswordResetEmail { name, url, to } => (
+            receiptent(name, to),
+            "password_reset",
+            "Reset your password",
+            json!({ "name": name, "url": url }),
+        ),
+        MailerEvent::SendPasswordChangeEmail { name, to } => (
+            receiptent(name, to),
+            "password_change",
+            "Your password was changed",
+            json!({ "name": name }),
+        ),
+        MailerEvent::SendSessionStartEmail {
+            name,
+            to,
+            ip_address,
+            user_agent,
+        } => (
+            receiptent(name, to),
+            "session_start",
+            "New sign-in to your account",
+            json!({ "name": name, "ip_address": ip_address, "user_agent": user_agent }),
+        ),
+        MailerEvent::SendOtp { name, to, otp } => (
+            receiptent(name, to),
+            "otp",
+            "Your one-time code",
+            json!({ "name": name, "otp": otp }),
+        ),
+        MailerEvent::SendApplicationApprovedEmail { name, url, to } => (
+            receiptent(name, to),
+            "application_approved",
+            "Your application has been approved",
+            json!({ "name": name, "url": url }),
+        ),
+        MailerEvent::SendApplicationRejectedEmail { name, to, reason } => (
+            receiptent(name, to),
+            "application_rejected",
+            "Your application to KUD",
+            json!({ "name": name, "reason": reason }),
+        ),
+        MailerEvent::SendAccountDeletionEmail { .. }
+        | MailerEvent::SendAccountDeactivationEmail { .. }
+        | MailerEvent::SendAccountActivationEmail { .. }
+        | MailerEvent::SendSessionAddedEmail { .. } => return None,
+    };
+    Some(envelope)
+}
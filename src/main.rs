@@ -5,7 +5,9 @@ use server::{AppState, handlers, swagger};
 
 use std::sync::Arc;
 
-use tower_http::{compression::CompressionLayer, cors::CorsLayer, trace::TraceLayer};
+use tower_http::{
+    compression::CompressionLayer, cors::CorsLayer, services::ServeDir, trace::TraceLayer,
+};
 
 pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!();
 #[tokio::main]
@@ -26,11 +28,56 @@ async fn main() {
         std::process::exit(1);
     }
 
+    if let Err(e) = server::auth::keys::init() {
+        tracing::error!("Could not load JWT signing keys: {}", e.to_string());
+        std::process::exit(1);
+    }
+
     if let Err(e) = server::services::users::seed_default_admin(pool.clone()).await {
         tracing::error!("Failed to seed default admin: {}", e.to_string());
     }
 
-    let state: Arc<AppState> = AppState { pool: pool.clone() }.into();
+    if let Err(e) = server::services::permissions::seed_default_permissions(pool.clone()).await {
+        tracing::error!("Failed to seed role permissions: {}", e.to_string());
+    }
+    let permissions = match server::services::permissions::load_permissions(pool.clone()).await {
+        Ok(permissions) => permissions,
+        Err(e) => {
+            tracing::error!("Could not load role permissions: {}", e.to_string());
+            std::process::exit(1);
+        }
+    };
+
+    let settings = match server::services::settings::load_settings(pool.clone()).await {
+        Ok(settings) => settings,
+        Err(e) => {
+            tracing::error!("Could not load organisation settings: {}", e.to_string());
+            std::process::exit(1);
+        }
+    };
+
+    let mailer = server::mailer::worker::start();
+
+    let oauth = match server::config::Config::init() {
+        Ok(config) => Some(Arc::new(config)),
+        Err(e) => {
+            tracing::warn!("Google sign-in disabled: {}", e.to_string());
+            None
+        }
+    };
+
+    let storage = server::storage::LocalStorage::from_env();
+    let uploads = ServeDir::new(storage.root());
+
+    let state: Arc<AppState> = AppState {
+        pool: pool.clone(),
+        mailer,
+        oauth,
+        permissions,
+        settings,
+        storage: Arc::new(storage),
+    }
+    .into();
 
     server::CHIDA_LOCATION
         .set(server::dto::attendance::GeoPoint {
@@ -59,11 +106,15 @@ async fn main() {
             Method::PUT,
         ])
         .allow_headers([
+            AUTHORIZATION,
             CONTENT_TYPE,
             ACCESS_CONTROL_ALLOW_HEADERS,
             ACCESS_CONTROL_ALLOW_CREDENTIALS,
             ACCESS_CONTROL_ALLOW_ORIGIN,
         ])
+        .expose_headers([HeaderName::from_static(
+            server::auth::middleware::IMPERSONATED_BY_HEADER,
+        )])
         .allow_credentials(true)
         .allow_origin([
             "http://localhost:3000".parse::<HeaderValue>().unwrap(),
@@ -89,11 +140,18 @@ async fn main() {
 
     let api = axum::Router::new()
         .nest("/api/v1", app)
+        .merge(handlers::keys::well_known_routes())
+        .nest_service("/uploads", uploads)
         .merge(swagger::swagger_routes());
 
     let listener = tokio::net::TcpListener::bind("0.0.0.0:9898").await.unwrap();
     server::info!("Listening on {}", listener.local_addr().unwrap());
-    axum::serve(listener, api).await.unwrap();
+    axum::serve(
+        listener,
+        api.into_make_service_with_connect_info::<std::net::SocketAddr>(),
+    )
+    .await
+    .unwrap();
 }
 
 pub async fn run_migration() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
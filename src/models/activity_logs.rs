@@ -40,6 +40,36 @@ pub enum ActivityType {
     RosterImported,
     UserHallUpdated,
     RosterShared,
+    RefreshTokenReused,
+    SessionRevoked,
+    PermissionsUpdated,
+    LoginFailed,
+    AccountUnlocked,
+    MfaEnabled,
+    MfaDisabled,
+    SettingsUpdated,
+    ApiKeyCreated,
+    ApiKeyRevoked,
+    ApiKeyUsed,
+    SigningKeyRotated,
+    ImpersonationStarted,
+    ImpersonationEnded,
+    ImpersonatedRequest,
+    UserInvited,
+    InviteAccepted,
+    ApplicationApproved,
+    ApplicationRejected,
+    ImportProfileSaved,
+    ImportProfileDeleted,
+    UserDeleted,
+    UserRestored,
+    UserPurged,
+    RegNosRenumbered,
+    BulkUserAction,
+    CustomFieldSaved,
+    CustomFieldDeleted,
+    PersonalDataExported,
+    UserAnonymised,
 }
 
 impl ActivityType {
@@ -68,6 +98,38 @@ impl ActivityType {
             ActivityType::RosterImported => "Imported a new roster.".into(),
             ActivityType::UserHallUpdated => "Updated a user's hall assignment.".into(),
             ActivityType::RosterShared => "Shared a roster.".into(),
+            ActivityType::RefreshTokenReused => {
+                "Reused a refresh token; the session was revoked.".into()
+            }
+            ActivityType::SessionRevoked => "Signed out a session.".into(),
+            ActivityType::PermissionsUpdated => "Updated role permissions.".into(),
+            ActivityType::LoginFailed => "Failed to log in.".into(),
+            ActivityType::AccountUnlocked => "Unlocked a user account.".into(),
+            ActivityType::MfaEnabled => "Enabled two-factor authentication.".into(),
+            ActivityType::MfaDisabled => "Disabled two-factor authentication.".into(),
+            ActivityType::SettingsUpdated => "Updated organisation settings.".into(),
+            ActivityType::ApiKeyCreated => "Created an API key.".into(),
+            ActivityType::ApiKeyRevoked => "Revoked an API key.".into(),
+            ActivityType::ApiKeyUsed => "Used an API key.".into(),
+            ActivityType::SigningKeyRotated => "Rotated the token signing key.".into(),
+            ActivityType::ImpersonationStarted => "Started viewing the app as a user.".into(),
+            ActivityType::ImpersonationEnded => "Stopped viewing the app as a user.".into(),
+            ActivityType::ImpersonatedRequest => "Made a request while viewing as a user.".into(),
+            ActivityType::UserInvited => "Invited a new user.".into(),
+            ActivityType::InviteAccepted => "Accepted an invitation.".into(),
+            ActivityType::ApplicationApproved => "Approved a registration application.".into(),
+            ActivityType::ApplicationRejected => "Rejected a registration application.".into(),
+            ActivityType::ImportProfileSaved => "Saved an import profile.".into(),
+            ActivityType::ImportProfileDeleted => "Deleted an import profile.".into(),
+            ActivityType::UserDeleted => "Deleted a user.".into(),
+            ActivityType::UserRestored => "Restored a deleted user.".into(),
+            ActivityType::UserPurged => "Permanently removed a deleted user.".into(),
+            ActivityType::RegNosRenumbered => "Renumbered registration numbers.".into(),
+            ActivityType::BulkUserAction => "Applied an action to several users.".into(),
+            ActivityType::CustomFieldSaved => "Saved a custom profile field.".into(),
+            ActivityType::CustomFieldDeleted => "Deleted a custom profile field.".into(),
+            ActivityType::PersonalDataExported => "Downloaded a user's personal data.".into(),
+            ActivityType::UserAnonymised => "Anonymised a user's personal data.".into(),
         }
     }
 }
@@ -82,7 +144,7 @@ impl FromSql<Text, diesel::pg::Pg> for ActivityType {
 impl ToSql<Text, diesel::pg::Pg> for ActivityType {
     fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
         let s = serde_json::to_string(self)?;
-        out.write(s.as_bytes())?;
+        out.write_all(s.as_bytes())?;
         Ok(serialize::IsNull::No)
     }
 }
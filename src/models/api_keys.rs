@@ -0,0 +1,46 @@
+use super::*;
+use crate::models::permissions::Permission;
+
+/// A key for non-browser clients such as the check-in kiosk. Only the SHA-256 of the key is
+/// stored; `prefix` is kept in the clear so admins can tell keys apart.
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Selectable, Identifiable, Insertable)]
+#[diesel(table_name = crate::schema::api_keys)]
+pub struct ApiKey {
+    pub id: Uuid,
+    pub name: String,
+    pub prefix: String,
+    #[serde(skip_serializing)]
+    pub key_hash: String,
+    /// The only permissions requests made with the key can use.
+    pub scopes: Vec<Permission>,
+    /// The admin who issued the key. Requests made with it act on their behalf.
+    pub created_by: Uuid,
+    pub expires_at: NaiveDateTime,
+    pub last_used_at: Option<NaiveDateTime>,
+    pub revoked_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
+
+impl ApiKey {
+    pub fn new(
+        name: String,
+        prefix: String,
+        key_hash: String,
+        scopes: Vec<Permission>,
+        created_by: Uuid,
+        expires_at: NaiveDateTime,
+    ) -> Self {
+        Self {
+            id: Uuid::now_v7(),
+            name,
+            prefix,
+            key_hash,
+            scopes,
+            created_by,
+            expires_at,
+            last_used_at: None,
+            revoked_at: None,
+            created_at: chrono::Utc::now().naive_utc(),
+        }
+    }
+}
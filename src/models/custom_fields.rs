@@ -0,0 +1,109 @@
+use super::*;
+use diesel::{deserialize::FromSqlRow, expression::AsExpression, sql_types::Text};
+
+/// The kind of value a custom field holds, and so how it is stored in `users.custom_fields`.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Serialize,
+    Deserialize,
+    AsExpression,
+    FromSqlRow,
+    PartialEq,
+    Eq,
+    utoipa::ToSchema,
+)]
+#[diesel(sql_type = Text)]
+#[serde(rename_all = "lowercase")]
+pub enum CustomFieldType {
+    Text,
+    Number,
+    Boolean,
+    /// Stored as `YYYY-MM-DD`.
+    Date,
+    /// One of the field's `options`.
+    Select,
+}
+
+impl FromSql<Text, diesel::pg::Pg> for CustomFieldType {
+    fn from_sql(bytes: diesel::pg::PgValue<'_>) -> deserialize::Result<Self> {
+        let s = std::str::from_utf8(bytes.as_bytes())?;
+        serde_json::from_str(s).map_err(Into::into)
+    }
+}
+
+impl ToSql<Text, diesel::pg::Pg> for CustomFieldType {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        let s = serde_json::to_string(self)?;
+        out.write_all(s.as_bytes())?;
+        Ok(serialize::IsNull::No)
+    }
+}
+
+/// Who can see a custom field's value, and who can change it.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Serialize,
+    Deserialize,
+    AsExpression,
+    FromSqlRow,
+    PartialEq,
+    Eq,
+    utoipa::ToSchema,
+)]
+#[diesel(sql_type = Text)]
+#[serde(rename_all = "lowercase")]
+pub enum FieldVisibility {
+    /// Shown to everyone who can see the user; the user keeps it up to date.
+    Public,
+    /// Shown only to the user and to user managers; the user keeps it up to date.
+    Private,
+    /// Shown to and set by user managers only.
+    Admin,
+}
+
+impl FromSql<Text, diesel::pg::Pg> for FieldVisibility {
+    fn from_sql(bytes: diesel::pg::PgValue<'_>) -> deserialize::Result<Self> {
+        let s = std::str::from_utf8(bytes.as_bytes())?;
+        serde_json::from_str(s).map_err(Into::into)
+    }
+}
+
+impl ToSql<Text, diesel::pg::Pg> for FieldVisibility {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        let s = serde_json::to_string(self)?;
+        out.write_all(s.as_bytes())?;
+        Ok(serialize::IsNull::No)
+    }
+}
+
+/// An admin-defined profile field. Values live in `users.custom_fields` under `key`.
+#[derive(
+    Debug,
+    Clone,
+    Serialize,
+    Deserialize,
+    Queryable,
+    Selectable,
+    Identifiable,
+    Insertable,
+    AsChangeset,
+    utoipa::ToSchema,
+)]
+#[diesel(table_name = crate::schema::custom_fields)]
+pub struct CustomField {
+    pub id: Uuid,
+    pub key: String,
+    pub label: String,
+    pub field_type: CustomFieldType,
+    pub options: Vec<String>,
+    pub required: bool,
+    pub visibility: FieldVisibility,
+    /// Fields are listed, and exported, in ascending position.
+    pub position: i32,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
@@ -63,7 +63,7 @@ impl FromSql<Text, diesel::pg::Pg> for Location {
 impl ToSql<Text, diesel::pg::Pg> for Location {
     fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
         let s = serde_json::to_string(self)?;
-        out.write(s.as_bytes())?;
+        out.write_all(s.as_bytes())?;
         Ok(serialize::IsNull::No)
     }
 }
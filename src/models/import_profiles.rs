@@ -0,0 +1,87 @@
+use super::*;
+use diesel::{deserialize::FromSqlRow, expression::AsExpression, sql_types::Text};
+use serde_json::Value;
+
+/// What an import profile reads.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Serialize,
+    Deserialize,
+    AsExpression,
+    FromSqlRow,
+    PartialEq,
+    Eq,
+    utoipa::ToSchema,
+)]
+#[diesel(sql_type = Text)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportKind {
+    Users,
+    Roster,
+}
+
+impl ImportKind {
+    /// The fields a file of this kind is read into, and whether each is required.
+    pub fn fields(&self) -> &'static [(&'static str, bool)] {
+        match self {
+            ImportKind::Users => &[
+                ("first_name", true),
+                ("last_name", true),
+                ("email", true),
+                ("dob", false),
+                ("year_joined", true),
+                ("gender", false),
+                ("phone", false),
+                ("reg_no", false),
+            ],
+            ImportKind::Roster => &[("first_name", true), ("last_name", true), ("hall", true)],
+        }
+    }
+}
+
+impl FromSql<Text, diesel::pg::Pg> for ImportKind {
+    fn from_sql(bytes: diesel::pg::PgValue<'_>) -> deserialize::Result<Self> {
+        let s = std::str::from_utf8(bytes.as_bytes())?;
+        serde_json::from_str(s).map_err(Into::into)
+    }
+}
+
+impl ToSql<Text, diesel::pg::Pg> for ImportKind {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        let s = serde_json::to_string(self)?;
+        out.write_all(s.as_bytes())?;
+        Ok(serialize::IsNull::No)
+    }
+}
+
+/// A saved description of an import file layout: which header holds each field, the date
+/// format, transforms to clean values up with, and which existing values an update may
+/// overwrite. `column_map`, `transforms` and `overwrite` hold the JSON of `ImportProfileDto`'s
+/// maps.
+#[derive(
+    Debug,
+    Clone,
+    Serialize,
+    Deserialize,
+    Queryable,
+    Selectable,
+    Identifiable,
+    Insertable,
+    AsChangeset,
+)]
+#[diesel(table_name = crate::schema::import_profiles)]
+pub struct ImportProfile {
+    pub id: Uuid,
+    pub name: String,
+    pub kind: ImportKind,
+    pub column_map: Value,
+    pub date_format: Option<String>,
+    pub transforms: Value,
+    /// Cleared when the admin who saved the profile is purged.
+    pub created_by: Option<Uuid>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+    pub overwrite: Value,
+}
@@ -0,0 +1,43 @@
+use super::*;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Selectable, Identifiable, Insertable)]
+#[diesel(table_name = crate::schema::login_throttles)]
+#[diesel(primary_key(key))]
+pub struct LoginThrottle {
+    pub key: String,
+    pub failures: i32,
+    pub locked_until: Option<NaiveDateTime>,
+    pub last_failure_at: NaiveDateTime,
+}
+
+impl LoginThrottle {
+    pub fn user_key(user_id: Uuid) -> String {
+        format!("user:{}", user_id)
+    }
+
+    /// IPv6 clients usually hold a whole /64, so they share one key per /64 rather than
+    /// getting a fresh allowance for every address in it.
+    pub fn ip_key(ip: std::net::IpAddr) -> String {
+        match ip {
+            std::net::IpAddr::V4(ip) => format!("ip:{}", ip),
+            std::net::IpAddr::V6(ip) => {
+                let [a, b, c, d, ..] = ip.segments();
+                format!("ip:{:x}:{:x}:{:x}:{:x}::/64", a, b, c, d)
+            }
+        }
+    }
+
+    pub fn application_key(ip: std::net::IpAddr) -> String {
+        format!("apply:{}", Self::ip_key(ip))
+    }
+
+    /// The first failure recorded against `key`.
+    pub fn new(key: String) -> Self {
+        Self {
+            key,
+            failures: 1,
+            locked_until: None,
+            last_failure_at: chrono::Utc::now().naive_utc(),
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use super::*;
+
+/// TOTP enrollment of a user. The row exists from setup onwards; two-factor is only in
+/// force once `enabled_at` is set by confirming a first code.
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Selectable, Identifiable, Insertable)]
+#[diesel(table_name = crate::schema::user_mfa)]
+#[diesel(primary_key(user_id))]
+pub struct UserMfa {
+    pub user_id: Uuid,
+    /// Base32 shared secret. It has to be readable to verify codes, so it is not hashed.
+    #[serde(skip_serializing)]
+    pub secret: String,
+    pub enabled_at: Option<NaiveDateTime>,
+    /// The last accepted 30-second time step; a code is never accepted twice.
+    pub last_used_step: Option<i64>,
+    pub created_at: NaiveDateTime,
+}
+
+impl UserMfa {
+    pub fn new(user_id: Uuid, secret: String) -> Self {
+        Self {
+            user_id,
+            secret,
+            enabled_at: None,
+            last_used_step: None,
+            created_at: chrono::Utc::now().naive_utc(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Selectable, Identifiable, Insertable)]
+#[diesel(table_name = crate::schema::mfa_recovery_codes)]
+pub struct MfaRecoveryCode {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub code_hash: String,
+    pub used_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
+
+impl MfaRecoveryCode {
+    pub fn new(user_id: Uuid, code_hash: String) -> Self {
+        Self {
+            id: Uuid::now_v7(),
+            user_id,
+            code_hash,
+            used_at: None,
+            created_at: chrono::Utc::now().naive_utc(),
+        }
+    }
+}
@@ -1,11 +1,24 @@
 pub mod activity_logs;
+pub mod api_keys;
 pub mod count_logs;
 pub mod counter;
+pub mod custom_fields;
 pub mod events;
+pub mod import_profiles;
+pub mod login_throttles;
+pub mod mfa;
+pub mod oauth_accounts;
+pub mod password_resets;
+pub mod permissions;
+pub mod refresh_tokens;
+pub mod registration_applications;
 pub mod roster;
+pub mod sessions;
+pub mod settings;
 pub mod suggestion_comments;
 pub mod suggestions;
 pub mod user_attendance;
+pub mod user_invites;
 pub mod users;
 pub mod users_roster;
 
@@ -0,0 +1,26 @@
+use super::*;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Selectable, Identifiable, Insertable)]
+#[diesel(table_name = crate::schema::oauth_accounts)]
+pub struct OAuthAccount {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub provider: String,
+    pub subject: String,
+    pub email: String,
+    pub created_at: NaiveDateTime,
+}
+
+impl OAuthAccount {
+    /// `subject` is the provider's stable account id, which survives email changes on their side.
+    pub fn new(user_id: Uuid, provider: &str, subject: String, email: String) -> Self {
+        Self {
+            id: Uuid::now_v7(),
+            user_id,
+            provider: provider.to_string(),
+            subject,
+            email,
+            created_at: chrono::Utc::now().naive_utc(),
+        }
+    }
+}
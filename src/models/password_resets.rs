@@ -0,0 +1,28 @@
+use super::*;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Selectable, Identifiable, Insertable)]
+#[diesel(table_name = crate::schema::password_resets)]
+pub struct PasswordReset {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub code_hash: String,
+    pub expires_at: NaiveDateTime,
+    pub used_at: Option<NaiveDateTime>,
+    pub attempts: i32,
+    pub created_at: NaiveDateTime,
+}
+
+impl PasswordReset {
+    /// Only the hash of the code is stored; the plain code goes out by email and nowhere else.
+    pub fn new(user_id: Uuid, code_hash: String, expires_at: NaiveDateTime) -> Self {
+        Self {
+            id: Uuid::now_v7(),
+            user_id,
+            code_hash,
+            expires_at,
+            used_at: None,
+            attempts: 0,
+            created_at: chrono::Utc::now().naive_utc(),
+        }
+    }
+}
@@ -0,0 +1,29 @@
+use super::*;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Selectable, Identifiable, Insertable)]
+#[diesel(table_name = crate::schema::refresh_tokens)]
+pub struct RefreshToken {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub family_id: Uuid,
+    pub expires_at: NaiveDateTime,
+    pub used_at: Option<NaiveDateTime>,
+    pub revoked_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
+
+impl RefreshToken {
+    /// A refresh token belongs to a family that is started at login and carried over on
+    /// every rotation, so that a replayed token can take the whole chain down with it.
+    pub fn new(user_id: Uuid, family_id: Uuid, expires_at: NaiveDateTime) -> Self {
+        Self {
+            id: Uuid::now_v7(),
+            user_id,
+            family_id,
+            expires_at,
+            used_at: None,
+            revoked_at: None,
+            created_at: chrono::Utc::now().naive_utc(),
+        }
+    }
+}
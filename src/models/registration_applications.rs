@@ -0,0 +1,68 @@
+use super::*;
+use diesel::{deserialize::FromSqlRow, expression::AsExpression, sql_types::Text};
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Serialize,
+    Deserialize,
+    AsExpression,
+    FromSqlRow,
+    PartialEq,
+    Eq,
+    utoipa::ToSchema,
+)]
+#[diesel(sql_type = Text)]
+#[serde(rename_all = "lowercase")]
+pub enum ApplicationStatus {
+    Pending,
+    Approved,
+    Rejected,
+}
+
+impl FromSql<Text, diesel::pg::Pg> for ApplicationStatus {
+    fn from_sql(bytes: diesel::pg::PgValue<'_>) -> deserialize::Result<Self> {
+        let s = std::str::from_utf8(bytes.as_bytes())?;
+        serde_json::from_str(s).map_err(Into::into)
+    }
+}
+
+impl ToSql<Text, diesel::pg::Pg> for ApplicationStatus {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        let s = serde_json::to_string(self)?;
+        out.write_all(s.as_bytes())?;
+        Ok(serialize::IsNull::No)
+    }
+}
+
+/// A would-be usher's self-registration, waiting for an admin to approve or reject it.
+/// Approval creates the user and links it through `user_id`.
+#[derive(
+    Debug,
+    Clone,
+    Serialize,
+    Deserialize,
+    Queryable,
+    Selectable,
+    Identifiable,
+    Insertable,
+    utoipa::ToSchema,
+)]
+#[diesel(table_name = crate::schema::registration_applications)]
+pub struct RegistrationApplication {
+    pub id: Uuid,
+    pub first_name: String,
+    pub last_name: String,
+    pub email: String,
+    pub dob: Option<NaiveDateTime>,
+    pub year_joined: String,
+    pub gender: Option<String>,
+    pub phone: Option<String>,
+    pub status: ApplicationStatus,
+    pub rejection_reason: Option<String>,
+    pub reviewed_by: Option<Uuid>,
+    pub reviewed_at: Option<NaiveDateTime>,
+    pub user_id: Option<Uuid>,
+    pub created_at: NaiveDateTime,
+}
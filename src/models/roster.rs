@@ -70,7 +70,7 @@ impl FromSql<Text, diesel::pg::Pg> for Hall {
 impl ToSql<Text, diesel::pg::Pg> for Hall {
     fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
         let s = serde_json::to_string(self)?;
-        out.write(s.as_bytes())?;
+        out.write_all(s.as_bytes())?;
         Ok(serialize::IsNull::No)
     }
 }
@@ -87,6 +87,21 @@ impl Hall {
         ]
     }
 
+    /// Reads a hall written the way people write it, e.g. "Main Hall", "hall one" or
+    /// "MainHall".
+    pub fn from_label(label: &str) -> Option<Hall> {
+        let normalise = |s: &str| {
+            s.chars()
+                .filter(|c| c.is_alphanumeric())
+                .collect::<String>()
+                .to_lowercase()
+        };
+        let label = normalise(label);
+        Hall::all()
+            .into_iter()
+            .find(|hall| serde_json::to_string(hall).is_ok_and(|name| normalise(&name) == label))
+    }
+
     fn get_available_halls(served_halls: &[Hall], available: Vec<Hall>) -> Vec<Hall> {
         available
             .into_iter()
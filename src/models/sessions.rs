@@ -0,0 +1,59 @@
+use super::*;
+use crate::auth::middleware::ConnectionInfo;
+
+/// A login session. The session id is carried in the access token as the `jti` claim and
+/// doubles as the `family_id` of the refresh tokens rotated under it.
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Selectable, Identifiable, Insertable)]
+#[diesel(table_name = crate::schema::sessions)]
+pub struct Session {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub created_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+    pub revoked_at: Option<NaiveDateTime>,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub last_seen_at: NaiveDateTime,
+    /// Whether the login behind this session passed a second factor.
+    pub mfa_verified: bool,
+    /// The admin who opened this session to act as `user_id`, if any.
+    pub impersonator_id: Option<Uuid>,
+}
+
+impl Session {
+    pub fn new(user_id: Uuid, expires_at: NaiveDateTime) -> Self {
+        let now = chrono::Utc::now().naive_utc();
+        Self {
+            id: Uuid::now_v7(),
+            user_id,
+            created_at: now,
+            expires_at,
+            revoked_at: None,
+            ip_address: None,
+            user_agent: None,
+            last_seen_at: now,
+            mfa_verified: false,
+            impersonator_id: None,
+        }
+    }
+
+    pub fn set_connection_info(&mut self, info: &ConnectionInfo) -> &mut Self {
+        self.ip_address = Some(info.ip.clone());
+        self.user_agent = Some(info.user_agent.clone());
+        self
+    }
+
+    pub fn set_mfa_verified(&mut self, mfa_verified: bool) -> &mut Self {
+        self.mfa_verified = mfa_verified;
+        self
+    }
+
+    pub fn set_impersonator_id(&mut self, impersonator_id: Uuid) -> &mut Self {
+        self.impersonator_id = Some(impersonator_id);
+        self
+    }
+
+    pub fn finish(&self) -> Self {
+        self.clone()
+    }
+}
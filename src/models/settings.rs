@@ -0,0 +1,23 @@
+use super::*;
+
+/// A single organisation-wide setting, stored as JSON under its key.
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Selectable, Identifiable, Insertable)]
+#[diesel(table_name = crate::schema::settings)]
+#[diesel(primary_key(key))]
+pub struct Setting {
+    pub key: String,
+    pub value: serde_json::Value,
+    pub updated_by: Option<Uuid>,
+    pub updated_at: NaiveDateTime,
+}
+
+impl Setting {
+    pub fn new(key: &str, value: serde_json::Value, updated_by: Uuid) -> Self {
+        Self {
+            key: key.to_string(),
+            value,
+            updated_by: Some(updated_by),
+            updated_at: chrono::Utc::now().naive_utc(),
+        }
+    }
+}
@@ -0,0 +1,38 @@
+use super::*;
+
+/// A single-use invitation for a user created without a password. Only the SHA-256 of the
+/// token is stored; the token itself goes out by email.
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Selectable, Identifiable, Insertable)]
+#[diesel(table_name = crate::schema::user_invites)]
+pub struct UserInvite {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    #[serde(skip_serializing)]
+    pub token_hash: String,
+    /// Cleared when the admin who sent the invite is purged.
+    pub invited_by: Option<Uuid>,
+    pub expires_at: NaiveDateTime,
+    pub accepted_at: Option<NaiveDateTime>,
+    pub revoked_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
+
+impl UserInvite {
+    pub fn new(
+        user_id: Uuid,
+        token_hash: String,
+        invited_by: Uuid,
+        expires_at: NaiveDateTime,
+    ) -> Self {
+        Self {
+            id: Uuid::now_v7(),
+            user_id,
+            token_hash,
+            invited_by: Some(invited_by),
+            expires_at,
+            accepted_at: None,
+            revoked_at: None,
+            created_at: chrono::Utc::now().naive_utc(),
+        }
+    }
+}
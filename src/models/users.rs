@@ -42,11 +42,41 @@ pub struct User {
     pub country: Option<String>,
     pub phone: Option<String>,
     pub hall_derivation: i32,
+    /// Set on accounts created with a placeholder password; every route but change-password
+    /// is refused until the user picks their own.
+    pub must_change_password: bool,
 }
 
 impl User {
-    pub fn set_reg_no(&mut self, code: i64) {
-        self.reg_no = format!("{}/KUD/{:03}", self.year_joined, code);
+    /// Renders a reg_no template: `{year}` is the year joined, `{yy}` its last two digits,
+    /// `{seq}` the code and `{seq:0N}` the code zero-padded to N digits. Templates are checked
+    /// when saved, so anything else in braces is copied as it is.
+    pub fn format_reg_no(format: &str, year: &str, code: i64) -> String {
+        let mut reg_no = String::with_capacity(format.len() + 8);
+        let mut rest = format;
+        while let Some(start) = rest.find('{') {
+            reg_no.push_str(&rest[..start]);
+            let Some(end) = rest[start..].find('}').map(|end| start + end) else {
+                rest = &rest[start..];
+                break;
+            };
+            let token = &rest[start + 1..end];
+            match token {
+                "year" => reg_no.push_str(year),
+                "yy" => reg_no.extend(year.chars().skip(year.chars().count().saturating_sub(2))),
+                "seq" => reg_no.push_str(&code.to_string()),
+                _ => match token
+                    .strip_prefix("seq:0")
+                    .and_then(|w| w.parse::<usize>().ok())
+                {
+                    Some(width) => reg_no.push_str(&format!("{code:0width$}")),
+                    None => reg_no.push_str(&rest[start..=end]),
+                },
+            }
+            rest = &rest[end + 1..];
+        }
+        reg_no.push_str(rest);
+        reg_no
     }
 }
 
@@ -71,6 +101,12 @@ pub enum Role {
     Technical,
 }
 
+impl Role {
+    pub fn all() -> Vec<Role> {
+        vec![Role::Admin, Role::User, Role::Technical]
+    }
+}
+
 impl FromSql<Text, diesel::pg::Pg> for Role {
     fn from_sql(bytes: diesel::pg::PgValue<'_>) -> deserialize::Result<Self> {
         let s = std::str::from_utf8(bytes.as_bytes())?;
@@ -81,7 +117,35 @@ impl FromSql<Text, diesel::pg::Pg> for Role {
 impl ToSql<Text, diesel::pg::Pg> for Role {
     fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
         let s = serde_json::to_string(self)?;
-        out.write(s.as_bytes())?;
+        out.write_all(s.as_bytes())?;
         Ok(serialize::IsNull::No)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_reg_no_fills_placeholders() {
+        assert_eq!(
+            User::format_reg_no("{year}/KUD/{seq:03}", "2024", 7),
+            "2024/KUD/007"
+        );
+        assert_eq!(User::format_reg_no("K{yy}-{seq}", "2024", 1234), "K24-1234");
+    }
+
+    #[test]
+    fn format_reg_no_does_not_truncate_wide_codes() {
+        assert_eq!(User::format_reg_no("{yy}{seq:02}", "2024", 1234), "241234");
+    }
+
+    #[test]
+    fn format_reg_no_keeps_unknown_text() {
+        assert_eq!(
+            User::format_reg_no("{dept}/{year}/{seq}", "2024", 5),
+            "{dept}/2024/5"
+        );
+        assert_eq!(User::format_reg_no("{year}/{seq", "2024", 5), "2024/{seq");
+    }
+}
@@ -12,6 +12,36 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    api_keys (id) {
+        id -> Uuid,
+        name -> Text,
+        prefix -> Text,
+        key_hash -> Text,
+        scopes -> Array<Text>,
+        created_by -> Uuid,
+        expires_at -> Timestamp,
+        last_used_at -> Nullable<Timestamp>,
+        revoked_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    custom_fields (id) {
+        id -> Uuid,
+        key -> Text,
+        label -> Text,
+        field_type -> Text,
+        options -> Array<Text>,
+        required -> Bool,
+        visibility -> Text,
+        position -> Int4,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     events (id) {
         id -> Uuid,
@@ -28,6 +58,110 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    import_profiles (id) {
+        id -> Uuid,
+        name -> Text,
+        kind -> Text,
+        column_map -> Jsonb,
+        date_format -> Nullable<Text>,
+        transforms -> Jsonb,
+        created_by -> Nullable<Uuid>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+        overwrite -> Jsonb,
+    }
+}
+
+diesel::table! {
+    login_throttles (key) {
+        key -> Text,
+        failures -> Int4,
+        locked_until -> Nullable<Timestamp>,
+        last_failure_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    mfa_recovery_codes (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        code_hash -> Text,
+        used_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    oauth_accounts (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        provider -> Text,
+        subject -> Text,
+        email -> Text,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    password_resets (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        code_hash -> Text,
+        expires_at -> Timestamp,
+        used_at -> Nullable<Timestamp>,
+        attempts -> Int4,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    refresh_tokens (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        family_id -> Uuid,
+        expires_at -> Timestamp,
+        used_at -> Nullable<Timestamp>,
+        revoked_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    reg_no_sequences (year) {
+        year -> Text,
+        last_value -> Int8,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    registration_applications (id) {
+        id -> Uuid,
+        first_name -> Text,
+        last_name -> Text,
+        email -> Text,
+        dob -> Nullable<Timestamp>,
+        year_joined -> Text,
+        gender -> Nullable<Text>,
+        phone -> Nullable<Text>,
+        status -> Text,
+        rejection_reason -> Nullable<Text>,
+        reviewed_by -> Nullable<Uuid>,
+        reviewed_at -> Nullable<Timestamp>,
+        user_id -> Nullable<Uuid>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    role_permissions (role, permission) {
+        role -> Text,
+        permission -> Text,
+        created_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     rosters (id) {
         id -> Uuid,
@@ -55,6 +189,30 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    sessions (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        created_at -> Timestamp,
+        expires_at -> Timestamp,
+        revoked_at -> Nullable<Timestamp>,
+        ip_address -> Nullable<Text>,
+        user_agent -> Nullable<Text>,
+        last_seen_at -> Timestamp,
+        mfa_verified -> Bool,
+        impersonator_id -> Nullable<Uuid>,
+    }
+}
+
+diesel::table! {
+    settings (key) {
+        key -> Text,
+        value -> Jsonb,
+        updated_by -> Nullable<Uuid>,
+        updated_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     user_attendance (id) {
         id -> Uuid,
@@ -71,6 +229,29 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    user_invites (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        token_hash -> Text,
+        invited_by -> Nullable<Uuid>,
+        expires_at -> Timestamp,
+        accepted_at -> Nullable<Timestamp>,
+        revoked_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    user_mfa (user_id) {
+        user_id -> Uuid,
+        secret -> Text,
+        enabled_at -> Nullable<Timestamp>,
+        last_used_step -> Nullable<Int8>,
+        created_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     users (id) {
         id -> Uuid,
@@ -98,6 +279,12 @@ diesel::table! {
         country -> Nullable<Text>,
         phone -> Nullable<Text>,
         hall_derivation -> Int4,
+        must_change_password -> Bool,
+        deleted_at -> Nullable<Timestamp>,
+        deleted_by -> Nullable<Uuid>,
+        custom_fields -> Jsonb,
+        emergency_contacts -> Jsonb,
+        anonymised_at -> Nullable<Timestamp>,
     }
 }
 
@@ -113,17 +300,40 @@ diesel::table! {
 }
 
 diesel::joinable!(activity_logs -> users (user_id));
+diesel::joinable!(api_keys -> users (created_by));
 diesel::joinable!(events -> users (created_by));
+diesel::joinable!(mfa_recovery_codes -> users (user_id));
+diesel::joinable!(oauth_accounts -> users (user_id));
+diesel::joinable!(password_resets -> users (user_id));
+diesel::joinable!(refresh_tokens -> users (user_id));
+diesel::joinable!(sessions -> users (user_id));
 diesel::joinable!(user_attendance -> events (event_id));
 diesel::joinable!(user_attendance -> users (user_id));
+diesel::joinable!(user_invites -> users (user_id));
+diesel::joinable!(user_mfa -> users (user_id));
 diesel::joinable!(users_rosters -> rosters (roster_id));
 diesel::joinable!(users_rosters -> users (user_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
     activity_logs,
+    api_keys,
+    custom_fields,
     events,
+    import_profiles,
+    login_throttles,
+    mfa_recovery_codes,
+    oauth_accounts,
+    password_resets,
+    refresh_tokens,
+    reg_no_sequences,
+    registration_applications,
+    role_permissions,
     rosters,
+    sessions,
+    settings,
     user_attendance,
+    user_invites,
+    user_mfa,
     users,
     users_rosters,
 );
@@ -15,10 +15,39 @@ use diesel_async::RunQueryDsl;
 use std::sync::Arc;
 use uuid::Uuid;
 
+type LogRow = (
+    Uuid,
+    Uuid,
+    String,
+    String,
+    String,
+    crate::models::users::Role,
+    crate::models::activity_logs::ActivityType,
+    chrono::NaiveDateTime,
+);
+
+/// Inserts `payload`. Logs written while an admin is impersonating get the admin's id added
+/// to their details as `impersonator_id`.
 pub async fn emit_log<'a>(
-    payload: ActivityLog,
+    mut payload: ActivityLog,
     mut conn: &mut Connection<'a>,
 ) -> Result<(), ModuleError> {
+    if let Some(impersonator_id) = crate::auth::middleware::current_impersonator() {
+        match &mut payload.details {
+            serde_json::Value::Object(details) => {
+                details.insert("impersonator_id".into(), impersonator_id.to_string().into());
+            }
+            serde_json::Value::Null => {
+                payload.details = serde_json::json!({ "impersonator_id": impersonator_id });
+            }
+            details => {
+                payload.details = serde_json::json!({
+                    "impersonator_id": impersonator_id,
+                    "details": details.take(),
+                });
+            }
+        }
+    }
     diesel::insert_into(activity_logs::table)
         .values(&payload)
         .execute(&mut conn)
@@ -75,16 +104,7 @@ pub async fn get_logs(
     let total_count: i64 = count_query.count().get_result(&mut conn).await?;
 
     // Apply pagination and ordering
-    let results: Vec<(
-        Uuid,
-        Uuid,
-        String,
-        String,
-        String,
-        crate::models::users::Role,
-        crate::models::activity_logs::ActivityType,
-        chrono::NaiveDateTime,
-    )> = query
+    let results: Vec<LogRow> = query
         .order(activity_logs::created_at.desc())
         .limit(pagination.size as i64)
         .offset(pagination.offset() as i64)
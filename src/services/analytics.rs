@@ -78,6 +78,7 @@ pub async fn fetch_total_users(
     conn: &mut impl AsyncConnection<Backend = diesel::pg::Pg>,
 ) -> Result<Message<Vec<UserDto>>, ModuleError> {
     let total_users = schema::users::table
+        .filter(schema::users::deleted_at.is_null())
         .select(UserDto::as_select())
         .load::<UserDto>(conn)
         .await?;
@@ -113,6 +114,7 @@ pub async fn fetch_users_present_on_a_specific_day(
     let active_users: Vec<UserDto> = RunQueryDsl::load(
         users::table
             //.filter(users::is_active.eq(true))
+            .filter(users::deleted_at.is_null())
             .select(UserDto::as_select()),
         conn,
     )
@@ -153,6 +155,7 @@ pub async fn fetch_upcoming_birthdays(
         users::table
             .filter(users::is_active.eq(true))
             .filter(users::dob.is_not_null())
+            .filter(users::deleted_at.is_null())
             .select(UserDto::as_select()),
         conn,
     )
@@ -197,6 +200,7 @@ pub async fn fetch_attendance_rates(
 
     // 2. Fetch all users with their roles and status
     let all_users: Vec<(uuid::Uuid, Role, bool)> = users::table
+        .filter(users::deleted_at.is_null())
         .select((users::id, users::role, users::is_active))
         .load::<(uuid::Uuid, Role, bool)>(conn)
         .await?;
@@ -318,6 +322,7 @@ pub async fn fetch_event_stats_report(
                 .and(user_attendance::event_id.eq(Some(event_id)))),
         )
         .filter(users::is_active.eq(true))
+        .filter(users::deleted_at.is_null())
         .filter(user_attendance::id.is_null())
         .select(UserDto::as_select())
         .load::<UserDto>(conn)
@@ -0,0 +1,228 @@
+use super::*;
+use crate::PermissionCache;
+use crate::auth::jwt::{ApiKeyGrant, Claims};
+use crate::auth::middleware::ConnectionInfo;
+use crate::dto::api_keys::*;
+use crate::models::activity_logs::{ActivityLog, ActivityType};
+use crate::models::api_keys::ApiKey;
+use crate::models::permissions::Permission;
+use crate::models::users::Role;
+
+/// Every key starts with this, which is how the `Claims` extractor tells keys from JWTs.
+const API_KEY_PREFIX: &str = "kud_";
+const API_KEY_RANDOM_LENGTH: usize = 40;
+/// Characters of the key kept in the clear for listings, `kud_` included.
+const DISPLAY_PREFIX_LENGTH: usize = 12;
+const MAX_EXPIRY_DAYS: i64 = 365;
+
+pub fn is_api_key(token: &str) -> bool {
+    token.starts_with(API_KEY_PREFIX)
+}
+
+/// Issues a key. The issuer can only hand out permissions their own role holds.
+pub async fn create_api_key(
+    pool: Arc<Pool>,
+    permissions: &PermissionCache,
+    role: Role,
+    payload: CreateApiKeyRequest,
+    performer_id: Uuid,
+) -> Result<CreatedApiKeyDto, ModuleError> {
+    let name = payload.name.trim().to_string();
+    if name.is_empty() {
+        return Err(ModuleError::BadRequest("API key name is required".into()));
+    }
+    if payload.scopes.is_empty() {
+        return Err(ModuleError::BadRequest(
+            "An API key needs at least one scope".into(),
+        ));
+    }
+    if !(1..=MAX_EXPIRY_DAYS).contains(&payload.expires_in_days) {
+        return Err(ModuleError::BadRequest(
+            format!("expires_in_days must be between 1 and {MAX_EXPIRY_DAYS}").into(),
+        ));
+    }
+    for scope in &payload.scopes {
+        if !crate::services::permissions::has_permission(permissions, &role, *scope).await {
+            return Err(ModuleError::PermissionDenied);
+        }
+    }
+
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|_| ModuleError::InternalError(POOL_ERROR_MSG.into()))?;
+
+    let key = format!(
+        "{API_KEY_PREFIX}{}",
+        crate::helpers::random_token(API_KEY_RANDOM_LENGTH)
+    );
+
+    let mut scopes: Vec<Permission> = Vec::new();
+    for scope in payload.scopes {
+        if !scopes.contains(&scope) {
+            scopes.push(scope);
+        }
+    }
+    let expires_at =
+        chrono::Utc::now().naive_utc() + chrono::Duration::days(payload.expires_in_days);
+    let api_key = ApiKey::new(
+        name,
+        key[..DISPLAY_PREFIX_LENGTH].to_string(),
+        crate::helpers::token_hash(&key),
+        scopes,
+        performer_id,
+        expires_at,
+    );
+    diesel::insert_into(schema::api_keys::table)
+        .values(&api_key)
+        .execute(&mut conn)
+        .await?;
+
+    let log = ActivityLog::new(ActivityType::ApiKeyCreated, performer_id)
+        .set_target_id(api_key.id)
+        .set_target_type("ApiKey".into())
+        .set_details(serde_json::json!({
+            "name": api_key.name,
+            "scopes": api_key.scopes,
+            "expires_at": api_key.expires_at,
+        }))
+        .finish();
+    crate::services::activity_logs::emit_log(log, &mut conn).await?;
+
+    Ok(CreatedApiKeyDto {
+        key,
+        api_key: ApiKeyDto {
+            id: api_key.id,
+            name: api_key.name,
+            prefix: api_key.prefix,
+            scopes: api_key.scopes,
+            created_by: api_key.created_by,
+            expires_at: api_key.expires_at,
+            last_used_at: api_key.last_used_at,
+            revoked_at: api_key.revoked_at,
+            created_at: api_key.created_at,
+        },
+    })
+}
+
+pub async fn get_api_keys(pool: Arc<Pool>) -> Result<Vec<ApiKeyDto>, ModuleError> {
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|_| ModuleError::InternalError(POOL_ERROR_MSG.into()))?;
+
+    let keys = schema::api_keys::table
+        .order(schema::api_keys::created_at.desc())
+        .select(ApiKeyDto::as_select())
+        .load::<ApiKeyDto>(&mut conn)
+        .await?;
+    Ok(keys)
+}
+
+pub async fn revoke_api_key(
+    pool: Arc<Pool>,
+    id: Uuid,
+    performer_id: Uuid,
+) -> Result<Message<()>, ModuleError> {
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|_| ModuleError::InternalError(POOL_ERROR_MSG.into()))?;
+
+    let revoked = diesel::update(
+        schema::api_keys::table
+            .find(id)
+            .filter(schema::api_keys::revoked_at.is_null()),
+    )
+    .set(schema::api_keys::revoked_at.eq(chrono::Utc::now().naive_utc()))
+    .execute(&mut conn)
+    .await?;
+    if revoked == 0 {
+        return Err(ModuleError::ResourceNotFound(
+            "API key not found or already revoked".into(),
+        ));
+    }
+
+    let log = ActivityLog::new(ActivityType::ApiKeyRevoked, performer_id)
+        .set_target_id(id)
+        .set_target_type("ApiKey".into())
+        .finish();
+    crate::services::activity_logs::emit_log(log, &mut conn).await?;
+
+    Ok("API key revoked".into())
+}
+
+/// Resolves a key sent as a bearer token into `Claims` for a route that admits keys with
+/// `scope`. The request acts as the issuing admin but only with the key's scopes that the
+/// admin's role still holds, so demotions and permission changes reach their keys at once.
+/// Every use is recorded as `ApiKeyUsed`.
+pub async fn authenticate(
+    conn: &mut crate::Connection<'_>,
+    permissions: &PermissionCache,
+    key: &str,
+    scope: Permission,
+    parts: &axum::http::request::Parts,
+) -> Result<Claims, ModuleError> {
+    let now = chrono::Utc::now().naive_utc();
+    let (mut api_key, role) = schema::api_keys::table
+        .inner_join(schema::users::table)
+        .filter(schema::api_keys::key_hash.eq(crate::helpers::token_hash(key)))
+        .filter(schema::api_keys::revoked_at.is_null())
+        .filter(schema::api_keys::expires_at.gt(now))
+        .filter(schema::users::is_active.eq(true))
+        .filter(schema::users::deleted_at.is_null())
+        .select((ApiKey::as_select(), schema::users::role))
+        .first::<(ApiKey, Role)>(conn)
+        .await
+        .optional()?
+        .ok_or(ModuleError::InvalidToken)?;
+    let mut granted = Vec::with_capacity(api_key.scopes.len());
+    for permission in api_key.scopes {
+        if crate::services::permissions::has_permission(permissions, &role, permission).await {
+            granted.push(permission);
+        }
+    }
+    api_key.scopes = granted;
+    if !api_key.scopes.contains(&scope) {
+        return Err(ModuleError::PermissionDenied);
+    }
+
+    diesel::update(schema::api_keys::table.find(api_key.id))
+        .set(schema::api_keys::last_used_at.eq(now))
+        .execute(conn)
+        .await?;
+
+    let connection = parts.extensions.get::<ConnectionInfo>();
+    // Nested routers see the path with their prefix stripped.
+    let path = parts
+        .extensions
+        .get::<axum::extract::OriginalUri>()
+        .map_or(parts.uri.path(), |uri| uri.0.path());
+    let log = ActivityLog::new(ActivityType::ApiKeyUsed, api_key.created_by)
+        .set_target_id(api_key.id)
+        .set_target_type("ApiKey".into())
+        .set_details(serde_json::json!({
+            "name": api_key.name,
+            "method": parts.method.as_str(),
+            "path": path,
+            "ip_address": connection.map(|info| info.ip.clone()),
+            "user_agent": connection.map(|info| info.user_agent.clone()),
+        }))
+        .finish();
+    crate::services::activity_logs::emit_log(log, conn).await?;
+
+    Ok(Claims {
+        user_id: api_key.created_by,
+        exp: api_key.expires_at.and_utc().timestamp() as usize,
+        // Keys never act through a role; their scopes are checked instead.
+        role: Role::User,
+        jti: api_key.id,
+        mfa: false,
+        impersonator_id: None,
+        read_only: false,
+        api_key: Some(ApiKeyGrant {
+            id: api_key.id,
+            scopes: api_key.scopes,
+        }),
+    })
+}
@@ -0,0 +1,212 @@
+use std::io::Cursor;
+
+use axum::extract::Multipart;
+use image::codecs::jpeg::JpegEncoder;
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader, Limits, RgbImage};
+
+use super::*;
+use crate::dto::user::{AvatarDto, AvatarThumbnail};
+use crate::models::activity_logs::{ActivityLog, ActivityType};
+use crate::storage::Storage;
+
+/// Largest upload accepted, before any processing.
+pub const MAX_AVATAR_BYTES: usize = 5 * 1024 * 1024;
+/// Uploads wider or taller than this are refused rather than decoded.
+const MAX_AVATAR_DIMENSION: u32 = 8000;
+/// The stored sizes, largest first. The largest is the avatar itself, the rest thumbnails.
+pub const AVATAR_SIZES: [u32; 3] = [512, 128, 48];
+const JPEG_QUALITY: u8 = 85;
+
+fn key(user_id: Uuid, size: u32) -> String {
+    format!("avatars/{user_id}/{size}.jpg")
+}
+
+async fn read_image(mut multipart: Multipart) -> Result<Vec<u8>, ModuleError> {
+    let field = multipart
+        .next_field()
+        .await
+        .map_err(|e| ModuleError::BadRequest(e.body_text().into()))?
+        .ok_or(ModuleError::BadRequest("No image was uploaded".into()))?;
+    let data = field
+        .bytes()
+        .await
+        .map_err(|e| ModuleError::BadRequest(e.body_text().into()))?;
+    if data.is_empty() {
+        return Err(ModuleError::BadRequest(
+            "The uploaded image is empty".into(),
+        ));
+    }
+    if data.len() > MAX_AVATAR_BYTES {
+        return Err(ModuleError::BadRequest(
+            format!(
+                "Avatars must be at most {} MiB",
+                MAX_AVATAR_BYTES / 1024 / 1024
+            )
+            .into(),
+        ));
+    }
+    Ok(data.to_vec())
+}
+
+/// Decodes a JPEG, PNG or WebP upload, turns it upright and renders every size as a square
+/// JPEG. Only pixels are re-encoded, so EXIF data, GPS position included, never reaches storage.
+fn render(data: &[u8]) -> Result<Vec<(u32, Vec<u8>)>, ModuleError> {
+    let mut reader = ImageReader::new(Cursor::new(data))
+        .with_guessed_format()
+        .map_err(|_| decode_error(None))?;
+    match reader.format() {
+        Some(ImageFormat::Jpeg | ImageFormat::Png | ImageFormat::WebP) => {}
+        _ => {
+            return Err(ModuleError::BadRequest(
+                "Avatars must be JPEG, PNG or WebP images".into(),
+            ));
+        }
+    }
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_AVATAR_DIMENSION);
+    limits.max_image_height = Some(MAX_AVATAR_DIMENSION);
+    reader.limits(limits);
+
+    let mut decoder = reader.into_decoder().map_err(|e| decode_error(Some(e)))?;
+    let orientation = decoder
+        .orientation()
+        .unwrap_or(image::metadata::Orientation::NoTransforms);
+    let mut img = DynamicImage::from_decoder(decoder).map_err(|e| decode_error(Some(e)))?;
+    img.apply_orientation(orientation);
+
+    AVATAR_SIZES
+        .iter()
+        .map(|&size| {
+            let square =
+                flatten(&img.resize_to_fill(size, size, image::imageops::FilterType::Lanczos3));
+            let mut out = Vec::new();
+            square
+                .write_with_encoder(JpegEncoder::new_with_quality(&mut out, JPEG_QUALITY))
+                .map_err(|e| ModuleError::InternalError(e.to_string().into()))?;
+            Ok((size, out))
+        })
+        .collect()
+}
+
+fn decode_error(error: Option<image::ImageError>) -> ModuleError {
+    match error {
+        Some(image::ImageError::Limits(_)) => ModuleError::BadRequest(
+            format!("Avatars must be at most {MAX_AVATAR_DIMENSION} pixels on each side").into(),
+        ),
+        _ => ModuleError::BadRequest("The uploaded file is not a readable image".into()),
+    }
+}
+
+/// JPEG has no alpha channel; transparent areas become white instead of black.
+fn flatten(img: &DynamicImage) -> DynamicImage {
+    let rgba = img.to_rgba8();
+    let mut rgb = RgbImage::new(rgba.width(), rgba.height());
+    for (target, source) in rgb.pixels_mut().zip(rgba.pixels()) {
+        let alpha = source[3] as u32;
+        for channel in 0..3 {
+            target[channel] = ((source[channel] as u32 * alpha + 255 * (255 - alpha)) / 255) as u8;
+        }
+    }
+    DynamicImage::ImageRgb8(rgb)
+}
+
+/// Replaces the avatar of `user_id` with the uploaded image.
+pub async fn upload_avatar(
+    pool: Arc<Pool>,
+    storage: Arc<dyn Storage>,
+    multipart: Multipart,
+    user_id: Uuid,
+    performer_id: Uuid,
+) -> Result<AvatarDto, ModuleError> {
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|_| ModuleError::InternalError(POOL_ERROR_MSG.into()))?;
+    let exists = diesel::select(diesel::dsl::exists(schema::users::table.find(user_id)))
+        .get_result::<bool>(&mut conn)
+        .await?;
+    if !exists {
+        return Err(ModuleError::ResourceNotFound("User not found".into()));
+    }
+
+    let data = read_image(multipart).await?;
+    let rendered = tokio::task::spawn_blocking(move || render(&data))
+        .await
+        .map_err(|e| ModuleError::InternalError(e.to_string().into()))??;
+
+    // Files keep their names across uploads, so the URLs carry a version to defeat caches.
+    let version = chrono::Utc::now().timestamp_millis();
+    let mut thumbnails = Vec::new();
+    for (size, bytes) in rendered {
+        let url = storage
+            .put(&key(user_id, size), bytes, "image/jpeg")
+            .await?;
+        thumbnails.push(AvatarThumbnail {
+            size,
+            url: format!("{url}?v={version}"),
+        });
+    }
+    let avatar_url = thumbnails[0].url.clone();
+
+    diesel::update(schema::users::table.find(user_id))
+        .set((
+            schema::users::avatar_url.eq(&avatar_url),
+            schema::users::updated_at.eq(chrono::Local::now().naive_local()),
+        ))
+        .execute(&mut conn)
+        .await?;
+
+    let log = ActivityLog::new(ActivityType::UserUpdated, performer_id)
+        .set_target_id(user_id)
+        .set_target_type("User".into())
+        .set_details(serde_json::json!({ "avatar": "uploaded" }))
+        .finish();
+    crate::services::activity_logs::emit_log(log, &mut conn).await?;
+
+    Ok(AvatarDto {
+        avatar_url,
+        thumbnails,
+    })
+}
+
+/// Deletes every stored size of the avatar of `user_id`; missing files are fine.
+pub async fn delete_files(storage: &dyn Storage, user_id: Uuid) -> Result<(), ModuleError> {
+    for size in AVATAR_SIZES {
+        storage.delete(&key(user_id, size)).await?;
+    }
+    Ok(())
+}
+
+/// Clears the avatar of `user_id` and deletes its stored files.
+pub async fn remove_avatar(
+    pool: Arc<Pool>,
+    storage: Arc<dyn Storage>,
+    user_id: Uuid,
+    performer_id: Uuid,
+) -> Result<Message<()>, ModuleError> {
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|_| ModuleError::InternalError(POOL_ERROR_MSG.into()))?;
+    let updated = diesel::update(schema::users::table.find(user_id))
+        .set((
+            schema::users::avatar_url.eq(None::<String>),
+            schema::users::updated_at.eq(chrono::Local::now().naive_local()),
+        ))
+        .execute(&mut conn)
+        .await?;
+    if updated == 0 {
+        return Err(ModuleError::ResourceNotFound("User not found".into()));
+    }
+
+    delete_files(storage.as_ref(), user_id).await?;
+
+    let log = ActivityLog::new(ActivityType::UserUpdated, performer_id)
+        .set_target_id(user_id)
+        .set_target_type("User".into())
+        .set_details(serde_json::json!({ "avatar": "removed" }))
+        .finish();
+    crate::services::activity_logs::emit_log(log, &mut conn).await?;
+
+    Ok("Avatar removed successfully".into())
+}
@@ -0,0 +1,541 @@
+use serde_json::{Map, Value};
+
+use super::*;
+use crate::dto::custom_fields::CustomFieldRequest;
+use crate::dto::user::{EmergencyContact, UserDto, UserProfileDto};
+use crate::models::activity_logs::{ActivityLog, ActivityType};
+use crate::models::custom_fields::{CustomField, CustomFieldType, FieldVisibility};
+use crate::services::exports::Cell;
+
+const MAX_KEY_LENGTH: usize = 50;
+const MAX_TEXT_LENGTH: usize = 500;
+const MAX_EMERGENCY_CONTACTS: usize = 5;
+
+/// Who is looking at, or changing, a user's profile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Viewer {
+    /// Any other signed-in user.
+    Other,
+    /// The user themselves.
+    Owner,
+    /// Somebody with `users.manage`.
+    Manager,
+}
+
+impl Viewer {
+    fn can_see(&self, visibility: FieldVisibility) -> bool {
+        match visibility {
+            FieldVisibility::Public => true,
+            FieldVisibility::Private => *self != Viewer::Other,
+            FieldVisibility::Admin => *self == Viewer::Manager,
+        }
+    }
+
+    fn can_edit(&self, visibility: FieldVisibility) -> bool {
+        match self {
+            Viewer::Other => false,
+            Viewer::Owner => visibility != FieldVisibility::Admin,
+            Viewer::Manager => true,
+        }
+    }
+}
+
+/// Every definition, in display order.
+pub async fn load(conn: &mut crate::Connection<'_>) -> Result<Vec<CustomField>, ModuleError> {
+    let fields = schema::custom_fields::table
+        .order((
+            schema::custom_fields::position.asc(),
+            schema::custom_fields::key.asc(),
+        ))
+        .select(CustomField::as_select())
+        .load::<CustomField>(conn)
+        .await?;
+    Ok(fields)
+}
+
+pub async fn get_custom_fields(pool: Arc<Pool>) -> Result<Vec<CustomField>, ModuleError> {
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|_| ModuleError::InternalError(POOL_ERROR_MSG.into()))?;
+    load(&mut conn).await
+}
+
+pub async fn create_custom_field(
+    pool: Arc<Pool>,
+    payload: CustomFieldRequest,
+    performer_id: Uuid,
+) -> Result<CustomField, ModuleError> {
+    let payload = validate(payload)?;
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|_| ModuleError::InternalError(POOL_ERROR_MSG.into()))?;
+
+    let taken = diesel::select(diesel::dsl::exists(
+        schema::custom_fields::table.filter(schema::custom_fields::key.eq(&payload.key)),
+    ))
+    .get_result::<bool>(&mut conn)
+    .await?;
+    if taken {
+        return Err(ModuleError::BadRequest(
+            format!("A custom field with key '{}' already exists", payload.key).into(),
+        ));
+    }
+
+    let now = chrono::Utc::now().naive_utc();
+    let field = CustomField {
+        id: Uuid::now_v7(),
+        key: payload.key,
+        label: payload.label,
+        field_type: payload.field_type,
+        options: payload.options,
+        required: payload.required,
+        visibility: payload.visibility,
+        position: payload.position,
+        created_at: now,
+        updated_at: now,
+    };
+    diesel::insert_into(schema::custom_fields::table)
+        .values(&field)
+        .execute(&mut conn)
+        .await?;
+
+    let log = ActivityLog::new(ActivityType::CustomFieldSaved, performer_id)
+        .set_target_id(field.id)
+        .set_target_type("CustomField".into())
+        .set_details(serde_json::json!({ "key": field.key, "field_type": field.field_type }))
+        .finish();
+    crate::services::activity_logs::emit_log(log, &mut conn).await?;
+
+    Ok(field)
+}
+
+/// Replaces a definition. The key cannot change, and neither can the type of a field or an
+/// option of a select field while users still hold values that depend on it.
+pub async fn update_custom_field(
+    pool: Arc<Pool>,
+    id: Uuid,
+    payload: CustomFieldRequest,
+    performer_id: Uuid,
+) -> Result<CustomField, ModuleError> {
+    use schema::users;
+
+    let payload = validate(payload)?;
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|_| ModuleError::InternalError(POOL_ERROR_MSG.into()))?;
+
+    let current = schema::custom_fields::table
+        .find(id)
+        .select(CustomField::as_select())
+        .first::<CustomField>(&mut conn)
+        .await
+        .optional()?
+        .ok_or(ModuleError::ResourceNotFound(
+            "Custom field not found".into(),
+        ))?;
+    if payload.key != current.key {
+        return Err(ModuleError::BadRequest(
+            "The key of a custom field cannot be changed".into(),
+        ));
+    }
+    if payload.field_type != current.field_type {
+        let in_use = diesel::select(diesel::dsl::exists(
+            users::table.filter(users::custom_fields.has_key(&current.key)),
+        ))
+        .get_result::<bool>(&mut conn)
+        .await?;
+        if in_use {
+            return Err(ModuleError::BadRequest(
+                format!(
+                    "Users have values for '{}'; its type cannot change until they are cleared",
+                    current.label
+                )
+                .into(),
+            ));
+        }
+    }
+    if payload.field_type == CustomFieldType::Select && current.field_type == payload.field_type {
+        let removed = current
+            .options
+            .iter()
+            .filter(|option| !payload.options.contains(option))
+            .cloned()
+            .collect::<Vec<_>>();
+        if !removed.is_empty() {
+            let in_use = users::table
+                .filter(
+                    users::custom_fields
+                        .retrieve_as_text(current.key.as_str())
+                        .eq_any(&removed),
+                )
+                .select(users::custom_fields.retrieve_as_text(current.key.as_str()))
+                .first::<String>(&mut conn)
+                .await
+                .optional()?;
+            if let Some(option) = in_use {
+                return Err(ModuleError::BadRequest(
+                    format!("Option '{option}' of '{}' is still in use", current.label).into(),
+                ));
+            }
+        }
+    }
+
+    let field = diesel::update(schema::custom_fields::table.find(id))
+        .set((
+            schema::custom_fields::label.eq(&payload.label),
+            schema::custom_fields::field_type.eq(payload.field_type),
+            schema::custom_fields::options.eq(&payload.options),
+            schema::custom_fields::required.eq(payload.required),
+            schema::custom_fields::visibility.eq(payload.visibility),
+            schema::custom_fields::position.eq(payload.position),
+            schema::custom_fields::updated_at.eq(chrono::Utc::now().naive_utc()),
+        ))
+        .returning(CustomField::as_returning())
+        .get_result::<CustomField>(&mut conn)
+        .await?;
+
+    let log = ActivityLog::new(ActivityType::CustomFieldSaved, performer_id)
+        .set_target_id(field.id)
+        .set_target_type("CustomField".into())
+        .set_details(serde_json::json!({ "key": field.key, "field_type": field.field_type }))
+        .finish();
+    crate::services::activity_logs::emit_log(log, &mut conn).await?;
+
+    Ok(field)
+}
+
+/// Deletes a definition together with every user's value for it.
+pub async fn delete_custom_field(
+    pool: Arc<Pool>,
+    id: Uuid,
+    performer_id: Uuid,
+) -> Result<Message<()>, ModuleError> {
+    use schema::users;
+
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|_| ModuleError::InternalError(POOL_ERROR_MSG.into()))?;
+
+    let (key, cleared) = conn
+        .build_transaction()
+        .run(|conn| {
+            Box::pin(async move {
+                let key = diesel::delete(schema::custom_fields::table.find(id))
+                    .returning(schema::custom_fields::key)
+                    .get_result::<String>(conn)
+                    .await
+                    .optional()?
+                    .ok_or(ModuleError::ResourceNotFound(
+                        "Custom field not found".into(),
+                    ))?;
+                let cleared =
+                    diesel::update(users::table.filter(users::custom_fields.has_key(&key)))
+                        .set(users::custom_fields.eq(users::custom_fields.remove(key.as_str())))
+                        .execute(conn)
+                        .await?;
+                Ok::<_, ModuleError>((key, cleared))
+            })
+        })
+        .await?;
+
+    let log = ActivityLog::new(ActivityType::CustomFieldDeleted, performer_id)
+        .set_target_id(id)
+        .set_target_type("CustomField".into())
+        .set_details(serde_json::json!({ "key": key, "values_cleared": cleared }))
+        .finish();
+    crate::services::activity_logs::emit_log(log, &mut conn).await?;
+
+    Ok("Custom field deleted".into())
+}
+
+fn validate(mut payload: CustomFieldRequest) -> Result<CustomFieldRequest, ModuleError> {
+    payload.key = payload.key.trim().to_string();
+    let well_formed = payload.key.len() <= MAX_KEY_LENGTH
+        && payload
+            .key
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_lowercase())
+        && payload
+            .key
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+    if !well_formed {
+        return Err(ModuleError::BadRequest(
+            format!(
+                "Custom field keys are up to {MAX_KEY_LENGTH} lowercase letters, digits and underscores, starting with a letter"
+            )
+            .into(),
+        ));
+    }
+
+    payload.label = payload.label.trim().to_string();
+    if payload.label.is_empty() {
+        return Err(ModuleError::BadRequest(
+            "Custom field label is required".into(),
+        ));
+    }
+
+    let mut options: Vec<String> = Vec::new();
+    for option in &payload.options {
+        let option = option.trim();
+        if option.is_empty() {
+            return Err(ModuleError::BadRequest("Options cannot be blank".into()));
+        }
+        if options.iter().any(|o| o.eq_ignore_ascii_case(option)) {
+            return Err(ModuleError::BadRequest(
+                format!("Option '{option}' is given twice").into(),
+            ));
+        }
+        options.push(option.to_string());
+    }
+    match payload.field_type {
+        CustomFieldType::Select if options.is_empty() => {
+            return Err(ModuleError::BadRequest(
+                "Select fields need at least one option".into(),
+            ));
+        }
+        CustomFieldType::Select => {}
+        _ if !options.is_empty() => {
+            return Err(ModuleError::BadRequest(
+                "Only select fields have options".into(),
+            ));
+        }
+        _ => {}
+    }
+    payload.options = options;
+    Ok(payload)
+}
+
+/// Reads `value` as a value of `field`, in the form it is stored in. Blank values read as
+/// `None`.
+pub fn parse_value(field: &CustomField, value: &Value) -> Result<Option<Value>, ModuleError> {
+    let invalid = |expected: &str| {
+        ModuleError::BadRequest(format!("'{}' must be {expected}", field.label).into())
+    };
+    let text = match value {
+        Value::Null => return Ok(None),
+        Value::String(text) if text.trim().is_empty() => return Ok(None),
+        Value::String(text) => Some(text.trim()),
+        _ => None,
+    };
+
+    let parsed = match field.field_type {
+        CustomFieldType::Text => {
+            let text = text.ok_or_else(|| invalid("text"))?;
+            if text.chars().count() > MAX_TEXT_LENGTH {
+                return Err(ModuleError::BadRequest(
+                    format!(
+                        "'{}' must be at most {MAX_TEXT_LENGTH} characters",
+                        field.label
+                    )
+                    .into(),
+                ));
+            }
+            Value::String(text.to_string())
+        }
+        CustomFieldType::Number => {
+            let number = match (value, text) {
+                (Value::Number(number), _) => number.as_f64(),
+                (_, Some(text)) => text.parse::<f64>().ok(),
+                _ => None,
+            }
+            .filter(|number| number.is_finite())
+            .ok_or_else(|| invalid("a number"))?;
+            // Whole numbers are stored as integers so `3` and `3.0` compare equal as text.
+            if number.fract() == 0.0 && number.abs() < i64::MAX as f64 {
+                Value::from(number as i64)
+            } else {
+                Value::from(number)
+            }
+        }
+        CustomFieldType::Boolean => match (value, text.map(str::to_lowercase).as_deref()) {
+            (Value::Bool(flag), _) => Value::Bool(*flag),
+            (_, Some("true" | "yes")) => Value::Bool(true),
+            (_, Some("false" | "no")) => Value::Bool(false),
+            _ => return Err(invalid("true or false")),
+        },
+        CustomFieldType::Date => {
+            let date = text
+                .and_then(|text| chrono::NaiveDate::parse_from_str(text, "%Y-%m-%d").ok())
+                .ok_or_else(|| invalid("a date as YYYY-MM-DD"))?;
+            Value::String(date.format("%Y-%m-%d").to_string())
+        }
+        CustomFieldType::Select => {
+            let text =
+                text.ok_or_else(|| invalid(&format!("one of {}", field.options.join(", "))))?;
+            let option = field
+                .options
+                .iter()
+                .find(|option| option.eq_ignore_ascii_case(text))
+                .ok_or_else(|| invalid(&format!("one of {}", field.options.join(", "))))?;
+            Value::String(option.clone())
+        }
+    };
+    Ok(Some(parsed))
+}
+
+/// Applies `changes` to a user's `current` custom fields on behalf of `viewer`. Returns the
+/// values to set and the keys to remove. Required fields the viewer may edit must have a value
+/// afterwards.
+pub fn apply_changes(
+    fields: &[CustomField],
+    current: &Map<String, Value>,
+    changes: Map<String, Value>,
+    viewer: Viewer,
+) -> Result<(Map<String, Value>, Vec<String>), ModuleError> {
+    let mut set = Map::new();
+    let mut removed = Vec::new();
+    for (key, value) in changes {
+        let field = fields
+            .iter()
+            .find(|field| field.key == key)
+            .ok_or(ModuleError::BadRequest(
+                format!("Unknown custom field '{key}'").into(),
+            ))?;
+        if !viewer.can_edit(field.visibility) {
+            return Err(ModuleError::BadRequest(
+                format!("'{}' can only be set by a user manager", field.label).into(),
+            ));
+        }
+        match parse_value(field, &value)? {
+            Some(value) => {
+                set.insert(key, value);
+            }
+            None => removed.push(key),
+        }
+    }
+
+    let missing = fields.iter().find(|field| {
+        field.required
+            && viewer.can_edit(field.visibility)
+            && !set.contains_key(&field.key)
+            && (removed.contains(&field.key) || !current.contains_key(&field.key))
+    });
+    if let Some(field) = missing {
+        return Err(ModuleError::BadRequest(
+            format!("'{}' is required", field.label).into(),
+        ));
+    }
+    Ok((set, removed))
+}
+
+/// Trims and checks a user's emergency contacts.
+pub fn validate_contacts(
+    contacts: Vec<EmergencyContact>,
+) -> Result<Vec<EmergencyContact>, ModuleError> {
+    if contacts.len() > MAX_EMERGENCY_CONTACTS {
+        return Err(ModuleError::BadRequest(
+            format!("At most {MAX_EMERGENCY_CONTACTS} emergency contacts can be given").into(),
+        ));
+    }
+    if contacts
+        .iter()
+        .filter(|contact| contact.next_of_kin)
+        .count()
+        > 1
+    {
+        return Err(ModuleError::BadRequest(
+            "Only one emergency contact can be the next of kin".into(),
+        ));
+    }
+    contacts
+        .into_iter()
+        .map(|contact| {
+            let contact = EmergencyContact {
+                name: contact.name.trim().to_string(),
+                relationship: contact.relationship.trim().to_string(),
+                phone: contact.phone.trim().to_string(),
+                email: contact
+                    .email
+                    .map(|email| email.trim().to_string())
+                    .filter(|email| !email.is_empty()),
+                next_of_kin: contact.next_of_kin,
+            };
+            if contact.name.is_empty()
+                || contact.relationship.is_empty()
+                || contact.phone.is_empty()
+            {
+                return Err(ModuleError::BadRequest(
+                    "Emergency contacts need a name, relationship and phone".into(),
+                ));
+            }
+            Ok(contact)
+        })
+        .collect()
+}
+
+/// Puts a user together with the custom fields and contacts `viewer` may see. Values of
+/// fields that no longer exist are dropped.
+pub fn profile(
+    user: UserDto,
+    custom_fields: Value,
+    emergency_contacts: Value,
+    fields: &[CustomField],
+    viewer: Viewer,
+) -> Result<UserProfileDto, ModuleError> {
+    let mut values = match custom_fields {
+        Value::Object(values) => values,
+        _ => Map::new(),
+    };
+    values.retain(|key, _| {
+        fields
+            .iter()
+            .any(|field| &field.key == key && viewer.can_see(field.visibility))
+    });
+    let emergency_contacts = match viewer {
+        Viewer::Other => None,
+        Viewer::Owner | Viewer::Manager => Some(serde_json::from_value(emergency_contacts)?),
+    };
+    Ok(UserProfileDto {
+        user,
+        custom_fields: values,
+        emergency_contacts,
+    })
+}
+
+/// A stored value as an export cell.
+pub fn cell(field: &CustomField, value: Option<&Value>) -> Cell {
+    match (field.field_type, value) {
+        (_, None | Some(Value::Null)) => Cell::Empty,
+        (CustomFieldType::Number, Some(Value::Number(number))) => {
+            number.as_f64().map_or(Cell::Empty, Cell::Number)
+        }
+        (CustomFieldType::Boolean, Some(Value::Bool(flag))) => {
+            Cell::Text(if *flag { "Yes" } else { "No" }.to_string())
+        }
+        (CustomFieldType::Date, Some(Value::String(text))) => {
+            chrono::NaiveDate::parse_from_str(text, "%Y-%m-%d")
+                .map_or(Cell::Text(text.clone()), Cell::Date)
+        }
+        (_, Some(Value::String(text))) => Cell::Text(text.clone()),
+        (_, Some(value)) => Cell::Text(value.to_string()),
+    }
+}
+
+/// Emergency contacts in one cell, as `Name (Relationship): phone` separated by `; `.
+pub fn contacts_cell(contacts: &[EmergencyContact]) -> Cell {
+    if contacts.is_empty() {
+        return Cell::Empty;
+    }
+    let text = contacts
+        .iter()
+        .map(|contact| {
+            let kin = if contact.next_of_kin {
+                ", next of kin"
+            } else {
+                ""
+            };
+            format!(
+                "{} ({}{kin}): {}",
+                contact.name, contact.relationship, contact.phone
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("; ");
+    Cell::Text(text)
+}
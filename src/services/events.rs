@@ -111,7 +111,7 @@ pub async fn delete_event(
 pub async fn check_into_event(
     pool: Arc<Pool>,
     payload: CheckIntoEventRequest,
-    requester_role: crate::models::users::Role,
+    can_mark_attendance: bool,
     performer_id: Uuid,
 ) -> Result<Message<()>, ModuleError> {
     let mut conn = pool.get().await?;
@@ -128,20 +128,16 @@ pub async fn check_into_event(
     let start_time = event.date.and_time(event.time);
     let end_time = start_time + Duration::minutes(event.grace_period_in_minutes as i64);
 
-    let is_admin = matches!(
-        requester_role,
-        crate::models::users::Role::Admin | crate::models::users::Role::Technical
-    );
+    // Holders of `attendance.mark` may check in after the window and away from the venue.
+    let is_admin = can_mark_attendance;
     if now < start_time {
         return Err(ModuleError::Error("Event has not started yet".into()));
     }
     // Simple window check
-    if !is_admin {
-        if now > end_time {
-            return Err(ModuleError::Error(
-                "Event check-in window has closed".into(),
-            ));
-        }
+    if !is_admin && now > end_time {
+        return Err(ModuleError::Error(
+            "Event check-in window has closed".into(),
+        ));
     }
     if !is_admin {
         match event.location {
@@ -206,7 +202,7 @@ pub async fn check_into_event(
 pub async fn check_in_with_identifier(
     pool: Arc<Pool>,
     payload: crate::dto::events::CheckInWithIdentifierRequest,
-    requester_role: crate::models::users::Role,
+    can_mark_attendance: bool,
     performer_id: Uuid,
 ) -> Result<Message<()>, ModuleError> {
     let mut conn = pool.get().await?;
@@ -217,6 +213,7 @@ pub async fn check_in_with_identifier(
                 .eq(&payload.identifier)
                 .or(schema::users::reg_no.eq(&payload.identifier)),
         )
+        .filter(schema::users::deleted_at.is_null())
         .select(schema::users::id)
         .first::<Uuid>(&mut conn)
         .await
@@ -229,7 +226,13 @@ pub async fn check_in_with_identifier(
         location: payload.location,
     };
 
-    check_into_event(pool.clone(), check_in_payload, requester_role, performer_id).await
+    check_into_event(
+        pool.clone(),
+        check_in_payload,
+        can_mark_attendance,
+        performer_id,
+    )
+    .await
 }
 
 pub async fn get_event(pool: Arc<Pool>, event_id: Uuid) -> Result<Event, ModuleError> {
@@ -0,0 +1,152 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use rust_xlsxwriter::{Format, Workbook, XlsxError};
+
+use super::*;
+use crate::dto::exports::ExportFormat;
+
+const XLSX_CONTENT_TYPE: &str = "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet";
+
+/// A value of an exported table. Dates stay typed so spreadsheets do not have to guess them.
+pub enum Cell {
+    Text(String),
+    Number(f64),
+    Date(NaiveDate),
+    DateTime(NaiveDateTime),
+    /// Written as "N/A" in CSV and left blank in workbooks.
+    Empty,
+}
+
+impl From<Option<String>> for Cell {
+    fn from(value: Option<String>) -> Self {
+        value.map_or(Cell::Empty, Cell::Text)
+    }
+}
+
+pub struct Sheet {
+    pub name: String,
+    pub headers: Vec<String>,
+    pub rows: Vec<Vec<Cell>>,
+}
+
+/// Renders `sheets` as a download named `file_stem`. Workbooks get one worksheet per sheet;
+/// CSV files hold the rows of every sheet under the first sheet's header.
+pub fn export(
+    format: ExportFormat,
+    file_stem: &str,
+    sheets: Vec<Sheet>,
+) -> Result<(axum::http::HeaderMap, Vec<u8>), ModuleError> {
+    let (data, content_type, extension) = match format {
+        ExportFormat::Csv => (write_csv(sheets)?, "text/csv", "csv"),
+        ExportFormat::Xlsx => (
+            write_xlsx(sheets).map_err(|e| ModuleError::InternalError(e.to_string().into()))?,
+            XLSX_CONTENT_TYPE,
+            "xlsx",
+        ),
+    };
+
+    Ok((download_headers(content_type, file_stem, extension)?, data))
+}
+
+/// Headers that make a response download as `file_stem.extension`.
+pub fn download_headers(
+    content_type: &'static str,
+    file_stem: &str,
+    extension: &str,
+) -> Result<axum::http::HeaderMap, ModuleError> {
+    let mut headers = axum::http::HeaderMap::new();
+    headers.insert(
+        axum::http::header::CONTENT_TYPE,
+        axum::http::header::HeaderValue::from_static(content_type),
+    );
+    headers.insert(
+        axum::http::header::CONTENT_DISPOSITION,
+        axum::http::header::HeaderValue::from_str(&format!(
+            "attachment; filename=\"{}.{extension}\"",
+            file_stem.replace([' ', '"'], "_")
+        ))
+        .map_err(|e| ModuleError::InternalError(e.to_string().into()))?,
+    );
+    Ok(headers)
+}
+
+fn write_csv(sheets: Vec<Sheet>) -> Result<Vec<u8>, ModuleError> {
+    let mut wtr = csv::Writer::from_writer(vec![]);
+    if let Some(sheet) = sheets.as_slice().first() {
+        wtr.write_record(&sheet.headers)
+            .map_err(|e| ModuleError::InternalError(e.to_string().into()))?;
+    }
+    for row in sheets.into_iter().flat_map(|sheet| sheet.rows) {
+        let row: Vec<String> = row
+            .into_iter()
+            .map(|cell| match cell {
+                Cell::Text(text) => text,
+                Cell::Number(number) => number.to_string(),
+                Cell::Date(date) => date.format("%Y-%m-%d").to_string(),
+                Cell::DateTime(datetime) => datetime.format("%Y-%m-%d %H:%M:%S").to_string(),
+                Cell::Empty => "N/A".to_string(),
+            })
+            .collect();
+        wtr.write_record(&row)
+            .map_err(|e| ModuleError::InternalError(e.to_string().into()))?;
+    }
+    wtr.into_inner()
+        .map_err(|e| ModuleError::InternalError(e.to_string().into()))
+}
+
+fn write_xlsx(sheets: Vec<Sheet>) -> Result<Vec<u8>, XlsxError> {
+    let header_format = Format::new().set_bold();
+    let date_format = Format::new().set_num_format("yyyy-mm-dd");
+    let datetime_format = Format::new().set_num_format("yyyy-mm-dd hh:mm");
+
+    let mut workbook = Workbook::new();
+    for sheet in sheets {
+        let worksheet = workbook.add_worksheet();
+        worksheet.set_name(sheet_name(&sheet.name))?;
+        for (col, header) in sheet.headers.iter().enumerate() {
+            worksheet.write_string_with_format(0, col as u16, header, &header_format)?;
+        }
+        worksheet.set_freeze_panes(1, 0)?;
+        for (index, row) in sheet.rows.into_iter().enumerate() {
+            let row_num = index as u32 + 1;
+            for (col, cell) in row.into_iter().enumerate() {
+                let col = col as u16;
+                match cell {
+                    Cell::Text(text) => {
+                        worksheet.write_string(row_num, col, text)?;
+                    }
+                    Cell::Number(number) => {
+                        worksheet.write_number(row_num, col, number)?;
+                    }
+                    Cell::Date(date) => {
+                        worksheet.write_datetime_with_format(row_num, col, date, &date_format)?;
+                    }
+                    Cell::DateTime(datetime) => {
+                        worksheet.write_datetime_with_format(
+                            row_num,
+                            col,
+                            datetime,
+                            &datetime_format,
+                        )?;
+                    }
+                    Cell::Empty => {}
+                }
+            }
+        }
+        worksheet.autofit();
+    }
+    workbook.save_to_buffer()
+}
+
+/// Worksheet names are at most 31 characters and cannot contain `[]:*?/\`.
+fn sheet_name(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| if "[]:*?/\\".contains(c) { '_' } else { c })
+        .take(31)
+        .collect();
+    if name.trim().is_empty() {
+        "Sheet1".to_string()
+    } else {
+        name
+    }
+}
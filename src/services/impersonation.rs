@@ -0,0 +1,184 @@
+use super::*;
+use crate::auth::middleware::ConnectionInfo;
+use crate::dto::impersonation::*;
+use crate::models::activity_logs::{ActivityLog, ActivityType};
+use crate::models::sessions::Session;
+use crate::models::users::Role;
+
+const DEFAULT_DURATION_MINUTES: i64 = 15;
+const MAX_DURATION_MINUTES: i64 = 60;
+
+/// Opens a time-boxed session as `user_id` on behalf of `impersonator_id`. Admins cannot be
+/// impersonated.
+pub async fn start_impersonation(
+    pool: Arc<Pool>,
+    user_id: Uuid,
+    payload: StartImpersonationRequest,
+    impersonator_id: Uuid,
+    mfa_verified: bool,
+    connection: &ConnectionInfo,
+) -> Result<ImpersonationDto, ModuleError> {
+    let reason = payload.reason.trim().to_string();
+    if reason.is_empty() {
+        return Err(ModuleError::BadRequest(
+            "A reason for impersonating is required".into(),
+        ));
+    }
+    let duration = payload.duration_minutes.unwrap_or(DEFAULT_DURATION_MINUTES);
+    if !(1..=MAX_DURATION_MINUTES).contains(&duration) {
+        return Err(ModuleError::BadRequest(
+            format!("duration_minutes must be between 1 and {MAX_DURATION_MINUTES}").into(),
+        ));
+    }
+    if user_id == impersonator_id {
+        return Err(ModuleError::BadRequest(
+            "You cannot impersonate yourself".into(),
+        ));
+    }
+
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|_| ModuleError::InternalError(POOL_ERROR_MSG.into()))?;
+
+    let (role, is_active) = schema::users::table
+        .find(user_id)
+        .filter(schema::users::deleted_at.is_null())
+        .select((schema::users::role, schema::users::is_active))
+        .first::<(Role, bool)>(&mut conn)
+        .await
+        .optional()?
+        .ok_or(ModuleError::ResourceNotFound("User not found".into()))?;
+    if role == Role::Admin {
+        return Err(ModuleError::BadRequest(
+            "Admins cannot be impersonated".into(),
+        ));
+    }
+    if !is_active {
+        return Err(ModuleError::BadRequest(
+            "Deactivated users cannot be impersonated".into(),
+        ));
+    }
+
+    let expires_at = chrono::Utc::now().naive_utc() + chrono::Duration::minutes(duration);
+    let session = Session::new(user_id, expires_at)
+        .set_connection_info(connection)
+        .set_mfa_verified(mfa_verified)
+        .set_impersonator_id(impersonator_id)
+        .finish();
+    diesel::insert_into(schema::sessions::table)
+        .values(&session)
+        .execute(&mut conn)
+        .await?;
+
+    let read_only = !payload.allow_writes;
+    let access_token = crate::auth::jwt::create_impersonation_token(&session, role, read_only)?;
+
+    let log = ActivityLog::new(ActivityType::ImpersonationStarted, impersonator_id)
+        .set_target_id(user_id)
+        .set_target_type("User".into())
+        .set_details(serde_json::json!({
+            "session_id": session.id,
+            "reason": reason,
+            "read_only": read_only,
+            "expires_at": expires_at,
+        }))
+        .finish();
+    crate::services::activity_logs::emit_log(log, &mut conn).await?;
+
+    Ok(ImpersonationDto {
+        access_token,
+        token_type: "Bearer".to_string(),
+        session_id: session.id,
+        user_id,
+        impersonator_id,
+        read_only,
+        expires_at,
+    })
+}
+
+/// Impersonation sessions that are neither revoked nor expired, newest first.
+pub async fn list_active_impersonations(
+    pool: Arc<Pool>,
+) -> Result<Vec<ImpersonationSessionDto>, ModuleError> {
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|_| ModuleError::InternalError(POOL_ERROR_MSG.into()))?;
+
+    let sessions = schema::sessions::table
+        .filter(schema::sessions::impersonator_id.is_not_null())
+        .filter(schema::sessions::revoked_at.is_null())
+        .filter(schema::sessions::expires_at.gt(chrono::Utc::now().naive_utc()))
+        .order(schema::sessions::created_at.desc())
+        .select(ImpersonationSessionDto::as_select())
+        .load::<ImpersonationSessionDto>(&mut conn)
+        .await?;
+    Ok(sessions)
+}
+
+pub async fn end_impersonation(
+    pool: Arc<Pool>,
+    session_id: Uuid,
+    performer_id: Uuid,
+) -> Result<Message<()>, ModuleError> {
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|_| ModuleError::InternalError(POOL_ERROR_MSG.into()))?;
+
+    let (user_id, impersonator_id) = schema::sessions::table
+        .find(session_id)
+        .filter(schema::sessions::revoked_at.is_null())
+        .select((schema::sessions::user_id, schema::sessions::impersonator_id))
+        .first::<(Uuid, Option<Uuid>)>(&mut conn)
+        .await
+        .optional()?
+        .and_then(|(user_id, impersonator_id)| impersonator_id.map(|id| (user_id, id)))
+        .ok_or(ModuleError::ResourceNotFound(
+            "Impersonation session not found or already ended".into(),
+        ))?;
+
+    crate::auth::service::revoke_session(&mut conn, session_id).await?;
+
+    let log = ActivityLog::new(ActivityType::ImpersonationEnded, performer_id)
+        .set_target_id(user_id)
+        .set_target_type("User".into())
+        .set_details(serde_json::json!({
+            "session_id": session_id,
+            "impersonator_id": impersonator_id,
+        }))
+        .finish();
+    crate::services::activity_logs::emit_log(log, &mut conn).await?;
+
+    Ok("Impersonation ended".into())
+}
+
+/// Called by the `Claims` extractor for every request made with an impersonation token.
+/// The log is written as the admin with the impersonated user as its target.
+pub async fn record_request(
+    conn: &mut crate::Connection<'_>,
+    impersonator_id: Uuid,
+    user_id: Uuid,
+    parts: &axum::http::request::Parts,
+) -> Result<(), ModuleError> {
+    crate::auth::middleware::set_impersonator(impersonator_id);
+
+    let connection = parts.extensions.get::<ConnectionInfo>();
+    // Nested routers see the path with their prefix stripped.
+    let path = parts
+        .extensions
+        .get::<axum::extract::OriginalUri>()
+        .map_or(parts.uri.path(), |uri| uri.0.path());
+    let log = ActivityLog::new(ActivityType::ImpersonatedRequest, impersonator_id)
+        .set_target_id(user_id)
+        .set_target_type("User".into())
+        .set_details(serde_json::json!({
+            "method": parts.method.as_str(),
+            "path": path,
+            "ip_address": connection.map(|info| info.ip.clone()),
+            "user_agent": connection.map(|info| info.user_agent.clone()),
+        }))
+        .finish();
+    crate::services::activity_logs::emit_log(log, conn).await
+}
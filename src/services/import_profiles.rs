@@ -0,0 +1,235 @@
+use chrono::format::{Item, StrftimeItems};
+
+use super::*;
+use crate::dto::imports::*;
+use crate::models::activity_logs::{ActivityLog, ActivityType};
+use crate::models::import_profiles::{ImportKind, ImportProfile};
+
+pub async fn get_import_profiles(pool: Arc<Pool>) -> Result<Vec<ImportProfileDto>, ModuleError> {
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|_| ModuleError::InternalError(POOL_ERROR_MSG.into()))?;
+
+    let profiles = schema::import_profiles::table
+        .order(schema::import_profiles::name.asc())
+        .select(ImportProfile::as_select())
+        .load::<ImportProfile>(&mut conn)
+        .await?;
+    profiles
+        .into_iter()
+        .map(ImportProfileDto::try_from)
+        .collect()
+}
+
+pub async fn create_import_profile(
+    pool: Arc<Pool>,
+    payload: ImportProfileRequest,
+    performer_id: Uuid,
+) -> Result<ImportProfileDto, ModuleError> {
+    let payload = validate(payload)?;
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|_| ModuleError::InternalError(POOL_ERROR_MSG.into()))?;
+    ensure_name_is_free(&mut conn, &payload.name, None).await?;
+
+    let now = chrono::Utc::now().naive_utc();
+    let profile = ImportProfile {
+        id: Uuid::now_v7(),
+        name: payload.name,
+        kind: payload.kind,
+        column_map: serde_json::to_value(&payload.columns)?,
+        date_format: payload.date_format,
+        transforms: serde_json::to_value(&payload.transforms)?,
+        created_by: Some(performer_id),
+        created_at: now,
+        updated_at: now,
+        overwrite: serde_json::to_value(&payload.overwrite)?,
+    };
+    diesel::insert_into(schema::import_profiles::table)
+        .values(&profile)
+        .execute(&mut conn)
+        .await?;
+
+    let log = ActivityLog::new(ActivityType::ImportProfileSaved, performer_id)
+        .set_target_id(profile.id)
+        .set_target_type("ImportProfile".into())
+        .set_details(serde_json::json!({ "name": profile.name, "kind": profile.kind }))
+        .finish();
+    crate::services::activity_logs::emit_log(log, &mut conn).await?;
+
+    profile.try_into()
+}
+
+pub async fn update_import_profile(
+    pool: Arc<Pool>,
+    id: Uuid,
+    payload: ImportProfileRequest,
+    performer_id: Uuid,
+) -> Result<ImportProfileDto, ModuleError> {
+    let payload = validate(payload)?;
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|_| ModuleError::InternalError(POOL_ERROR_MSG.into()))?;
+    ensure_name_is_free(&mut conn, &payload.name, Some(id)).await?;
+
+    let profile = diesel::update(schema::import_profiles::table.find(id))
+        .set((
+            schema::import_profiles::name.eq(&payload.name),
+            schema::import_profiles::kind.eq(payload.kind),
+            schema::import_profiles::column_map.eq(serde_json::to_value(&payload.columns)?),
+            schema::import_profiles::date_format.eq(&payload.date_format),
+            schema::import_profiles::transforms.eq(serde_json::to_value(&payload.transforms)?),
+            schema::import_profiles::overwrite.eq(serde_json::to_value(&payload.overwrite)?),
+            schema::import_profiles::updated_at.eq(chrono::Utc::now().naive_utc()),
+        ))
+        .returning(ImportProfile::as_returning())
+        .get_result::<ImportProfile>(&mut conn)
+        .await
+        .optional()?
+        .ok_or(ModuleError::ResourceNotFound(
+            "Import profile not found".into(),
+        ))?;
+
+    let log = ActivityLog::new(ActivityType::ImportProfileSaved, performer_id)
+        .set_target_id(profile.id)
+        .set_target_type("ImportProfile".into())
+        .set_details(serde_json::json!({ "name": profile.name, "kind": profile.kind }))
+        .finish();
+    crate::services::activity_logs::emit_log(log, &mut conn).await?;
+
+    profile.try_into()
+}
+
+pub async fn delete_import_profile(
+    pool: Arc<Pool>,
+    id: Uuid,
+    performer_id: Uuid,
+) -> Result<Message<()>, ModuleError> {
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|_| ModuleError::InternalError(POOL_ERROR_MSG.into()))?;
+
+    let deleted = diesel::delete(schema::import_profiles::table.find(id))
+        .execute(&mut conn)
+        .await?;
+    if deleted == 0 {
+        return Err(ModuleError::ResourceNotFound(
+            "Import profile not found".into(),
+        ));
+    }
+
+    let log = ActivityLog::new(ActivityType::ImportProfileDeleted, performer_id)
+        .set_target_id(id)
+        .set_target_type("ImportProfile".into())
+        .finish();
+    crate::services::activity_logs::emit_log(log, &mut conn).await?;
+
+    Ok("Import profile deleted".into())
+}
+
+/// The profile an import of `kind` reads its file with: the saved one named by `id`, or the
+/// built-in default.
+pub async fn resolve(
+    conn: &mut crate::Connection<'_>,
+    id: Option<Uuid>,
+    kind: ImportKind,
+) -> Result<ImportProfileDto, ModuleError> {
+    let Some(id) = id else {
+        return Ok(ImportProfileDto::default_for(kind));
+    };
+    let profile = schema::import_profiles::table
+        .find(id)
+        .select(ImportProfile::as_select())
+        .first::<ImportProfile>(conn)
+        .await
+        .optional()?
+        .ok_or(ModuleError::ResourceNotFound(
+            "Import profile not found".into(),
+        ))?;
+    if profile.kind != kind {
+        return Err(ModuleError::BadRequest(
+            format!(
+                "Import profile '{}' is not for this kind of import",
+                profile.name
+            )
+            .into(),
+        ));
+    }
+    profile.try_into()
+}
+
+fn validate(mut payload: ImportProfileRequest) -> Result<ImportProfileRequest, ModuleError> {
+    payload.name = payload.name.trim().to_string();
+    if payload.name.is_empty() {
+        return Err(ModuleError::BadRequest(
+            "Import profile name is required".into(),
+        ));
+    }
+
+    let fields = payload.kind.fields();
+    let known = |field: &String| fields.iter().any(|(name, _)| name == field);
+    for (field, header) in &payload.columns {
+        if !known(field) {
+            return Err(ModuleError::BadRequest(
+                format!("Unknown field '{field}'").into(),
+            ));
+        }
+        if header.trim().is_empty() {
+            return Err(ModuleError::BadRequest(
+                format!("No column given for '{field}'").into(),
+            ));
+        }
+    }
+    if let Some(field) = payload.transforms.keys().find(|field| !known(field)) {
+        return Err(ModuleError::BadRequest(
+            format!("Unknown field '{field}'").into(),
+        ));
+    }
+    let updatable = payload.kind == ImportKind::Users;
+    if let Some(field) = payload
+        .overwrite
+        .keys()
+        .find(|field| !updatable || !OverwriteRule::UPDATABLE_FIELDS.contains(&field.as_str()))
+    {
+        return Err(ModuleError::BadRequest(
+            format!("'{field}' cannot be updated by an import").into(),
+        ));
+    }
+
+    payload.date_format = payload
+        .date_format
+        .map(|format| format.trim().to_string())
+        .filter(|format| !format.is_empty());
+    if let Some(format) = &payload.date_format
+        && StrftimeItems::new(format).any(|item| matches!(item, Item::Error))
+    {
+        return Err(ModuleError::BadRequest(
+            format!("Invalid date format '{format}'").into(),
+        ));
+    }
+    Ok(payload)
+}
+
+async fn ensure_name_is_free(
+    conn: &mut crate::Connection<'_>,
+    name: &str,
+    except: Option<Uuid>,
+) -> Result<(), ModuleError> {
+    let taken = schema::import_profiles::table
+        .filter(schema::import_profiles::name.eq(name))
+        .select(schema::import_profiles::id)
+        .load::<Uuid>(conn)
+        .await?
+        .into_iter()
+        .any(|id| Some(id) != except);
+    if taken {
+        return Err(ModuleError::BadRequest(
+            format!("An import profile named '{name}' already exists").into(),
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,260 @@
+use std::collections::HashMap;
+use std::io::Cursor;
+
+use axum::extract::Multipart;
+use calamine::{Data, Reader, Xlsx};
+use chrono::NaiveDate;
+
+use super::*;
+use crate::dto::imports::ImportProfileDto;
+
+/// Date formats tried, in order, when a profile does not set one. Two-digit years come
+/// before four-digit ones so that "03/04/95" is not read as the year 95.
+pub const DATE_FORMATS: &[&str] = &[
+    "%m/%d/%y",
+    "%d/%m/%y",
+    "%m/%d/%Y",
+    "%d/%m/%Y",
+    "%Y-%m-%d",
+    "%d-%m-%Y",
+    "%d.%m.%Y",
+    "%Y/%m/%d",
+    "%d %B %Y",
+    "%B %d, %Y",
+    "%d %b %Y",
+    "%b %d, %Y",
+];
+
+/// The first file of a multipart upload.
+pub struct Upload {
+    pub file_name: Option<String>,
+    pub data: Vec<u8>,
+}
+
+impl Upload {
+    /// Workbooks are zip archives, so a CSV file cannot start like one.
+    fn is_xlsx(&self) -> bool {
+        self.data.starts_with(b"PK\x03\x04")
+            || self
+                .file_name
+                .as_deref()
+                .is_some_and(|name| name.to_lowercase().ends_with(".xlsx"))
+    }
+}
+
+/// The header row and data rows of an upload, whichever format it came in. Rows that could
+/// not be read carry the reason instead; every read row knows its line in the file.
+pub struct Table {
+    pub headers: csv::StringRecord,
+    pub records: Vec<Result<csv::StringRecord, String>>,
+}
+
+pub async fn read_upload(mut multipart: Multipart) -> Result<Upload, ModuleError> {
+    let field = multipart
+        .next_field()
+        .await
+        .map_err(|e| ModuleError::InternalError(e.to_string().into()))?
+        .ok_or(ModuleError::BadRequest("No file was uploaded".into()))?;
+    let file_name = field.file_name().map(str::to_string);
+    let data = field
+        .bytes()
+        .await
+        .map_err(|e| ModuleError::InternalError(e.to_string().into()))?;
+    Ok(Upload {
+        file_name,
+        data: data.to_vec(),
+    })
+}
+
+/// Reads a CSV file, or the first worksheet of an `.xlsx` workbook.
+pub fn read_table(upload: &Upload) -> Result<Table, ModuleError> {
+    if upload.is_xlsx() {
+        return read_xlsx(&upload.data);
+    }
+
+    let body = std::str::from_utf8(&upload.data)
+        .map_err(|_| ModuleError::BadRequest("The uploaded file is not valid UTF-8".into()))?;
+    let mut rdr = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(body.as_bytes());
+    let headers = rdr
+        .headers()
+        .map_err(|e| ModuleError::BadRequest(format!("Could not read the header row: {e}").into()))?
+        .clone();
+    let records = rdr
+        .records()
+        .map(|result| result.map_err(|e| format!("Could not read the row: {e}")))
+        .collect();
+    Ok(Table { headers, records })
+}
+
+fn read_xlsx(data: &[u8]) -> Result<Table, ModuleError> {
+    let mut workbook = Xlsx::new(Cursor::new(data))
+        .map_err(|e| ModuleError::BadRequest(format!("Could not read the workbook: {e}").into()))?;
+    let range = workbook
+        .worksheet_range_at(0)
+        .ok_or(ModuleError::BadRequest(
+            "The workbook has no worksheets".into(),
+        ))?
+        .map_err(|e| {
+            ModuleError::BadRequest(format!("Could not read the worksheet: {e}").into())
+        })?;
+
+    // Line numbers follow the sheet's own rows, which need not start at row 1.
+    let first_line = range.start().map_or(1, |(row, _)| row as u64 + 1);
+    let mut rows = range.rows().enumerate();
+    let headers = rows
+        .next()
+        .map(|(_, row)| row.iter().map(cell_text).collect::<csv::StringRecord>())
+        .ok_or(ModuleError::BadRequest("The worksheet is empty".into()))?;
+    let records = rows
+        .filter(|(_, row)| row.iter().any(|cell| !matches!(cell, Data::Empty)))
+        .map(|(index, row)| {
+            let mut record: csv::StringRecord = row.iter().map(cell_text).collect();
+            let mut position = csv::Position::new();
+            position.set_line(first_line + index as u64);
+            record.set_position(Some(position));
+            Ok(record)
+        })
+        .collect();
+    Ok(Table { headers, records })
+}
+
+/// Renders a cell the way it would appear in a CSV export: whole numbers without a decimal
+/// point and dates as `YYYY-MM-DD`, so profiles and date detection treat both formats alike.
+fn cell_text(cell: &Data) -> String {
+    match cell {
+        Data::String(text) => text.clone(),
+        Data::Int(number) => number.to_string(),
+        Data::Float(number) if number.fract() == 0.0 && number.abs() < 1e15 => {
+            (*number as i64).to_string()
+        }
+        Data::Float(number) => number.to_string(),
+        Data::Bool(value) => value.to_string(),
+        Data::DateTime(datetime) => match datetime.as_datetime() {
+            Some(datetime) if datetime.time() == chrono::NaiveTime::MIN => {
+                datetime.format("%Y-%m-%d").to_string()
+            }
+            Some(datetime) => datetime.format("%Y-%m-%d %H:%M:%S").to_string(),
+            None => datetime.to_string(),
+        },
+        Data::DateTimeIso(text) | Data::DurationIso(text) => text.clone(),
+        Data::Error(_) | Data::Empty => String::new(),
+    }
+}
+
+/// Reads the fields of a profile's kind out of CSV records, whatever the headers are called.
+pub struct ColumnMapper {
+    profile: ImportProfileDto,
+    indices: HashMap<&'static str, usize>,
+}
+
+impl ColumnMapper {
+    /// Locates every field's column in `headers`. Headers match case-insensitively and
+    /// ignoring surrounding whitespace; a missing required column rejects the file.
+    pub fn new(
+        profile: ImportProfileDto,
+        headers: &csv::StringRecord,
+    ) -> Result<Self, ModuleError> {
+        let mut positions: HashMap<String, usize> = HashMap::new();
+        for (index, header) in headers.iter().enumerate() {
+            positions.entry(normalise(header)).or_insert(index);
+        }
+
+        let mut indices = HashMap::new();
+        let mut missing = Vec::new();
+        for &(field, required) in profile.kind.fields() {
+            let header = profile.columns.get(field).map_or(field, String::as_str);
+            match positions.get(&normalise(header)) {
+                Some(&index) => {
+                    indices.insert(field, index);
+                }
+                None if required => missing.push(format!("'{header}' ({field})")),
+                None => {}
+            }
+        }
+        if !missing.is_empty() {
+            return Err(ModuleError::BadRequest(
+                format!("Missing column(s) {}", missing.join(", ")).into(),
+            ));
+        }
+        Ok(Self { profile, indices })
+    }
+
+    /// The trimmed, transformed value of `field`. Empty when the column is absent.
+    pub fn value(&self, record: &csv::StringRecord, field: &str) -> String {
+        let value = self
+            .indices
+            .get(field)
+            .and_then(|&index| record.get(index))
+            .unwrap_or_default()
+            .trim()
+            .to_string();
+        self.profile
+            .transforms
+            .get(field)
+            .into_iter()
+            .flatten()
+            .fold(value, |value, transform| transform.apply(value))
+    }
+
+    /// The profile's date format, or the first of `DATE_FORMATS` that reads every non-empty
+    /// value of `field`.
+    pub fn date_format(&self, records: &[csv::StringRecord], field: &str) -> Option<String> {
+        if let Some(format) = &self.profile.date_format {
+            return Some(format.clone());
+        }
+        let values: Vec<String> = records
+            .iter()
+            .map(|record| self.value(record, field))
+            .filter(|value| !value.is_empty())
+            .collect();
+        detect_date_format(&values).map(str::to_string)
+    }
+}
+
+pub fn detect_date_format(values: &[String]) -> Option<&'static str> {
+    if values.is_empty() {
+        return None;
+    }
+    DATE_FORMATS.iter().copied().find(|format| {
+        values
+            .iter()
+            .all(|value| NaiveDate::parse_from_str(value, format).is_ok())
+    })
+}
+
+fn normalise(header: &str) -> String {
+    header.trim().to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detect(values: &[&str]) -> Option<&'static str> {
+        let values: Vec<String> = values.iter().map(|value| value.to_string()).collect();
+        detect_date_format(&values)
+    }
+
+    #[test]
+    fn detects_the_format_every_value_reads_with() {
+        assert_eq!(detect(&["2024-03-05", "1999-12-31"]), Some("%Y-%m-%d"));
+        assert_eq!(detect(&["5 March 2024", "12 May 2001"]), Some("%d %B %Y"));
+        assert_eq!(detect(&["05.03.2024"]), Some("%d.%m.%Y"));
+    }
+
+    #[test]
+    fn day_above_twelve_settles_day_first() {
+        assert_eq!(detect(&["03/04/24"]), Some("%m/%d/%y"));
+        assert_eq!(detect(&["03/04/24", "25/04/24"]), Some("%d/%m/%y"));
+        assert_eq!(detect(&["03/04/2024", "25/04/2024"]), Some("%d/%m/%Y"));
+    }
+
+    #[test]
+    fn no_format_for_mixed_or_missing_values() {
+        assert_eq!(detect(&["01/13/24", "13/01/24"]), None);
+        assert_eq!(detect(&["2024-03-05", "not a date"]), None);
+        assert_eq!(detect(&[]), None);
+    }
+}
@@ -0,0 +1,305 @@
+use super::*;
+use crate::dto::invites::*;
+use crate::mailer::{
+    types::MailerEvent,
+    worker::{MailerSender, dispatch},
+};
+use crate::models::activity_logs::{ActivityLog, ActivityType};
+use crate::models::user_invites::UserInvite;
+use crate::models::users::User;
+
+const INVITE_TTL_DAYS: i64 = 7;
+const INVITE_TOKEN_LENGTH: usize = 48;
+
+/// Creates a user without a password and emails them an invitation to pick one. The reg_no
+/// is allocated the same way as for `register_user`.
+pub async fn invite_user(
+    pool: Arc<Pool>,
+    mailer: &MailerSender,
+    settings: &crate::SettingsCache,
+    payload: InviteUserRequest,
+    performer_id: Uuid,
+) -> Result<Message<()>, ModuleError> {
+    if payload.email.trim().is_empty() {
+        return Err(ModuleError::BadRequest("Email is required".into()));
+    }
+
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|_| ModuleError::InternalError(POOL_ERROR_MSG.into()))?;
+
+    let taken = schema::users::table
+        .filter(super::lower(schema::users::email).eq(payload.email.to_lowercase()))
+        .count()
+        .get_result::<i64>(&mut conn)
+        .await?
+        > 0;
+    if taken {
+        return Err(ModuleError::BadRequest(
+            "A user with this email already exists".into(),
+        ));
+    }
+
+    let reg_no_format = settings.read().await.reg_no_format.clone();
+    let user: User = payload.into();
+    // The user and their invite are created together, so a failure cannot leave behind a
+    // user nobody can invite again.
+    let (user, invite, url) = conn
+        .build_transaction()
+        .run(|conn| {
+            Box::pin(async move {
+                let mut user = user;
+                crate::services::users::insert_user(conn, &mut user, &reg_no_format).await?;
+                let (invite, url) = create_invite(conn, user.id, performer_id).await?;
+                Ok::<_, ModuleError>((user, invite, url))
+            })
+        })
+        .await?;
+    send_invite(mailer, user.first_name, user.email, url);
+
+    let log = ActivityLog::new(ActivityType::UserInvited, performer_id)
+        .set_target_id(user.id)
+        .set_target_type("User".into())
+        .set_details(serde_json::json!({
+            "invite_id": invite.id,
+            "expires_at": invite.expires_at,
+        }))
+        .finish();
+    crate::services::activity_logs::emit_log(log, &mut conn).await?;
+
+    Ok("User invited successfully".into())
+}
+
+/// Lists the current invite of every invited user, newest first. Invites replaced by a
+/// resend are left out.
+pub async fn get_invites(
+    pool: Arc<Pool>,
+    filter: InviteFilter,
+) -> Result<Vec<InviteDto>, ModuleError> {
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|_| ModuleError::InternalError(POOL_ERROR_MSG.into()))?;
+
+    let now = chrono::Utc::now().naive_utc();
+    let mut query = schema::user_invites::table
+        .inner_join(schema::users::table)
+        .filter(schema::user_invites::revoked_at.is_null())
+        .order(schema::user_invites::created_at.desc())
+        .select((
+            UserInvite::as_select(),
+            schema::users::email,
+            schema::users::first_name,
+            schema::users::last_name,
+        ))
+        .into_boxed();
+    query = match filter.status {
+        Some(InviteStatus::Accepted) => {
+            query.filter(schema::user_invites::accepted_at.is_not_null())
+        }
+        Some(InviteStatus::Expired) => query
+            .filter(schema::user_invites::accepted_at.is_null())
+            .filter(schema::user_invites::expires_at.le(now)),
+        Some(InviteStatus::Pending) => query
+            .filter(schema::user_invites::accepted_at.is_null())
+            .filter(schema::user_invites::expires_at.gt(now)),
+        None => query,
+    };
+    let rows = query
+        .load::<(UserInvite, String, String, String)>(&mut conn)
+        .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(invite, email, first_name, last_name)| InviteDto {
+            status: invite_status(&invite, now),
+            id: invite.id,
+            user_id: invite.user_id,
+            email,
+            first_name,
+            last_name,
+            invited_by: invite.invited_by,
+            expires_at: invite.expires_at,
+            accepted_at: invite.accepted_at,
+            created_at: invite.created_at,
+        })
+        .collect())
+}
+
+/// Replaces the user's invite with a fresh one and emails it again. Works for pending and
+/// expired invites alike.
+pub async fn resend_invite(
+    pool: Arc<Pool>,
+    mailer: &MailerSender,
+    user_id: Uuid,
+    performer_id: Uuid,
+) -> Result<Message<()>, ModuleError> {
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|_| ModuleError::InternalError(POOL_ERROR_MSG.into()))?;
+
+    let (invite, first_name, email) = schema::user_invites::table
+        .inner_join(schema::users::table)
+        .filter(schema::user_invites::user_id.eq(user_id))
+        .filter(schema::user_invites::revoked_at.is_null())
+        .order(schema::user_invites::created_at.desc())
+        .select((
+            UserInvite::as_select(),
+            schema::users::first_name,
+            schema::users::email,
+        ))
+        .first::<(UserInvite, String, String)>(&mut conn)
+        .await
+        .optional()?
+        .ok_or(ModuleError::ResourceNotFound(
+            "User has no invite to resend".into(),
+        ))?;
+    if invite.accepted_at.is_some() {
+        return Err(ModuleError::BadRequest(
+            "Invite has already been accepted".into(),
+        ));
+    }
+
+    let invite_id = invite.id;
+    let (invite, url) = conn
+        .build_transaction()
+        .run(|conn| {
+            Box::pin(async move {
+                diesel::update(schema::user_invites::table.find(invite_id))
+                    .set(schema::user_invites::revoked_at.eq(chrono::Utc::now().naive_utc()))
+                    .execute(conn)
+                    .await?;
+                create_invite(conn, user_id, performer_id).await
+            })
+        })
+        .await?;
+    send_invite(mailer, first_name, email, url);
+
+    let log = ActivityLog::new(ActivityType::UserInvited, performer_id)
+        .set_target_id(user_id)
+        .set_target_type("User".into())
+        .set_details(serde_json::json!({
+            "invite_id": invite.id,
+            "expires_at": invite.expires_at,
+            "resent": true,
+        }))
+        .finish();
+    crate::services::activity_logs::emit_log(log, &mut conn).await?;
+
+    Ok("Invite sent again".into())
+}
+
+/// Sets the invited user's password and burns the invite.
+pub async fn accept_invite(
+    pool: Arc<Pool>,
+    mailer: &MailerSender,
+    settings: &crate::SettingsCache,
+    payload: AcceptInviteRequest,
+) -> Result<Message<()>, ModuleError> {
+    crate::helpers::check_password_policy(&payload.password, &*settings.read().await)?;
+
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|_| ModuleError::InternalError(POOL_ERROR_MSG.into()))?;
+    let invalid_invite = || ModuleError::BadRequest("Invite is invalid or has expired".into());
+
+    let now = chrono::Utc::now().naive_utc();
+    let (invite, first_name, email) = schema::user_invites::table
+        .inner_join(schema::users::table)
+        .filter(schema::user_invites::token_hash.eq(crate::helpers::token_hash(&payload.token)))
+        .filter(schema::user_invites::revoked_at.is_null())
+        .filter(schema::user_invites::accepted_at.is_null())
+        .filter(schema::user_invites::expires_at.gt(now))
+        .select((
+            UserInvite::as_select(),
+            schema::users::first_name,
+            schema::users::email,
+        ))
+        .first::<(UserInvite, String, String)>(&mut conn)
+        .await
+        .optional()?
+        .ok_or_else(invalid_invite)?;
+
+    // Claim the invite so a concurrent request with the same token cannot use it twice.
+    let claimed = diesel::update(
+        schema::user_invites::table
+            .find(invite.id)
+            .filter(schema::user_invites::accepted_at.is_null()),
+    )
+    .set(schema::user_invites::accepted_at.eq(now))
+    .execute(&mut conn)
+    .await?;
+    if claimed == 0 {
+        return Err(invalid_invite());
+    }
+
+    let password_hash = crate::helpers::password_hasher(&payload.password)?;
+    diesel::update(schema::users::table.find(invite.user_id))
+        .set((
+            schema::users::password_hash.eq(password_hash),
+            schema::users::must_change_password.eq(false),
+            schema::users::updated_at.eq(chrono::Local::now().naive_local()),
+        ))
+        .execute(&mut conn)
+        .await?;
+
+    let log = ActivityLog::new(ActivityType::InviteAccepted, invite.user_id)
+        .set_target_id(invite.user_id)
+        .set_target_type("User".into())
+        .set_details(serde_json::json!({ "invite_id": invite.id }))
+        .finish();
+    crate::services::activity_logs::emit_log(log, &mut conn).await?;
+
+    dispatch(
+        mailer,
+        MailerEvent::SendWelcomeEmail {
+            name: first_name,
+            to: email,
+        },
+    );
+
+    Ok("Invite accepted, you can now log in".into())
+}
+
+fn invite_status(invite: &UserInvite, now: chrono::NaiveDateTime) -> InviteStatus {
+    if invite.accepted_at.is_some() {
+        InviteStatus::Accepted
+    } else if invite.expires_at <= now {
+        InviteStatus::Expired
+    } else {
+        InviteStatus::Pending
+    }
+}
+
+fn send_invite(mailer: &MailerSender, name: String, to: String, url: String) {
+    dispatch(mailer, MailerEvent::SendInvitationEmail { name, url, to });
+}
+
+/// Stores a new invite for `user_id` and returns it with the link to email. Sending the
+/// email is left to the caller.
+pub async fn create_invite(
+    conn: &mut crate::AsyncPgConnection,
+    user_id: Uuid,
+    performer_id: Uuid,
+) -> Result<(UserInvite, String), ModuleError> {
+    let origin = std::env::var("CLIENT_ORIGIN")?;
+    let token = crate::helpers::random_token(INVITE_TOKEN_LENGTH);
+    let mut url = reqwest::Url::parse(&format!("{}/accept-invite", origin))
+        .map_err(|e| ModuleError::InternalError(e.to_string().into()))?;
+    url.query_pairs_mut().append_pair("token", &token);
+
+    let invite = UserInvite::new(
+        user_id,
+        crate::helpers::token_hash(&token),
+        performer_id,
+        chrono::Utc::now().naive_utc() + chrono::Duration::days(INVITE_TTL_DAYS),
+    );
+    diesel::insert_into(schema::user_invites::table)
+        .values(&invite)
+        .execute(conn)
+        .await?;
+    Ok((invite, url.to_string()))
+}
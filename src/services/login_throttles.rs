@@ -0,0 +1,201 @@
+use super::*;
+use crate::models::activity_logs::{ActivityLog, ActivityType};
+use crate::models::login_throttles::LoginThrottle;
+
+/// Failed attempts on one account before it is locked.
+const ACCOUNT_THRESHOLD: i32 = 5;
+/// Failed attempts from one IP, across any accounts, before the IP is locked.
+const IP_THRESHOLD: i32 = 20;
+/// Registration applications from one IP before it has to wait. Every application counts,
+/// accepted or not.
+const APPLICATION_THRESHOLD: i32 = 5;
+const BASE_LOCKOUT_SECONDS: i64 = 30;
+const MAX_LOCKOUT_SECONDS: i64 = 60 * 60;
+/// Failures older than this are forgotten once any lock has run out.
+const FAILURE_WINDOW_HOURS: i64 = 24;
+
+/// The throttle keys a login attempt counts against, with their lock thresholds.
+///
+/// Unknown usernames only count against the IP. The IP is the one the connection info
+/// middleware resolved, which only trusted proxies can set. An unresolved IP is not
+/// tracked, since it would lock out every client without one.
+pub fn login_keys(user_id: Option<Uuid>, ip: &str) -> Vec<(String, i32)> {
+    let mut keys = Vec::new();
+    if let Some(user_id) = user_id {
+        keys.push((LoginThrottle::user_key(user_id), ACCOUNT_THRESHOLD));
+    }
+    if let Ok(ip) = ip.parse() {
+        keys.push((LoginThrottle::ip_key(ip), IP_THRESHOLD));
+    }
+    keys
+}
+
+/// The throttle key registration applications from `ip` count against. Like logins, an
+/// unresolved IP is not tracked.
+pub fn application_keys(ip: &str) -> Vec<(String, i32)> {
+    match ip.parse() {
+        Ok(ip) => vec![(LoginThrottle::application_key(ip), APPLICATION_THRESHOLD)],
+        Err(_) => Vec::new(),
+    }
+}
+
+pub async fn ensure_not_locked(
+    conn: &mut crate::Connection<'_>,
+    keys: &[(String, i32)],
+) -> Result<(), ModuleError> {
+    let now = chrono::Utc::now().naive_utc();
+    let names: Vec<&String> = keys.iter().map(|(key, _)| key).collect();
+    let locked_until = schema::login_throttles::table
+        .filter(schema::login_throttles::key.eq_any(names))
+        .filter(schema::login_throttles::locked_until.gt(now))
+        .select(diesel::dsl::max(schema::login_throttles::locked_until))
+        .first::<Option<NaiveDateTime>>(conn)
+        .await?;
+
+    match locked_until {
+        Some(until) => {
+            let minutes = (until - now).num_minutes() + 1;
+            Err(ModuleError::TooManyRequests(
+                format!("Too many attempts, try again in {} minute(s)", minutes).into(),
+            ))
+        }
+        None => Ok(()),
+    }
+}
+
+/// Counts a failed attempt against every key and locks the ones past their threshold.
+///
+/// Each failure past the threshold doubles the lock, from 30 seconds up to an hour.
+/// Returns the highest failure count among the keys.
+pub async fn record_failure(
+    conn: &mut crate::Connection<'_>,
+    keys: &[(String, i32)],
+) -> Result<i32, ModuleError> {
+    let now = chrono::Utc::now().naive_utc();
+    let names: Vec<&String> = keys.iter().map(|(key, _)| key).collect();
+    diesel::delete(
+        schema::login_throttles::table
+            .filter(schema::login_throttles::key.eq_any(names))
+            .filter(
+                schema::login_throttles::last_failure_at
+                    .lt(now - chrono::Duration::hours(FAILURE_WINDOW_HOURS)),
+            )
+            .filter(
+                schema::login_throttles::locked_until
+                    .is_null()
+                    .or(schema::login_throttles::locked_until.lt(now)),
+            ),
+    )
+    .execute(conn)
+    .await?;
+
+    let mut highest = 0;
+    for (key, threshold) in keys {
+        let throttle = diesel::insert_into(schema::login_throttles::table)
+            .values(LoginThrottle::new(key.clone()))
+            .on_conflict(schema::login_throttles::key)
+            .do_update()
+            .set((
+                schema::login_throttles::failures.eq(schema::login_throttles::failures + 1),
+                schema::login_throttles::last_failure_at.eq(now),
+            ))
+            .returning(LoginThrottle::as_returning())
+            .get_result::<LoginThrottle>(conn)
+            .await?;
+
+        if throttle.failures >= *threshold {
+            let excess = (throttle.failures - threshold).min(16) as u32;
+            let seconds = (BASE_LOCKOUT_SECONDS * 2_i64.pow(excess)).min(MAX_LOCKOUT_SECONDS);
+            diesel::update(schema::login_throttles::table.find(key))
+                .set(
+                    schema::login_throttles::locked_until
+                        .eq(now + chrono::Duration::seconds(seconds)),
+                )
+                .execute(conn)
+                .await?;
+            tracing::warn!(
+                "{} locked for {}s after {} attempts",
+                key,
+                seconds,
+                throttle.failures
+            );
+        }
+        highest = highest.max(throttle.failures);
+    }
+    Ok(highest)
+}
+
+/// Forgets the failures recorded against a user, after a successful login or an unlock.
+pub async fn clear_user(
+    conn: &mut crate::Connection<'_>,
+    user_id: Uuid,
+) -> Result<bool, ModuleError> {
+    let deleted =
+        diesel::delete(schema::login_throttles::table.find(LoginThrottle::user_key(user_id)))
+            .execute(conn)
+            .await?;
+    Ok(deleted > 0)
+}
+
+pub async fn unlock_user(
+    pool: Arc<Pool>,
+    user_id: Uuid,
+    performer_id: Uuid,
+) -> Result<Message<()>, ModuleError> {
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|_| ModuleError::InternalError(POOL_ERROR_MSG.into()))?;
+
+    let exists = schema::users::table
+        .find(user_id)
+        .count()
+        .get_result::<i64>(&mut conn)
+        .await?
+        > 0;
+    if !exists {
+        return Err(ModuleError::ResourceNotFound("User not found".into()));
+    }
+
+    clear_user(&mut conn, user_id).await?;
+
+    let log = ActivityLog::new(ActivityType::AccountUnlocked, performer_id)
+        .set_target_id(user_id)
+        .set_target_type("User".into())
+        .finish();
+    crate::services::activity_logs::emit_log(log, &mut conn).await?;
+
+    Ok("User account unlocked successfully".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_cover_account_and_ip() {
+        let user_id = Uuid::nil();
+        let keys = login_keys(Some(user_id), "203.0.113.7");
+        assert_eq!(
+            keys,
+            vec![
+                (LoginThrottle::user_key(user_id), ACCOUNT_THRESHOLD),
+                ("ip:203.0.113.7".to_string(), IP_THRESHOLD),
+            ]
+        );
+    }
+
+    #[test]
+    fn unresolved_ip_is_not_tracked() {
+        assert!(login_keys(None, "unknown").is_empty());
+    }
+
+    #[test]
+    fn ipv6_addresses_share_their_64() {
+        let a = login_keys(None, "2001:db8:1:2::1");
+        let b = login_keys(None, "2001:db8:1:2:ffff::9");
+        assert_eq!(a, b);
+        assert_eq!(a[0].0, "ip:2001:db8:1:2::/64");
+        assert_ne!(a, login_keys(None, "2001:db8:1:3::1"));
+    }
+}
@@ -0,0 +1,344 @@
+use super::*;
+use crate::SettingsCache;
+use crate::dto::mfa::*;
+use crate::models::activity_logs::{ActivityLog, ActivityType};
+use crate::models::mfa::{MfaRecoveryCode, UserMfa};
+use crate::models::users::Role;
+use rand::Rng;
+use totp_rs::{Algorithm, Secret, TOTP};
+
+const TOTP_ISSUER: &str = "KUD";
+const TOTP_STEP_SECONDS: i64 = 30;
+const RECOVERY_CODE_COUNT: usize = 10;
+
+/// Whether the organisation settings require a second factor for `role`.
+pub async fn is_required(settings: &SettingsCache, role: &Role) -> bool {
+    matches!(role, Role::Admin | Role::Technical)
+        && settings.read().await.mfa_required_for_privileged_roles
+}
+
+/// False when `role` must use two-factor but the session did not pass it.
+pub async fn is_satisfied(settings: &SettingsCache, role: &Role, mfa: bool) -> bool {
+    mfa || !is_required(settings, role).await
+}
+
+pub async fn is_enabled(
+    conn: &mut crate::Connection<'_>,
+    user_id: Uuid,
+) -> Result<bool, ModuleError> {
+    let enabled = schema::user_mfa::table
+        .find(user_id)
+        .filter(schema::user_mfa::enabled_at.is_not_null())
+        .count()
+        .get_result::<i64>(conn)
+        .await?
+        > 0;
+    Ok(enabled)
+}
+
+/// Starts, or restarts, enrollment with a fresh secret. Two-factor is not in force until
+/// the first code is confirmed through `enable`.
+pub async fn setup(pool: Arc<Pool>, user_id: Uuid) -> Result<MfaSetupDto, ModuleError> {
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|_| ModuleError::InternalError(POOL_ERROR_MSG.into()))?;
+
+    if is_enabled(&mut conn, user_id).await? {
+        return Err(ModuleError::BadRequest(
+            "Two-factor authentication is already enabled".into(),
+        ));
+    }
+    let email = schema::users::table
+        .find(user_id)
+        .select(schema::users::email)
+        .first::<String>(&mut conn)
+        .await?;
+
+    let secret = Secret::generate_secret().to_encoded().to_string();
+    let totp = build_totp(&secret, &email)?;
+
+    let enrollment = UserMfa::new(user_id, secret.clone());
+    diesel::insert_into(schema::user_mfa::table)
+        .values(&enrollment)
+        .on_conflict(schema::user_mfa::user_id)
+        .do_update()
+        .set((
+            schema::user_mfa::secret.eq(&enrollment.secret),
+            schema::user_mfa::last_used_step.eq(None::<i64>),
+            schema::user_mfa::created_at.eq(enrollment.created_at),
+        ))
+        .execute(&mut conn)
+        .await?;
+
+    Ok(MfaSetupDto {
+        secret,
+        otpauth_uri: totp.get_url(),
+    })
+}
+
+/// Confirms enrollment with a first code and returns a new set of recovery codes.
+pub async fn enable(
+    pool: Arc<Pool>,
+    user_id: Uuid,
+    payload: MfaCodeRequest,
+) -> Result<RecoveryCodesDto, ModuleError> {
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|_| ModuleError::InternalError(POOL_ERROR_MSG.into()))?;
+
+    let enrollment = schema::user_mfa::table
+        .find(user_id)
+        .filter(schema::user_mfa::enabled_at.is_null())
+        .select(UserMfa::as_select())
+        .first::<UserMfa>(&mut conn)
+        .await
+        .optional()?
+        .ok_or(ModuleError::BadRequest(
+            "Start two-factor setup before enabling it".into(),
+        ))?;
+
+    if !verify_totp(&mut conn, &enrollment, &payload.code).await? {
+        return Err(ModuleError::BadRequest(
+            "Invalid authentication code".into(),
+        ));
+    }
+
+    diesel::update(schema::user_mfa::table.find(user_id))
+        .set(schema::user_mfa::enabled_at.eq(chrono::Utc::now().naive_utc()))
+        .execute(&mut conn)
+        .await?;
+    let recovery_codes = replace_recovery_codes(&mut conn, user_id).await?;
+
+    let log = ActivityLog::new(ActivityType::MfaEnabled, user_id)
+        .set_target_id(user_id)
+        .set_target_type("User".into())
+        .finish();
+    crate::services::activity_logs::emit_log(log, &mut conn).await?;
+
+    Ok(RecoveryCodesDto { recovery_codes })
+}
+
+/// Turns two-factor off after checking a current code. Roles for which the organisation
+/// requires it cannot opt out.
+pub async fn disable(
+    pool: Arc<Pool>,
+    settings: &SettingsCache,
+    user_id: Uuid,
+    role: Role,
+    payload: MfaCodeRequest,
+) -> Result<Message<()>, ModuleError> {
+    if is_required(settings, &role).await {
+        return Err(ModuleError::BadRequest(
+            "Two-factor authentication is mandatory for your role".into(),
+        ));
+    }
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|_| ModuleError::InternalError(POOL_ERROR_MSG.into()))?;
+
+    if !verify_code(&mut conn, user_id, &payload.code).await? {
+        return Err(ModuleError::BadRequest(
+            "Invalid authentication code".into(),
+        ));
+    }
+    remove(&mut conn, user_id).await?;
+
+    let log = ActivityLog::new(ActivityType::MfaDisabled, user_id)
+        .set_target_id(user_id)
+        .set_target_type("User".into())
+        .finish();
+    crate::services::activity_logs::emit_log(log, &mut conn).await?;
+
+    Ok("Two-factor authentication disabled".into())
+}
+
+pub async fn regenerate_recovery_codes(
+    pool: Arc<Pool>,
+    user_id: Uuid,
+    payload: MfaCodeRequest,
+) -> Result<RecoveryCodesDto, ModuleError> {
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|_| ModuleError::InternalError(POOL_ERROR_MSG.into()))?;
+
+    if !verify_code(&mut conn, user_id, &payload.code).await? {
+        return Err(ModuleError::BadRequest(
+            "Invalid authentication code".into(),
+        ));
+    }
+    let recovery_codes = replace_recovery_codes(&mut conn, user_id).await?;
+    Ok(RecoveryCodesDto { recovery_codes })
+}
+
+/// Removes two-factor from an account whose owner lost their device.
+pub async fn admin_reset(
+    pool: Arc<Pool>,
+    user_id: Uuid,
+    performer_id: Uuid,
+) -> Result<Message<()>, ModuleError> {
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|_| ModuleError::InternalError(POOL_ERROR_MSG.into()))?;
+
+    if !remove(&mut conn, user_id).await? {
+        return Err(ModuleError::ResourceNotFound(
+            "User has no two-factor enrollment".into(),
+        ));
+    }
+
+    let log = ActivityLog::new(ActivityType::MfaDisabled, performer_id)
+        .set_target_id(user_id)
+        .set_target_type("User".into())
+        .finish();
+    crate::services::activity_logs::emit_log(log, &mut conn).await?;
+
+    Ok("Two-factor authentication reset".into())
+}
+
+/// Checks a TOTP code, or failing that an unused recovery code, for an enabled enrollment.
+/// Recovery codes are single use.
+pub async fn verify_code(
+    conn: &mut crate::Connection<'_>,
+    user_id: Uuid,
+    code: &str,
+) -> Result<bool, ModuleError> {
+    let enrollment = schema::user_mfa::table
+        .find(user_id)
+        .filter(schema::user_mfa::enabled_at.is_not_null())
+        .select(UserMfa::as_select())
+        .first::<UserMfa>(conn)
+        .await
+        .optional()?;
+    let Some(enrollment) = enrollment else {
+        return Ok(false);
+    };
+
+    if verify_totp(conn, &enrollment, code).await? {
+        return Ok(true);
+    }
+
+    let code = code.trim().to_lowercase();
+    let recovery_codes = schema::mfa_recovery_codes::table
+        .filter(schema::mfa_recovery_codes::user_id.eq(user_id))
+        .filter(schema::mfa_recovery_codes::used_at.is_null())
+        .select(MfaRecoveryCode::as_select())
+        .load::<MfaRecoveryCode>(conn)
+        .await?;
+    for recovery_code in recovery_codes {
+        if crate::helpers::password_verfier(&code, &recovery_code.code_hash) {
+            let claimed = diesel::update(
+                schema::mfa_recovery_codes::table
+                    .find(recovery_code.id)
+                    .filter(schema::mfa_recovery_codes::used_at.is_null()),
+            )
+            .set(schema::mfa_recovery_codes::used_at.eq(chrono::Utc::now().naive_utc()))
+            .execute(conn)
+            .await?;
+            return Ok(claimed > 0);
+        }
+    }
+    Ok(false)
+}
+
+/// Accepts a code for the current time step or one either side of it, and records the
+/// step so the same code cannot be replayed.
+async fn verify_totp(
+    conn: &mut crate::Connection<'_>,
+    enrollment: &UserMfa,
+    code: &str,
+) -> Result<bool, ModuleError> {
+    let code = code.trim();
+    if code.len() != 6 || !code.chars().all(|c| c.is_ascii_digit()) {
+        return Ok(false);
+    }
+    let email = schema::users::table
+        .find(enrollment.user_id)
+        .select(schema::users::email)
+        .first::<String>(conn)
+        .await?;
+    let totp = build_totp(&enrollment.secret, &email)?;
+
+    let current_step = chrono::Utc::now().timestamp() / TOTP_STEP_SECONDS;
+    let matched = (current_step - 1..=current_step + 1)
+        .find(|step| totp.generate((step * TOTP_STEP_SECONDS) as u64) == code);
+    let Some(step) = matched else {
+        return Ok(false);
+    };
+
+    let claimed = diesel::update(
+        schema::user_mfa::table.find(enrollment.user_id).filter(
+            schema::user_mfa::last_used_step
+                .is_null()
+                .or(schema::user_mfa::last_used_step.lt(step)),
+        ),
+    )
+    .set(schema::user_mfa::last_used_step.eq(step))
+    .execute(conn)
+    .await?;
+    Ok(claimed > 0)
+}
+
+fn build_totp(secret: &str, email: &str) -> Result<TOTP, ModuleError> {
+    let secret = Secret::Encoded(secret.to_string())
+        .to_bytes()
+        .map_err(|e| ModuleError::InternalError(format!("{:?}", e).into()))?;
+    TOTP::new(
+        Algorithm::SHA1,
+        6,
+        1,
+        TOTP_STEP_SECONDS as u64,
+        secret,
+        Some(TOTP_ISSUER.to_string()),
+        email.to_string(),
+    )
+    .map_err(|e| ModuleError::InternalError(e.to_string().into()))
+}
+
+async fn replace_recovery_codes(
+    conn: &mut crate::Connection<'_>,
+    user_id: Uuid,
+) -> Result<Vec<String>, ModuleError> {
+    diesel::delete(
+        schema::mfa_recovery_codes::table.filter(schema::mfa_recovery_codes::user_id.eq(user_id)),
+    )
+    .execute(conn)
+    .await?;
+
+    let mut codes = Vec::with_capacity(RECOVERY_CODE_COUNT);
+    let mut rows = Vec::with_capacity(RECOVERY_CODE_COUNT);
+    for _ in 0..RECOVERY_CODE_COUNT {
+        let raw: String = rand::rng()
+            .sample_iter(rand::distr::Alphanumeric)
+            .take(10)
+            .map(|c| char::from(c).to_ascii_lowercase())
+            .collect();
+        let code = format!("{}-{}", &raw[..5], &raw[5..]);
+        rows.push(MfaRecoveryCode::new(
+            user_id,
+            crate::helpers::password_hasher(&code)?,
+        ));
+        codes.push(code);
+    }
+    diesel::insert_into(schema::mfa_recovery_codes::table)
+        .values(&rows)
+        .execute(conn)
+        .await?;
+    Ok(codes)
+}
+
+async fn remove(conn: &mut crate::Connection<'_>, user_id: Uuid) -> Result<bool, ModuleError> {
+    diesel::delete(
+        schema::mfa_recovery_codes::table.filter(schema::mfa_recovery_codes::user_id.eq(user_id)),
+    )
+    .execute(conn)
+    .await?;
+    let deleted = diesel::delete(schema::user_mfa::table.find(user_id))
+        .execute(conn)
+        .await?;
+    Ok(deleted > 0)
+}
@@ -1,8 +1,28 @@
 pub mod activity_logs;
 pub mod analytics;
+pub mod api_keys;
+pub mod avatars;
+pub mod custom_fields;
 pub mod events;
+pub mod exports;
+pub mod impersonation;
+pub mod import_profiles;
+pub mod imports;
+pub mod invites;
+pub mod login_throttles;
+pub mod mfa;
+pub mod password_resets;
+pub mod permissions;
+pub mod personal_data;
+pub mod reg_nos;
+pub mod registrations;
 pub mod roster;
+pub mod sessions;
+pub mod settings;
+pub mod signing_keys;
 pub mod user_attendance;
+pub mod user_bulk;
+pub mod user_import;
 pub mod users;
 
 use super::*;
@@ -11,3 +31,9 @@ use diesel::prelude::*;
 use diesel::{ExpressionMethods, sql_query};
 use diesel::{OptionalExtension, QueryDsl, SelectableHelper};
 use diesel_async::RunQueryDsl;
+
+diesel::define_sql_function! {
+    /// SQL `lower()`. Emails are compared through it, since accounts keep the case they
+    /// were created with.
+    fn lower(x: diesel::sql_types::Text) -> diesel::sql_types::Text;
+}
@@ -0,0 +1,216 @@
+use super::*;
+use crate::auth::dto::{ForgotPasswordRequest, ResetMethod, ResetPasswordRequest};
+use crate::dto::user::UserDto;
+use crate::mailer::{
+    types::MailerEvent,
+    worker::{MailerSender, dispatch},
+};
+use crate::models::activity_logs::{ActivityLog, ActivityType};
+use crate::models::password_resets::PasswordReset;
+use rand::Rng;
+
+const OTP_TTL_MINUTES: i64 = 15;
+const LINK_TTL_MINUTES: i64 = 60;
+const MAX_REQUESTS_PER_HOUR: i64 = 3;
+const MAX_ATTEMPTS: i32 = 5;
+const GENERIC_RESPONSE: &str = "If the email belongs to an account, a reset code has been sent";
+
+/// Starts a password reset for `payload.email`.
+///
+/// The response is the same whether or not the email exists, and requests past the hourly
+/// limit are dropped silently, so the endpoint cannot be used to probe for accounts. The code
+/// is made and hashed before the lookup so unknown emails take as long as known ones, and
+/// nothing is written until everything that can fail has been checked. Issuing a new code
+/// invalidates any earlier one.
+pub async fn request_password_reset(
+    pool: Arc<Pool>,
+    mailer: &MailerSender,
+    payload: ForgotPasswordRequest,
+) -> Result<Message<()>, ModuleError> {
+    let (code, ttl) = match payload.method {
+        ResetMethod::Otp => (
+            format!("{:06}", rand::rng().random_range(0..1_000_000)),
+            OTP_TTL_MINUTES,
+        ),
+        ResetMethod::Link => (
+            rand::rng()
+                .sample_iter(rand::distr::Alphanumeric)
+                .take(48)
+                .map(char::from)
+                .collect::<String>(),
+            LINK_TTL_MINUTES,
+        ),
+    };
+    let reset_page = match payload.method {
+        ResetMethod::Otp => None,
+        ResetMethod::Link => {
+            let origin = std::env::var("CLIENT_ORIGIN")?;
+            Some(
+                reqwest::Url::parse(&format!("{}/reset-password", origin))
+                    .map_err(|e| ModuleError::InternalError(e.to_string().into()))?,
+            )
+        }
+    };
+    let code_hash = crate::helpers::password_hasher(&code)?;
+
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|_| ModuleError::InternalError(POOL_ERROR_MSG.into()))?;
+
+    let user = schema::users::table
+        .filter(super::lower(schema::users::email).eq(payload.email.to_lowercase()))
+        .filter(schema::users::is_active.eq(true))
+        .filter(schema::users::deleted_at.is_null())
+        .select(UserDto::as_select())
+        .first::<UserDto>(&mut conn)
+        .await
+        .optional()?;
+    let Some(user) = user else {
+        return Ok(GENERIC_RESPONSE.into());
+    };
+
+    let now = chrono::Utc::now().naive_utc();
+    let recent = schema::password_resets::table
+        .filter(schema::password_resets::user_id.eq(user.id))
+        .filter(schema::password_resets::created_at.gt(now - chrono::Duration::hours(1)))
+        .count()
+        .get_result::<i64>(&mut conn)
+        .await?;
+    if recent >= MAX_REQUESTS_PER_HOUR {
+        tracing::warn!("Password reset rate limit reached for user {}", user.id);
+        return Ok(GENERIC_RESPONSE.into());
+    }
+
+    invalidate_pending(&mut conn, user.id).await?;
+    let reset = PasswordReset::new(user.id, code_hash, now + chrono::Duration::minutes(ttl));
+    diesel::insert_into(schema::password_resets::table)
+        .values(&reset)
+        .execute(&mut conn)
+        .await?;
+
+    let event = match reset_page {
+        None => MailerEvent::SendOtp {
+            name: user.first_name,
+            to: user.email,
+            otp: code,
+        },
+        Some(mut url) => {
+            url.query_pairs_mut()
+                .append_pair("email", &user.email)
+                .append_pair("token", &code);
+            MailerEvent::SendPasswordResetEmail {
+                name: user.first_name,
+                url: url.to_string(),
+                to: user.email,
+            }
+        }
+    };
+    dispatch(mailer, event);
+
+    Ok(GENERIC_RESPONSE.into())
+}
+
+/// Sets a new password using the latest pending reset code for the account.
+///
+/// A wrong code counts against the reset; after `MAX_ATTEMPTS` the code is burned and a new
+/// one has to be requested. On success every session of the user is signed out.
+pub async fn reset_password(
+    pool: Arc<Pool>,
+    mailer: &MailerSender,
+    settings: &crate::SettingsCache,
+    payload: ResetPasswordRequest,
+) -> Result<Message<()>, ModuleError> {
+    crate::helpers::check_password_policy(&payload.password, &*settings.read().await)?;
+
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|_| ModuleError::InternalError(POOL_ERROR_MSG.into()))?;
+    let invalid_code = || ModuleError::BadRequest("Reset code is invalid or has expired".into());
+
+    let user = schema::users::table
+        .filter(super::lower(schema::users::email).eq(payload.email.to_lowercase()))
+        .select(UserDto::as_select())
+        .first::<UserDto>(&mut conn)
+        .await
+        .optional()?
+        .ok_or_else(invalid_code)?;
+
+    let now = chrono::Utc::now().naive_utc();
+    let reset = schema::password_resets::table
+        .filter(schema::password_resets::user_id.eq(user.id))
+        .filter(schema::password_resets::used_at.is_null())
+        .filter(schema::password_resets::expires_at.gt(now))
+        .filter(schema::password_resets::attempts.lt(MAX_ATTEMPTS))
+        .order(schema::password_resets::created_at.desc())
+        .select(PasswordReset::as_select())
+        .first::<PasswordReset>(&mut conn)
+        .await
+        .optional()?
+        .ok_or_else(invalid_code)?;
+
+    if !crate::helpers::password_verfier(&payload.code, &reset.code_hash) {
+        diesel::update(schema::password_resets::table.find(reset.id))
+            .set(schema::password_resets::attempts.eq(schema::password_resets::attempts + 1))
+            .execute(&mut conn)
+            .await?;
+        return Err(invalid_code());
+    }
+
+    // Claim the code so a concurrent request with the same code cannot use it twice.
+    let claimed = diesel::update(
+        schema::password_resets::table
+            .find(reset.id)
+            .filter(schema::password_resets::used_at.is_null()),
+    )
+    .set(schema::password_resets::used_at.eq(now))
+    .execute(&mut conn)
+    .await?;
+    if claimed == 0 {
+        return Err(invalid_code());
+    }
+
+    let password_hash = crate::helpers::password_hasher(&payload.password)?;
+    diesel::update(schema::users::table.find(user.id))
+        .set((
+            schema::users::password_hash.eq(password_hash),
+            schema::users::must_change_password.eq(false),
+            schema::users::updated_at.eq(chrono::Local::now().naive_local()),
+        ))
+        .execute(&mut conn)
+        .await?;
+    crate::services::sessions::revoke_all_sessions(&mut conn, user.id, None).await?;
+
+    let log = ActivityLog::new(ActivityType::PasswordChanged, user.id)
+        .set_target_id(user.id)
+        .set_target_type("User".into())
+        .set_details(serde_json::json!({ "via": "password_reset" }))
+        .finish();
+    crate::services::activity_logs::emit_log(log, &mut conn).await?;
+
+    dispatch(
+        mailer,
+        MailerEvent::SendPasswordChangeEmail {
+            name: user.first_name,
+            to: user.email,
+        },
+    );
+
+    Ok("Password reset successfully".into())
+}
+
+async fn invalidate_pending(
+    conn: &mut crate::Connection<'_>,
+    user_id: Uuid,
+) -> Result<(), ModuleError> {
+    diesel::update(
+        schema::password_resets::table
+            .filter(schema::password_resets::user_id.eq(user_id))
+            .filter(schema::password_resets::used_at.is_null()),
+    )
+    .set(schema::password_resets::used_at.eq(chrono::Utc::now().naive_utc()))
+    .execute(conn)
+    .await?;
+    Ok(())
+}
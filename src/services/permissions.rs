@@ -0,0 +1,168 @@
+use std::collections::{HashMap, HashSet};
+
+use super::*;
+use crate::PermissionCache;
+use crate::auth::jwt::Claims;
+use crate::dto::permissions::*;
+use crate::models::activity_logs::{ActivityLog, ActivityType};
+use crate::models::permissions::{Permission, RolePermission};
+use crate::models::users::Role;
+
+/// Fills `role_permissions` with the default mapping when the table is empty.
+pub async fn seed_default_permissions(pool: Arc<Pool>) -> Result<(), ModuleError> {
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|_| ModuleError::InternalError(POOL_ERROR_MSG.into()))?;
+
+    let count = schema::role_permissions::table
+        .count()
+        .get_result::<i64>(&mut conn)
+        .await?;
+    if count > 0 {
+        return Ok(());
+    }
+
+    tracing::info!("Seeding default role permissions...");
+    let rows: Vec<RolePermission> = Role::all()
+        .into_iter()
+        .flat_map(|role| {
+            Permission::defaults(&role)
+                .into_iter()
+                .map(move |permission| RolePermission::new(role.clone(), permission))
+        })
+        .collect();
+    diesel::insert_into(schema::role_permissions::table)
+        .values(&rows)
+        .execute(&mut conn)
+        .await?;
+    Ok(())
+}
+
+/// Loads the role to permission mapping into a fresh cache for `AppState`.
+pub async fn load_permissions(pool: Arc<Pool>) -> Result<PermissionCache, ModuleError> {
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|_| ModuleError::InternalError(POOL_ERROR_MSG.into()))?;
+    let map = fetch_permission_map(&mut conn).await?;
+    Ok(Arc::new(tokio::sync::RwLock::new(map)))
+}
+
+async fn fetch_permission_map(
+    conn: &mut crate::Connection<'_>,
+) -> Result<HashMap<Role, HashSet<Permission>>, ModuleError> {
+    let rows = schema::role_permissions::table
+        .select(RolePermission::as_select())
+        .load::<RolePermission>(conn)
+        .await?;
+
+    let mut map: HashMap<Role, HashSet<Permission>> = HashMap::new();
+    for row in rows {
+        map.entry(row.role).or_default().insert(row.permission);
+    }
+    Ok(map)
+}
+
+pub async fn has_permission(cache: &PermissionCache, role: &Role, permission: Permission) -> bool {
+    cache
+        .read()
+        .await
+        .get(role)
+        .is_some_and(|permissions| permissions.contains(&permission))
+}
+
+/// Whether the caller behind `claims` may use `permission`: through the scopes of an API
+/// key, or else through their role, with two-factor where the organisation requires it.
+/// Read-only impersonation sessions hold none.
+pub async fn claims_allow(state: &AppState, claims: &Claims, permission: Permission) -> bool {
+    if claims.read_only {
+        return false;
+    }
+    match &claims.api_key {
+        Some(grant) => grant.scopes.contains(&permission),
+        None => {
+            has_permission(&state.permissions, &claims.role, permission).await
+                && crate::services::mfa::is_satisfied(&state.settings, &claims.role, claims.mfa)
+                    .await
+        }
+    }
+}
+
+pub async fn get_permissions(cache: &PermissionCache) -> PermissionCatalogueDto {
+    let map = cache.read().await;
+    let roles = Role::all()
+        .into_iter()
+        .map(|role| {
+            // Keep the catalogue order so the output is stable.
+            let granted = map.get(&role);
+            let permissions = Permission::all()
+                .into_iter()
+                .filter(|permission| granted.is_some_and(|set| set.contains(permission)))
+                .collect();
+            RolePermissionsDto { role, permissions }
+        })
+        .collect();
+    PermissionCatalogueDto {
+        permissions: Permission::all(),
+        roles,
+    }
+}
+
+/// Replaces the permission set of `role` and reloads the cache.
+///
+/// Admin always keeps `permissions.manage`, otherwise nobody could undo the change.
+pub async fn update_role_permissions(
+    pool: Arc<Pool>,
+    cache: &PermissionCache,
+    role: Role,
+    payload: UpdateRolePermissionsRequest,
+    performer_id: Uuid,
+) -> Result<Message<()>, ModuleError> {
+    if role == Role::Admin && !payload.permissions.contains(&Permission::PermissionsManage) {
+        return Err(ModuleError::BadRequest(
+            "Admin cannot lose the permissions.manage permission".into(),
+        ));
+    }
+
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|_| ModuleError::InternalError(POOL_ERROR_MSG.into()))?;
+
+    let permissions: HashSet<Permission> = payload.permissions.into_iter().collect();
+    let rows: Vec<RolePermission> = permissions
+        .iter()
+        .map(|permission| RolePermission::new(role.clone(), *permission))
+        .collect();
+
+    let target_role = role.clone();
+    conn.build_transaction()
+        .run(|conn| {
+            Box::pin(async move {
+                diesel::delete(
+                    schema::role_permissions::table
+                        .filter(schema::role_permissions::role.eq(&target_role)),
+                )
+                .execute(conn)
+                .await?;
+                diesel::insert_into(schema::role_permissions::table)
+                    .values(&rows)
+                    .execute(conn)
+                    .await?;
+                Ok::<(), ModuleError>(())
+            })
+        })
+        .await?;
+
+    let log = ActivityLog::new(ActivityType::PermissionsUpdated, performer_id)
+        .set_target_type("Role".into())
+        .set_details(serde_json::json!({ "role": role, "permissions": permissions }))
+        .finish();
+    crate::services::activity_logs::emit_log(log, &mut conn).await?;
+
+    let map = fetch_permission_map(&mut conn).await?;
+    *cache.write().await = map;
+
+    Ok("Role permissions updated successfully".into())
+}
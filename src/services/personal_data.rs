@@ -0,0 +1,286 @@
+use std::io::{Cursor, Write};
+
+use zip::write::SimpleFileOptions;
+
+use super::*;
+use crate::dto::attendance::UserAttendanceDto;
+use crate::dto::exports::PersonalDataFormat;
+use crate::dto::user::PersonalDataExport;
+use crate::models::activity_logs::{ActivityLog, ActivityType};
+use crate::services::custom_fields::Viewer;
+use crate::storage::Storage;
+
+/// Keys of activity log details that describe the device or network a user acted from.
+const CONNECTION_DETAILS: [&str; 2] = ["ip_address", "user_agent"];
+/// Keys of activity log details that hold a user's own data: the connection details, the
+/// old and new values of profile changes, and the identifiers purges and approvals record.
+const PERSONAL_DETAILS: [&str; 7] = [
+    "ip_address",
+    "user_agent",
+    "before",
+    "after",
+    "email",
+    "reg_no",
+    "phone",
+];
+
+/// Bundles the profile, attendance, roster history and activity of `user_id` for download.
+///
+/// `viewer` decides which custom fields the profile carries, as on Get User: users
+/// downloading their own data get what their profile shows them, without admin-only
+/// fields, while user managers get everything.
+pub async fn export_personal_data(
+    pool: Arc<Pool>,
+    user_id: Uuid,
+    format: PersonalDataFormat,
+    viewer: Viewer,
+    performer_id: Uuid,
+) -> Result<(axum::http::HeaderMap, Vec<u8>), ModuleError> {
+    use schema::{activity_logs, user_attendance};
+
+    let profile = crate::services::users::get_user(pool.clone(), user_id, viewer).await?;
+    let rosters = crate::services::roster::get_user_roster_history(pool.clone(), user_id).await?;
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|_| ModuleError::InternalError(POOL_ERROR_MSG.into()))?;
+    let attendance = user_attendance::table
+        .filter(user_attendance::user_id.eq(user_id))
+        .order(user_attendance::time_in.asc())
+        .select(UserAttendanceDto::as_select())
+        .load::<UserAttendanceDto>(&mut conn)
+        .await?;
+    let activity = activity_logs::table
+        .filter(
+            activity_logs::user_id
+                .eq(user_id)
+                .or(activity_logs::target_id.eq(user_id)),
+        )
+        .order(activity_logs::created_at.asc())
+        .select(ActivityLog::as_select())
+        .load::<ActivityLog>(&mut conn)
+        .await?;
+
+    let generated_at = chrono::Local::now().naive_local();
+    let file_stem = format!(
+        "personal-data-{}-{}",
+        profile.user.reg_no.replace('/', "-"),
+        generated_at.format("%Y-%m-%d")
+    );
+    let export = PersonalDataExport {
+        generated_at,
+        profile,
+        attendance,
+        rosters,
+        activity,
+    };
+    let (data, content_type, extension) = match format {
+        PersonalDataFormat::Json => (
+            serde_json::to_vec_pretty(&export)?,
+            "application/json",
+            "json",
+        ),
+        PersonalDataFormat::Zip => (write_zip(&export)?, "application/zip", "zip"),
+    };
+    let headers = crate::services::exports::download_headers(content_type, &file_stem, extension)?;
+
+    let log = ActivityLog::new(ActivityType::PersonalDataExported, performer_id)
+        .set_target_id(user_id)
+        .set_target_type("User".into())
+        .set_details(serde_json::json!({ "format": format }))
+        .finish();
+    crate::services::activity_logs::emit_log(log, &mut conn).await?;
+
+    Ok((headers, data))
+}
+
+fn write_zip(export: &PersonalDataExport) -> Result<Vec<u8>, ModuleError> {
+    let zip_error = |e: zip::result::ZipError| ModuleError::InternalError(e.to_string().into());
+    let files = [
+        (
+            "profile.json",
+            serde_json::to_vec_pretty(&serde_json::json!({
+                "generated_at": export.generated_at,
+                "profile": export.profile,
+            }))?,
+        ),
+        (
+            "attendance.json",
+            serde_json::to_vec_pretty(&export.attendance)?,
+        ),
+        ("rosters.json", serde_json::to_vec_pretty(&export.rosters)?),
+        (
+            "activity.json",
+            serde_json::to_vec_pretty(&export.activity)?,
+        ),
+    ];
+
+    let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    for (name, data) in files {
+        zip.start_file(name, options).map_err(zip_error)?;
+        zip.write_all(&data)?;
+    }
+    Ok(zip.finish().map_err(zip_error)?.into_inner())
+}
+
+/// Scrubs the personal data of a user for good while keeping their attendance, roster history
+/// and the events they created, so counts and statistics stay right. The user is deleted if
+/// they were not already, signed out everywhere and can no longer be restored.
+pub async fn anonymise_user(
+    pool: Arc<Pool>,
+    storage: Arc<dyn Storage>,
+    id: Uuid,
+    performer_id: Uuid,
+) -> Result<Message<()>, ModuleError> {
+    use schema::users;
+
+    if id == performer_id {
+        return Err(ModuleError::BadRequest(
+            "You cannot anonymise your own account".into(),
+        ));
+    }
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|_| ModuleError::InternalError(POOL_ERROR_MSG.into()))?;
+
+    conn.build_transaction()
+        .run(|conn| {
+            Box::pin(async move {
+                let anonymised_at = users::table
+                    .find(id)
+                    .select(users::anonymised_at)
+                    .for_update()
+                    .first::<Option<NaiveDateTime>>(conn)
+                    .await
+                    .optional()?
+                    .ok_or(ModuleError::ResourceNotFound("User not found".into()))?;
+                if anonymised_at.is_some() {
+                    return Err(ModuleError::BadRequest("User is already anonymised".into()));
+                }
+
+                let now = chrono::Local::now().naive_local();
+                let tag = id.simple().to_string();
+                diesel::update(users::table.find(id))
+                    .set((
+                        users::first_name.eq("Anonymised"),
+                        users::last_name.eq("User"),
+                        users::email.eq(format!("{tag}@anonymised.invalid")),
+                        users::username.eq(None::<String>),
+                        users::reg_no.eq(format!("ANON-{tag}")),
+                        // Not a valid hash, so no password matches it.
+                        users::password_hash.eq("!"),
+                        users::dob.eq(None::<NaiveDateTime>),
+                        users::avatar_url.eq(None::<String>),
+                        users::phone.eq(None::<String>),
+                        users::address.eq(None::<String>),
+                        users::city.eq(None::<String>),
+                        users::state.eq(None::<String>),
+                        users::country.eq(None::<String>),
+                        users::device_id.eq(None::<String>),
+                        users::custom_fields.eq(serde_json::json!({})),
+                        users::emergency_contacts.eq(serde_json::json!([])),
+                        users::is_active.eq(false),
+                        users::must_change_password.eq(false),
+                        users::anonymised_at.eq(now),
+                        users::updated_at.eq(now),
+                    ))
+                    .execute(conn)
+                    .await?;
+                diesel::update(users::table.find(id).filter(users::deleted_at.is_null()))
+                    .set((
+                        users::deleted_at.eq(now),
+                        users::deleted_by.eq(performer_id),
+                    ))
+                    .execute(conn)
+                    .await?;
+
+                // Sessions hold IP addresses and user agents, so they go rather than being
+                // revoked. The rest are ways back into the account.
+                diesel::delete(
+                    schema::refresh_tokens::table.filter(schema::refresh_tokens::user_id.eq(id)),
+                )
+                .execute(conn)
+                .await?;
+                diesel::delete(schema::sessions::table.filter(schema::sessions::user_id.eq(id)))
+                    .execute(conn)
+                    .await?;
+                diesel::delete(
+                    schema::oauth_accounts::table.filter(schema::oauth_accounts::user_id.eq(id)),
+                )
+                .execute(conn)
+                .await?;
+                diesel::delete(schema::user_mfa::table.filter(schema::user_mfa::user_id.eq(id)))
+                    .execute(conn)
+                    .await?;
+                diesel::delete(
+                    schema::mfa_recovery_codes::table
+                        .filter(schema::mfa_recovery_codes::user_id.eq(id)),
+                )
+                .execute(conn)
+                .await?;
+                diesel::delete(
+                    schema::password_resets::table.filter(schema::password_resets::user_id.eq(id)),
+                )
+                .execute(conn)
+                .await?;
+                diesel::delete(
+                    schema::user_invites::table.filter(schema::user_invites::user_id.eq(id)),
+                )
+                .execute(conn)
+                .await?;
+                diesel::delete(
+                    schema::registration_applications::table
+                        .filter(schema::registration_applications::user_id.eq(id)),
+                )
+                .execute(conn)
+                .await?;
+
+                // Entries about the user lose everything that describes them; entries the user
+                // wrote about others keep their content but not where the user acted from.
+                let about_user =
+                    schema::activity_logs::target_id
+                        .eq(id)
+                        .or(schema::activity_logs::details
+                            .retrieve_as_text("user_id")
+                            .eq(id.to_string()));
+                diesel::update(
+                    schema::activity_logs::table.filter(about_user).filter(
+                        schema::activity_logs::details.has_any_key(PERSONAL_DETAILS.to_vec()),
+                    ),
+                )
+                .set(
+                    schema::activity_logs::details
+                        .eq(schema::activity_logs::details.remove(PERSONAL_DETAILS.to_vec())),
+                )
+                .execute(conn)
+                .await?;
+                diesel::update(
+                    schema::activity_logs::table
+                        .filter(schema::activity_logs::user_id.eq(id))
+                        .filter(
+                            schema::activity_logs::details.has_any_key(CONNECTION_DETAILS.to_vec()),
+                        ),
+                )
+                .set(
+                    schema::activity_logs::details
+                        .eq(schema::activity_logs::details.remove(CONNECTION_DETAILS.to_vec())),
+                )
+                .execute(conn)
+                .await?;
+                Ok::<_, ModuleError>(())
+            })
+        })
+        .await?;
+
+    crate::services::avatars::delete_files(storage.as_ref(), id).await?;
+
+    let log = ActivityLog::new(ActivityType::UserAnonymised, performer_id)
+        .set_target_id(id)
+        .set_target_type("User".into())
+        .finish();
+    crate::services::activity_logs::emit_log(log, &mut conn).await?;
+
+    Ok("User anonymised successfully".into())
+}
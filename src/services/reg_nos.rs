@@ -0,0 +1,334 @@
+//! Registration numbers. Codes come from `reg_no_sequences`, one row per year, which is
+//! bumped with a single upsert so concurrent registrations never draw the same code.
+
+use std::collections::HashSet;
+
+use diesel::upsert::excluded;
+
+use super::*;
+use crate::dto::user::{RegNoChange, RenumberReport, RenumberRequest};
+use crate::models::activity_logs::{ActivityLog, ActivityType};
+use crate::models::users::User;
+
+/// Checks a reg_no template before it is saved or used.
+pub fn validate_format(format: &str) -> Result<(), ModuleError> {
+    let invalid =
+        |reason: String| ModuleError::BadRequest(format!("Invalid reg_no format: {reason}").into());
+    let mut has_seq = false;
+    let mut has_year = false;
+    let mut rest = format;
+    while let Some(start) = rest.find('{') {
+        let end = rest[start..]
+            .find('}')
+            .map(|end| start + end)
+            .ok_or_else(|| invalid("unclosed '{'".into()))?;
+        match &rest[start + 1..end] {
+            "year" | "yy" => has_year = true,
+            "seq" => has_seq = true,
+            token => match token
+                .strip_prefix("seq:0")
+                .and_then(|w| w.parse::<usize>().ok())
+            {
+                Some(1..=9) => has_seq = true,
+                _ => return Err(invalid(format!("unknown placeholder '{{{token}}}'"))),
+            },
+        }
+        rest = &rest[end + 1..];
+    }
+    if rest.contains('}') || format.matches('{').count() != format.matches('}').count() {
+        return Err(invalid("unbalanced braces".into()));
+    }
+    if !has_seq {
+        return Err(invalid("it needs {seq} or {seq:0N}".into()));
+    }
+    if !has_year {
+        return Err(invalid(
+            "it needs {year} or {yy}, since codes restart every year".into(),
+        ));
+    }
+    Ok(())
+}
+
+/// Reserves `count` consecutive codes for `year` and returns the first.
+async fn reserve(
+    conn: &mut crate::AsyncPgConnection,
+    year: &str,
+    count: i64,
+) -> Result<i64, ModuleError> {
+    use schema::reg_no_sequences::dsl;
+
+    let last = diesel::insert_into(dsl::reg_no_sequences)
+        .values((dsl::year.eq(year), dsl::last_value.eq(count)))
+        .on_conflict(dsl::year)
+        .do_update()
+        .set((
+            dsl::last_value.eq(dsl::last_value + excluded(dsl::last_value)),
+            dsl::updated_at.eq(chrono::Utc::now().naive_utc()),
+        ))
+        .returning(dsl::last_value)
+        .get_result::<i64>(conn)
+        .await?;
+    Ok(last - count + 1)
+}
+
+/// The last code handed out for `year`, without reserving anything.
+pub async fn last_code(
+    conn: &mut crate::AsyncPgConnection,
+    year: &str,
+) -> Result<i64, ModuleError> {
+    let last = schema::reg_no_sequences::table
+        .find(year)
+        .select(schema::reg_no_sequences::last_value)
+        .first::<i64>(conn)
+        .await
+        .optional()?;
+    Ok(last.unwrap_or(0))
+}
+
+/// Hands out `count` new reg_nos for `year`. Codes whose reg_no is already taken, say by an
+/// imported or hand-edited number, are passed over.
+pub async fn allocate(
+    conn: &mut crate::AsyncPgConnection,
+    format: &str,
+    year: &str,
+    count: usize,
+) -> Result<Vec<String>, ModuleError> {
+    let mut reg_nos = Vec::with_capacity(count);
+    while reg_nos.len() < count {
+        let wanted = count - reg_nos.len();
+        let first = reserve(conn, year, wanted as i64).await?;
+        let candidates: Vec<String> = (first..first + wanted as i64)
+            .map(|code| User::format_reg_no(format, year, code))
+            .collect();
+        let taken: HashSet<String> = schema::users::table
+            .filter(schema::users::reg_no.eq_any(&candidates))
+            .select(schema::users::reg_no)
+            .load::<String>(conn)
+            .await?
+            .into_iter()
+            .collect();
+        reg_nos.extend(
+            candidates
+                .into_iter()
+                .filter(|reg_no| !taken.contains(reg_no)),
+        );
+    }
+    Ok(reg_nos)
+}
+
+/// The reg_nos the users of `year` would get, in creation order, and the users outside the
+/// year already holding one of them.
+async fn plan(
+    conn: &mut crate::AsyncPgConnection,
+    year: &str,
+    format: &str,
+) -> Result<(Vec<RegNoChange>, usize), ModuleError> {
+    let users = schema::users::table
+        .filter(schema::users::year_joined.eq(year))
+        .order((schema::users::created_at.asc(), schema::users::id.asc()))
+        .select((
+            schema::users::id,
+            schema::users::first_name,
+            schema::users::last_name,
+            schema::users::reg_no,
+        ))
+        .load::<(Uuid, String, String, String)>(conn)
+        .await?;
+
+    let mut changes = Vec::new();
+    let mut unchanged = 0;
+    for (code, (user_id, first_name, last_name, before)) in (1..).zip(users) {
+        let after = User::format_reg_no(format, year, code);
+        if after == before {
+            unchanged += 1;
+        } else {
+            changes.push(RegNoChange {
+                user_id,
+                first_name,
+                last_name,
+                before,
+                after,
+            });
+        }
+    }
+
+    let new_reg_nos: Vec<&String> = changes.iter().map(|change| &change.after).collect();
+    let clashes = schema::users::table
+        .filter(schema::users::year_joined.ne(year))
+        .filter(schema::users::reg_no.eq_any(new_reg_nos))
+        .select(schema::users::reg_no)
+        .limit(5)
+        .load::<String>(conn)
+        .await?;
+    if !clashes.is_empty() {
+        return Err(ModuleError::BadRequest(
+            format!(
+                "Users of other years already hold {}; pick a format that includes the year",
+                clashes.join(", ")
+            )
+            .into(),
+        ));
+    }
+    Ok((changes, unchanged))
+}
+
+fn format_for(payload: &RenumberRequest, default: String) -> Result<String, ModuleError> {
+    let format = payload.format.clone().unwrap_or(default);
+    validate_format(&format)?;
+    Ok(format)
+}
+
+/// Shows what `renumber` would change, without changing anything.
+pub async fn preview_renumber(
+    pool: Arc<Pool>,
+    settings: &crate::SettingsCache,
+    payload: RenumberRequest,
+) -> Result<RenumberReport, ModuleError> {
+    let format = format_for(&payload, settings.read().await.reg_no_format.clone())?;
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|_| ModuleError::InternalError(POOL_ERROR_MSG.into()))?;
+
+    let (changes, unchanged) = plan(&mut conn, &payload.year, &format).await?;
+    Ok(RenumberReport {
+        year: payload.year,
+        format,
+        changes,
+        unchanged,
+    })
+}
+
+/// Renumbers the users of a year from 1 in creation order and restarts the year's sequence
+/// after the last of them. Registrations for the year wait until it is done.
+pub async fn renumber(
+    pool: Arc<Pool>,
+    settings: &crate::SettingsCache,
+    payload: RenumberRequest,
+    performer_id: Uuid,
+) -> Result<RenumberReport, ModuleError> {
+    let format = format_for(&payload, settings.read().await.reg_no_format.clone())?;
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|_| ModuleError::InternalError(POOL_ERROR_MSG.into()))?;
+
+    let year = payload.year.clone();
+    let txn_format = format.clone();
+    let (changes, unchanged) = conn
+        .build_transaction()
+        .run(|conn| {
+            Box::pin(async move {
+                // Holding the sequence row keeps new registrations for the year out.
+                diesel::insert_into(schema::reg_no_sequences::table)
+                    .values(schema::reg_no_sequences::year.eq(&year))
+                    .on_conflict_do_nothing()
+                    .execute(conn)
+                    .await?;
+                schema::reg_no_sequences::table
+                    .find(&year)
+                    .for_update()
+                    .select(schema::reg_no_sequences::last_value)
+                    .first::<i64>(conn)
+                    .await?;
+
+                let (changes, unchanged) = plan(conn, &year, &txn_format).await?;
+                // Park every changing reg_no first, so swapping two never trips the unique index.
+                for change in &changes {
+                    diesel::update(schema::users::table.find(change.user_id))
+                        .set(schema::users::reg_no.eq(format!("renumbering/{}", change.user_id)))
+                        .execute(conn)
+                        .await?;
+                }
+                for change in &changes {
+                    diesel::update(schema::users::table.find(change.user_id))
+                        .set((
+                            schema::users::reg_no.eq(&change.after),
+                            schema::users::updated_at.eq(chrono::Local::now().naive_local()),
+                        ))
+                        .execute(conn)
+                        .await?;
+                }
+                let total = (changes.len() + unchanged) as i64;
+                diesel::update(schema::reg_no_sequences::table.find(&year))
+                    .set((
+                        schema::reg_no_sequences::last_value.eq(total),
+                        schema::reg_no_sequences::updated_at.eq(chrono::Utc::now().naive_utc()),
+                    ))
+                    .execute(conn)
+                    .await?;
+                Ok::<_, ModuleError>((changes, unchanged))
+            })
+        })
+        .await?;
+
+    for change in &changes {
+        let log = ActivityLog::new(ActivityType::UserUpdated, performer_id)
+            .set_target_id(change.user_id)
+            .set_target_type("User".into())
+            .set_details(serde_json::json!({
+                "source": "renumber",
+                "before": { "reg_no": change.before },
+                "after": { "reg_no": change.after },
+            }))
+            .finish();
+        crate::services::activity_logs::emit_log(log, &mut conn).await?;
+    }
+    let log = ActivityLog::new(ActivityType::RegNosRenumbered, performer_id)
+        .set_details(serde_json::json!({
+            "year": payload.year,
+            "format": format,
+            "changed": changes.len(),
+            "unchanged": unchanged,
+        }))
+        .finish();
+    crate::services::activity_logs::emit_log(log, &mut conn).await?;
+
+    Ok(RenumberReport {
+        year: payload.year,
+        format,
+        changes,
+        unchanged,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rejects(format: &str, reason: &str) {
+        match validate_format(format) {
+            Err(ModuleError::BadRequest(message)) => assert!(
+                message.contains(reason),
+                "{format}: expected '{reason}', got '{message}'"
+            ),
+            other => panic!("{format}: expected BadRequest, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn accepts_templates_with_year_and_seq() {
+        for format in [
+            crate::dto::settings::DEFAULT_REG_NO_FORMAT,
+            "{yy}{seq}",
+            "KUD-{year}-{seq:09}",
+        ] {
+            assert!(validate_format(format).is_ok(), "{format}");
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_templates() {
+        rejects("{year}/{seq", "unclosed '{'");
+        rejects("{year}/{dept}/{seq}", "unknown placeholder '{dept}'");
+        rejects("{year}/{seq:00}", "unknown placeholder");
+        rejects("{year}/{seq:010}", "unknown placeholder");
+        rejects("{year}}/{seq}", "unbalanced braces");
+    }
+
+    #[test]
+    fn requires_seq_and_year() {
+        rejects("KUD/{year}", "{seq}");
+        rejects("KUD/{seq:03}", "{year}");
+    }
+}
@@ -0,0 +1,268 @@
+use super::*;
+use crate::auth::middleware::ConnectionInfo;
+use crate::dto::invites::InviteUserRequest;
+use crate::dto::registrations::*;
+use crate::mailer::{
+    types::MailerEvent,
+    worker::{MailerSender, dispatch},
+};
+use crate::models::activity_logs::{ActivityLog, ActivityType};
+use crate::models::registration_applications::{ApplicationStatus, RegistrationApplication};
+use crate::models::users::{Role, User};
+
+/// Queues a self-registration for review. An email can only have one pending application,
+/// and cannot apply once it belongs to a user. Those applications are dropped, but get the
+/// same answer as accepted ones so the route cannot tell who has an account.
+pub async fn submit_application(
+    pool: Arc<Pool>,
+    connection: ConnectionInfo,
+    payload: RegistrationRequest,
+) -> Result<Message<()>, ModuleError> {
+    let first_name = payload.first_name.trim().to_string();
+    let last_name = payload.last_name.trim().to_string();
+    let email = payload.email.trim().to_lowercase();
+    let year_joined = payload.year_joined.replace("Year ", "").trim().to_string();
+    if first_name.is_empty() || last_name.is_empty() || email.is_empty() {
+        return Err(ModuleError::BadRequest(
+            "First name, last name and email are required".into(),
+        ));
+    }
+    if year_joined.parse::<i32>().is_err() {
+        return Err(ModuleError::BadRequest(
+            "year_joined must be a year, e.g. 2024".into(),
+        ));
+    }
+
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|_| ModuleError::InternalError(POOL_ERROR_MSG.into()))?;
+
+    let throttle_keys = crate::services::login_throttles::application_keys(&connection.ip);
+    crate::services::login_throttles::ensure_not_locked(&mut conn, &throttle_keys).await?;
+    crate::services::login_throttles::record_failure(&mut conn, &throttle_keys).await?;
+
+    let received = "Application received; you will get an email once it has been reviewed";
+    let is_user = schema::users::table
+        .filter(super::lower(schema::users::email).eq(&email))
+        .count()
+        .get_result::<i64>(&mut conn)
+        .await?
+        > 0;
+    let is_pending = schema::registration_applications::table
+        .filter(schema::registration_applications::email.eq(&email))
+        .filter(schema::registration_applications::status.eq(ApplicationStatus::Pending))
+        .count()
+        .get_result::<i64>(&mut conn)
+        .await?
+        > 0;
+    if is_user || is_pending {
+        return Ok(received.into());
+    }
+
+    let application = RegistrationApplication {
+        id: Uuid::now_v7(),
+        first_name,
+        last_name,
+        email,
+        dob: payload.dob,
+        year_joined,
+        gender: payload.gender,
+        phone: payload.phone,
+        status: ApplicationStatus::Pending,
+        rejection_reason: None,
+        reviewed_by: None,
+        reviewed_at: None,
+        user_id: None,
+        created_at: chrono::Utc::now().naive_utc(),
+    };
+    diesel::insert_into(schema::registration_applications::table)
+        .values(&application)
+        .execute(&mut conn)
+        .await?;
+
+    Ok(received.into())
+}
+
+pub async fn get_applications(
+    pool: Arc<Pool>,
+    filter: ApplicationFilter,
+) -> Result<Vec<RegistrationApplication>, ModuleError> {
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|_| ModuleError::InternalError(POOL_ERROR_MSG.into()))?;
+
+    let mut query = schema::registration_applications::table
+        .order(schema::registration_applications::created_at.desc())
+        .select(RegistrationApplication::as_select())
+        .into_boxed();
+    if let Some(status) = filter.status {
+        query = query.filter(schema::registration_applications::status.eq(status));
+    }
+    let applications = query.load::<RegistrationApplication>(&mut conn).await?;
+    Ok(applications)
+}
+
+/// Creates the user behind a pending application, with a reg_no allocated like
+/// `register_user`, and emails the applicant a link to set their password.
+pub async fn approve_application(
+    pool: Arc<Pool>,
+    mailer: &MailerSender,
+    settings: &crate::SettingsCache,
+    id: Uuid,
+    performer_id: Uuid,
+) -> Result<Message<()>, ModuleError> {
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|_| ModuleError::InternalError(POOL_ERROR_MSG.into()))?;
+
+    let application = pending_application(&mut conn, id).await?;
+    let taken = schema::users::table
+        .filter(super::lower(schema::users::email).eq(&application.email))
+        .count()
+        .get_result::<i64>(&mut conn)
+        .await?
+        > 0;
+    if taken {
+        return Err(ModuleError::BadRequest(
+            "A user with this email already exists".into(),
+        ));
+    }
+
+    let user: User = InviteUserRequest {
+        first_name: application.first_name.clone(),
+        last_name: application.last_name.clone(),
+        email: application.email.clone(),
+        dob: application.dob,
+        year_joined: application.year_joined.clone(),
+        role: Role::User,
+        gender: application.gender.clone(),
+        phone: application.phone.clone(),
+    }
+    .into();
+
+    let reg_no_format = settings.read().await.reg_no_format.clone();
+    let (user, url) = conn
+        .build_transaction()
+        .run(|conn| {
+            Box::pin(async move {
+                let mut user = user;
+                crate::services::users::insert_user(conn, &mut user, &reg_no_format).await?;
+                // Claim the application so a concurrent approval cannot create a second user.
+                let claimed =
+                    diesel::update(schema::registration_applications::table.find(id).filter(
+                        schema::registration_applications::status.eq(ApplicationStatus::Pending),
+                    ))
+                    .set((
+                        schema::registration_applications::status.eq(ApplicationStatus::Approved),
+                        schema::registration_applications::reviewed_by.eq(performer_id),
+                        schema::registration_applications::reviewed_at
+                            .eq(chrono::Utc::now().naive_utc()),
+                        schema::registration_applications::user_id.eq(user.id),
+                    ))
+                    .execute(conn)
+                    .await?;
+                if claimed == 0 {
+                    return Err(already_reviewed());
+                }
+                let (_, url) =
+                    crate::services::invites::create_invite(conn, user.id, performer_id).await?;
+                Ok::<_, ModuleError>((user, url))
+            })
+        })
+        .await?;
+
+    let log = ActivityLog::new(ActivityType::ApplicationApproved, performer_id)
+        .set_target_id(id)
+        .set_target_type("RegistrationApplication".into())
+        .set_details(serde_json::json!({ "user_id": user.id, "reg_no": user.reg_no }))
+        .finish();
+    crate::services::activity_logs::emit_log(log, &mut conn).await?;
+
+    dispatch(
+        mailer,
+        MailerEvent::SendApplicationApprovedEmail {
+            name: user.first_name,
+            url,
+            to: user.email,
+        },
+    );
+
+    Ok("Application approved".into())
+}
+
+pub async fn reject_application(
+    pool: Arc<Pool>,
+    mailer: &MailerSender,
+    id: Uuid,
+    payload: RejectApplicationRequest,
+    performer_id: Uuid,
+) -> Result<Message<()>, ModuleError> {
+    let reason = payload.reason.trim().to_string();
+    if reason.is_empty() {
+        return Err(ModuleError::BadRequest(
+            "A reason for the rejection is required".into(),
+        ));
+    }
+
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|_| ModuleError::InternalError(POOL_ERROR_MSG.into()))?;
+
+    let application = pending_application(&mut conn, id).await?;
+    let rejected = diesel::update(
+        schema::registration_applications::table
+            .find(id)
+            .filter(schema::registration_applications::status.eq(ApplicationStatus::Pending)),
+    )
+    .set((
+        schema::registration_applications::status.eq(ApplicationStatus::Rejected),
+        schema::registration_applications::rejection_reason.eq(&reason),
+        schema::registration_applications::reviewed_by.eq(performer_id),
+        schema::registration_applications::reviewed_at.eq(chrono::Utc::now().naive_utc()),
+    ))
+    .execute(&mut conn)
+    .await?;
+    if rejected == 0 {
+        return Err(already_reviewed());
+    }
+
+    let log = ActivityLog::new(ActivityType::ApplicationRejected, performer_id)
+        .set_target_id(id)
+        .set_target_type("RegistrationApplication".into())
+        .set_details(serde_json::json!({ "email": application.email, "reason": reason }))
+        .finish();
+    crate::services::activity_logs::emit_log(log, &mut conn).await?;
+
+    dispatch(
+        mailer,
+        MailerEvent::SendApplicationRejectedEmail {
+            name: application.first_name,
+            to: application.email,
+            reason,
+        },
+    );
+
+    Ok("Application rejected".into())
+}
+
+async fn pending_application(
+    conn: &mut crate::Connection<'_>,
+    id: Uuid,
+) -> Result<RegistrationApplication, ModuleError> {
+    schema::registration_applications::table
+        .find(id)
+        .filter(schema::registration_applications::status.eq(ApplicationStatus::Pending))
+        .select(RegistrationApplication::as_select())
+        .first::<RegistrationApplication>(conn)
+        .await
+        .optional()?
+        .ok_or_else(already_reviewed)
+}
+
+fn already_reviewed() -> ModuleError {
+    ModuleError::ResourceNotFound("Application not found or already reviewed".into())
+}
@@ -1,10 +1,12 @@
 #![allow(deprecated)]
 use super::*;
+use crate::dto::exports::ExportFormat;
 use crate::dto::roster::*;
 use crate::models::activity_logs::{ActivityLog, ActivityType};
 use crate::models::roster::*;
 use crate::models::users::User;
 use crate::models::users_roster::UsersRoster;
+use crate::services::exports::{Cell, Sheet};
 use axum::extract::Multipart;
 use rand::seq::IndexedRandom;
 
@@ -79,6 +81,7 @@ pub async fn activate_roster(
             Box::pin(async move {
                 let users = crate::schema::users::table
                     .filter(crate::schema::users::is_active.eq(true))
+                    .filter(crate::schema::users::deleted_at.is_null())
                     .select(crate::schema::users::id)
                     .load::<Uuid>(conn)
                     .await?;
@@ -230,6 +233,7 @@ pub async fn activate_roster_gendered(
                 // Load active users with their gender
                 let users: Vec<(Uuid, Option<String>)> = crate::schema::users::table
                     .filter(crate::schema::users::is_active.eq(true))
+                    .filter(crate::schema::users::deleted_at.is_null())
                     .select((crate::schema::users::id, crate::schema::users::gender))
                     .load::<(Uuid, Option<String>)>(conn)
                     .await?;
@@ -518,6 +522,7 @@ pub async fn delete_roster(
 pub async fn export_roster(
     id: Uuid,
     conn_pool: Arc<Pool>,
+    format: ExportFormat,
 ) -> Result<(axum::http::HeaderMap, Vec<u8>), ModuleError> {
     let mut conn = conn_pool.get().await?;
     let roster = crate::schema::rosters::table
@@ -526,13 +531,14 @@ pub async fn export_roster(
         .await
         .map_err(|_| ModuleError::ResourceNotFound("No active roster found".into()))?;
 
-    export_roster_data(conn, roster.id, roster.name).await
+    export_roster_data(conn, roster.id, roster.name, format).await
 }
 
 pub async fn export_roster_by_hall(
     id: Uuid,
     conn_pool: Arc<Pool>,
     hall: Hall,
+    format: ExportFormat,
 ) -> Result<(axum::http::HeaderMap, Vec<u8>), ModuleError> {
     let mut conn = conn_pool.get().await?;
     let roster = crate::schema::rosters::table
@@ -541,22 +547,26 @@ pub async fn export_roster_by_hall(
         .await
         .map_err(|_| ModuleError::ResourceNotFound("No active roster found".into()))?;
 
-    export_roster_data_filtered(conn, roster.id, roster.name, Some(hall)).await
+    export_roster_data_filtered(conn, roster.id, roster.name, Some(hall), format).await
 }
 
 async fn export_roster_data(
     conn: crate::Connection<'_>,
     roster_id: Uuid,
     roster_name: String,
+    format: ExportFormat,
 ) -> Result<(axum::http::HeaderMap, Vec<u8>), ModuleError> {
-    export_roster_data_filtered(conn, roster_id, roster_name, None).await
+    export_roster_data_filtered(conn, roster_id, roster_name, None, format).await
 }
 
+/// Workbooks get one worksheet per hall, numbered from 1 on each; CSV files list every hall
+/// in one table.
 async fn export_roster_data_filtered(
     mut conn: crate::Connection<'_>,
     roster_id: Uuid,
     roster_name: String,
     filter_hall: Option<Hall>,
+    format: ExportFormat,
 ) -> Result<(axum::http::HeaderMap, Vec<u8>), ModuleError> {
     let mut query = crate::schema::users_rosters::table
         .filter(crate::schema::users_rosters::roster_id.eq(roster_id))
@@ -577,38 +587,46 @@ async fn export_roster_data_filtered(
         .load::<(String, String, String, Hall)>(&mut conn)
         .await?;
 
-    let mut wtr = csv::Writer::from_writer(vec![]);
-    wtr.write_record(&["S/N", "Reg No", "Full Name", "Hall"])
-        .map_err(|e| ModuleError::InternalError(e.to_string().into()))?;
-
-    for (i, (reg_no, first_name, last_name, hall)) in data.into_iter().enumerate() {
-        let full_name = format!("{} {}", first_name, last_name);
-        let hall_str = serde_json::to_string(&hall)
+    let hall_name = |hall: &Hall| {
+        serde_json::to_string(hall)
             .unwrap_or_else(|_| "Unknown".to_string())
-            .replace("\"", "");
-        wtr.write_record(&[(i + 1).to_string(), reg_no, full_name, hall_str])
-            .map_err(|e| ModuleError::InternalError(e.to_string().into()))?;
-    }
-
-    let csv_data = wtr
-        .into_inner()
-        .map_err(|e| ModuleError::InternalError(e.to_string().into()))?;
-
-    let mut headers = axum::http::HeaderMap::new();
-    headers.insert(
-        axum::http::header::CONTENT_TYPE,
-        axum::http::header::HeaderValue::from_static("text/csv"),
-    );
-    headers.insert(
-        axum::http::header::CONTENT_DISPOSITION,
-        axum::http::header::HeaderValue::from_str(&format!(
-            "attachment; filename=\"roster_{}.csv\"",
-            roster_name.replace(" ", "_")
-        ))
-        .map_err(|e| ModuleError::InternalError(e.to_string().into()))?,
-    );
+            .replace("\"", "")
+    };
+    let to_row =
+        |(i, (reg_no, first_name, last_name, hall)): (usize, &(String, String, String, Hall))| {
+            vec![
+                Cell::Number((i + 1) as f64),
+                Cell::Text(reg_no.clone()),
+                Cell::Text(format!("{} {}", first_name, last_name)),
+                Cell::Text(hall_name(hall)),
+            ]
+        };
+    let headers = ["S/N", "Reg No", "Full Name", "Hall"]
+        .map(String::from)
+        .to_vec();
+    let sheets = match format {
+        ExportFormat::Csv => vec![Sheet {
+            name: roster_name.clone(),
+            headers,
+            rows: data.iter().enumerate().map(to_row).collect(),
+        }],
+        ExportFormat::Xlsx => Hall::all()
+            .into_iter()
+            .filter(|hall| filter_hall.as_ref().is_none_or(|filter| filter == hall))
+            .map(|hall| Sheet {
+                name: hall_name(&hall),
+                headers: headers.clone(),
+                rows: data
+                    .iter()
+                    .filter(|(.., assigned)| *assigned == hall)
+                    .enumerate()
+                    .map(to_row)
+                    .collect(),
+            })
+            .collect(),
+    };
 
-    Ok((headers, csv_data))
+    crate::services::exports::export(format, &format!("roster_{roster_name}"), sheets)
 }
 
 pub async fn view_roster_assignments(
@@ -634,14 +652,32 @@ pub async fn view_roster_assignments(
     Ok(assignments)
 }
 
+/// Assigns users to halls from an uploaded file, read through the import profile named by
+/// `profile_id`. Rows without a hall are skipped, as are rows whose user or hall is unknown.
 pub async fn import_roster(
     pool: Arc<Pool>,
     roster_id: Uuid,
-    mut multipart: Multipart,
+    multipart: Multipart,
+    profile_id: Option<Uuid>,
     performer_id: Uuid,
 ) -> Result<(), ModuleError> {
     let mut conn = pool.get().await?;
 
+    let profile = crate::services::import_profiles::resolve(
+        &mut conn,
+        profile_id,
+        crate::models::import_profiles::ImportKind::Roster,
+    )
+    .await?;
+    let upload = crate::services::imports::read_upload(multipart).await?;
+    let table = crate::services::imports::read_table(&upload)?;
+    let mapper = crate::services::imports::ColumnMapper::new(profile, &table.headers)?;
+    let records = table
+        .records
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| ModuleError::BadRequest(e.into()))?;
+
     conn.build_transaction()
         .run(|conn| {
             Box::pin(async move {
@@ -652,53 +688,42 @@ pub async fn import_roster(
                     .map_err(|_| ModuleError::ResourceNotFound("Roster not found".into()))?;
 
                 let mut user_roster = Vec::new();
-                while let Some(field) = multipart
-                    .next_field()
-                    .await
-                    .map_err(|e| ModuleError::InternalError(e.to_string().into()))?
-                {
-                    let data = field
-                        .bytes()
+                for record in &records {
+                    let first_name = mapper.value(record, "first_name");
+                    let last_name = mapper.value(record, "last_name");
+                    let hall_label = mapper.value(record, "hall");
+                    if hall_label.is_empty() {
+                        continue;
+                    }
+                    let Some(hall) = Hall::from_label(&hall_label) else {
+                        tracing::warn!(
+                            "Unknown hall '{}' for roster assignment: {} {}",
+                            hall_label,
+                            first_name,
+                            last_name
+                        );
+                        continue;
+                    };
+
+                    let user = crate::schema::users::table
+                        .filter(crate::schema::users::first_name.eq(&first_name))
+                        .filter(crate::schema::users::last_name.eq(&last_name))
+                        .filter(crate::schema::users::deleted_at.is_null())
+                        .select(User::as_select())
+                        .first::<User>(conn)
                         .await
-                        .map_err(|e| ModuleError::InternalError(e.to_string().into()))?;
-                    let body = String::from_utf8(data.to_vec())
-                        .map_err(|e| ModuleError::InternalError(e.to_string().into()))?;
-                    let mut rdr = csv::Reader::from_reader(body.as_bytes());
-
-                    for result in rdr.deserialize() {
-                        let csv_user: CsvUserRoster =
-                            result.map_err(|e| ModuleError::InternalError(e.to_string().into()))?;
-
-                        let user = crate::schema::users::table
-                            .filter(crate::schema::users::first_name.eq(&csv_user.first_name))
-                            .filter(crate::schema::users::last_name.eq(&csv_user.last_name))
-                            .select(User::as_select())
-                            .first::<User>(conn)
-                            .await
-                            .optional()?;
-
-                        if let Some(user) = user {
-                            match csv_user.hall {
-                                Some(hall) => {
-                                    let new_assignment = UsersRoster::new(
-                                        user.id,
-                                        roster_id,
-                                        hall,
-                                        roster.year.clone(),
-                                    );
-                                    user_roster.push(new_assignment);
-                                }
-                                None => {
-                                    continue;
-                                }
-                            }
-                        } else {
-                            tracing::warn!(
-                                "User not found for roster assignment: {} {}",
-                                csv_user.first_name,
-                                csv_user.last_name
-                            );
-                        }
+                        .optional()?;
+
+                    if let Some(user) = user {
+                        let new_assignment =
+                            UsersRoster::new(user.id, roster_id, hall, roster.year.clone());
+                        user_roster.push(new_assignment);
+                    } else {
+                        tracing::warn!(
+                            "User not found for roster assignment: {} {}",
+                            first_name,
+                            last_name
+                        );
                     }
                 }
                 if user_roster.is_empty() {
@@ -726,6 +751,7 @@ pub async fn import_roster(
     let log = ActivityLog::new(ActivityType::RosterActivated, performer_id)
         .set_target_id(roster_id)
         .set_target_type("Roster".into())
+        .set_details(serde_json::json!({ "profile_id": profile_id }))
         .finish();
     crate::services::activity_logs::emit_log(log, &mut conn).await?;
 
@@ -0,0 +1,113 @@
+use super::*;
+use crate::auth::service::revoke_session;
+use crate::dto::session::*;
+use crate::models::activity_logs::{ActivityLog, ActivityType};
+
+/// Lists the sessions of `user_id` that are neither revoked nor expired, newest first.
+/// `current_session` is flagged in the result so clients can tell "this device" apart.
+pub async fn list_active_sessions(
+    pool: Arc<Pool>,
+    user_id: Uuid,
+    current_session: Option<Uuid>,
+) -> Result<Vec<SessionDto>, ModuleError> {
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|_| ModuleError::InternalError(POOL_ERROR_MSG.into()))?;
+
+    let sessions = schema::sessions::table
+        .filter(schema::sessions::user_id.eq(user_id))
+        .filter(schema::sessions::revoked_at.is_null())
+        .filter(schema::sessions::expires_at.gt(chrono::Utc::now().naive_utc()))
+        .order(schema::sessions::last_seen_at.desc())
+        .select(SessionRow::as_select())
+        .load::<SessionRow>(&mut conn)
+        .await?;
+
+    Ok(sessions
+        .into_iter()
+        .map(|row| SessionDto::from_row(row, current_session))
+        .collect())
+}
+
+pub async fn revoke_user_session(
+    pool: Arc<Pool>,
+    user_id: Uuid,
+    session_id: Uuid,
+    performer_id: Uuid,
+) -> Result<Message<()>, ModuleError> {
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|_| ModuleError::InternalError(POOL_ERROR_MSG.into()))?;
+
+    let exists = schema::sessions::table
+        .filter(schema::sessions::id.eq(session_id))
+        .filter(schema::sessions::user_id.eq(user_id))
+        .count()
+        .get_result::<i64>(&mut conn)
+        .await?
+        > 0;
+    if !exists {
+        return Err(ModuleError::ResourceNotFound("Session not found".into()));
+    }
+
+    revoke_session(&mut conn, session_id).await?;
+
+    let log = ActivityLog::new(ActivityType::SessionRevoked, performer_id)
+        .set_target_id(user_id)
+        .set_target_type("User".into())
+        .set_details(serde_json::json!({ "session_ids": [session_id] }))
+        .finish();
+    crate::services::activity_logs::emit_log(log, &mut conn).await?;
+
+    Ok("Session signed out successfully".into())
+}
+
+/// Revokes every active session of `user_id`, except `keep` when given.
+pub async fn revoke_user_sessions(
+    pool: Arc<Pool>,
+    user_id: Uuid,
+    keep: Option<Uuid>,
+    performer_id: Uuid,
+) -> Result<Message<()>, ModuleError> {
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|_| ModuleError::InternalError(POOL_ERROR_MSG.into()))?;
+
+    let revoked = revoke_all_sessions(&mut conn, user_id, keep).await?;
+
+    let log = ActivityLog::new(ActivityType::SessionRevoked, performer_id)
+        .set_target_id(user_id)
+        .set_target_type("User".into())
+        .set_details(serde_json::json!({ "session_ids": revoked }))
+        .finish();
+    crate::services::activity_logs::emit_log(log, &mut conn).await?;
+
+    Ok(Message::new(
+        format!("Signed out of {} session(s)", revoked.len()),
+        None,
+    ))
+}
+
+pub async fn revoke_all_sessions(
+    conn: &mut crate::AsyncPgConnection,
+    user_id: Uuid,
+    keep: Option<Uuid>,
+) -> Result<Vec<Uuid>, ModuleError> {
+    let mut query = schema::sessions::table
+        .filter(schema::sessions::user_id.eq(user_id))
+        .filter(schema::sessions::revoked_at.is_null())
+        .select(schema::sessions::id)
+        .into_boxed();
+    if let Some(keep) = keep {
+        query = query.filter(schema::sessions::id.ne(keep));
+    }
+    let session_ids = query.load::<Uuid>(conn).await?;
+
+    for session_id in &session_ids {
+        revoke_session(conn, *session_id).await?;
+    }
+    Ok(session_ids)
+}
@@ -0,0 +1,98 @@
+use super::*;
+use crate::SettingsCache;
+use crate::dto::settings::*;
+use crate::models::activity_logs::{ActivityLog, ActivityType};
+use crate::models::settings::Setting;
+
+/// Bounds for the `password_min_length` setting.
+const MIN_PASSWORD_LENGTH: usize = 8;
+const MAX_PASSWORD_LENGTH: usize = 128;
+
+/// Loads the organisation settings into a fresh cache for `AppState`.
+pub async fn load_settings(pool: Arc<Pool>) -> Result<SettingsCache, ModuleError> {
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|_| ModuleError::InternalError(POOL_ERROR_MSG.into()))?;
+    let settings = fetch_settings(&mut conn).await?;
+    Ok(Arc::new(tokio::sync::RwLock::new(settings)))
+}
+
+async fn fetch_settings(
+    conn: &mut crate::Connection<'_>,
+) -> Result<OrganisationSettings, ModuleError> {
+    let rows = schema::settings::table
+        .select(Setting::as_select())
+        .load::<Setting>(conn)
+        .await?;
+    let object: serde_json::Map<String, serde_json::Value> =
+        rows.into_iter().map(|row| (row.key, row.value)).collect();
+    Ok(serde_json::from_value(serde_json::Value::Object(object))?)
+}
+
+/// The reg_no template, read straight from the table for code that runs before the cache
+/// exists.
+pub async fn reg_no_format(conn: &mut crate::Connection<'_>) -> Result<String, ModuleError> {
+    Ok(fetch_settings(conn).await?.reg_no_format)
+}
+
+pub async fn get_settings(cache: &SettingsCache) -> OrganisationSettings {
+    cache.read().await.clone()
+}
+
+/// Stores every field present in `payload` and reloads the cache.
+pub async fn update_settings(
+    pool: Arc<Pool>,
+    cache: &SettingsCache,
+    payload: UpdateSettingsRequest,
+    performer_id: Uuid,
+) -> Result<OrganisationSettings, ModuleError> {
+    if payload
+        .password_min_length
+        .is_some_and(|length| !(MIN_PASSWORD_LENGTH..=MAX_PASSWORD_LENGTH).contains(&length))
+    {
+        return Err(ModuleError::BadRequest(
+            format!(
+                "password_min_length must be between {MIN_PASSWORD_LENGTH} and {MAX_PASSWORD_LENGTH}"
+            )
+            .into(),
+        ));
+    }
+
+    if let Some(format) = &payload.reg_no_format {
+        crate::services::reg_nos::validate_format(format)?;
+    }
+
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|_| ModuleError::InternalError(POOL_ERROR_MSG.into()))?;
+
+    let changes = serde_json::to_value(&payload)?;
+    if let serde_json::Value::Object(fields) = &changes {
+        for (key, value) in fields {
+            let setting = Setting::new(key, value.clone(), performer_id);
+            diesel::insert_into(schema::settings::table)
+                .values(&setting)
+                .on_conflict(schema::settings::key)
+                .do_update()
+                .set((
+                    schema::settings::value.eq(&setting.value),
+                    schema::settings::updated_by.eq(setting.updated_by),
+                    schema::settings::updated_at.eq(setting.updated_at),
+                ))
+                .execute(&mut conn)
+                .await?;
+        }
+    }
+
+    let log = ActivityLog::new(ActivityType::SettingsUpdated, performer_id)
+        .set_target_type("Settings".into())
+        .set_details(changes)
+        .finish();
+    crate::services::activity_logs::emit_log(log, &mut conn).await?;
+
+    let settings = fetch_settings(&mut conn).await?;
+    *cache.write().await = settings.clone();
+    Ok(settings)
+}
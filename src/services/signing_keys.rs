@@ -0,0 +1,22 @@
+use super::*;
+use crate::auth::keys::SigningKeyDto;
+use crate::models::activity_logs::{ActivityLog, ActivityType};
+
+pub async fn rotate_signing_key(
+    pool: Arc<Pool>,
+    performer_id: Uuid,
+) -> Result<SigningKeyDto, ModuleError> {
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|_| ModuleError::InternalError(POOL_ERROR_MSG.into()))?;
+
+    let key = crate::auth::keys::rotate()?;
+
+    let log = ActivityLog::new(ActivityType::SigningKeyRotated, performer_id)
+        .set_details(serde_json::json!({ "kid": key.kid }))
+        .finish();
+    crate::services::activity_logs::emit_log(log, &mut conn).await?;
+
+    Ok(key)
+}
@@ -243,6 +243,69 @@ pub async fn get_attendance_on_day(
     Ok(Message::new("Attendance found", Some(response)))
 }
 
+/// Exports every attendance record between two days, oldest first.
+pub async fn export_attendance(
+    pool: Arc<Pool>,
+    query: AttendanceExportQuery,
+) -> Result<(axum::http::HeaderMap, Vec<u8>), ModuleError> {
+    use crate::services::exports::{Cell, Sheet};
+
+    if query.from > query.to {
+        return Err(ModuleError::BadRequest(
+            "`from` must not be after `to`".into(),
+        ));
+    }
+    let mut conn = pool.get().await?;
+
+    let records = schema::user_attendance::table
+        .inner_join(schema::users::table)
+        .filter(schema::user_attendance::date.between(query.from, query.to))
+        .order_by((
+            schema::user_attendance::date.asc(),
+            schema::user_attendance::time_in.asc(),
+        ))
+        .select((UserAttendanceDto::as_select(), UserDto::as_select()))
+        .load::<(UserAttendanceDto, UserDto)>(&mut conn)
+        .await?;
+
+    let rows = records
+        .into_iter()
+        .map(|(attendance, user)| {
+            vec![
+                Cell::Date(attendance.date),
+                Cell::Text(attendance.week_day),
+                Cell::Text(user.reg_no),
+                Cell::Text(user.first_name),
+                Cell::Text(user.last_name),
+                Cell::DateTime(attendance.time_in),
+                attendance.time_out.map_or(Cell::Empty, Cell::DateTime),
+                Cell::Text(format!("{:?}", attendance.attendance_type)),
+            ]
+        })
+        .collect();
+    let sheet = Sheet {
+        name: "Attendance".to_string(),
+        headers: [
+            "Date",
+            "Week Day",
+            "Reg No",
+            "First Name",
+            "Last Name",
+            "Time In",
+            "Time Out",
+            "Type",
+        ]
+        .map(String::from)
+        .to_vec(),
+        rows,
+    };
+    crate::services::exports::export(
+        query.format,
+        &format!("attendance_{}_{}", query.from, query.to),
+        vec![sheet],
+    )
+}
+
 pub async fn revoke_attendance(
     pool: Arc<Pool>,
     id: Uuid,
@@ -0,0 +1,261 @@
+use std::collections::HashMap;
+
+use super::*;
+use crate::dto::user::*;
+use crate::models::activity_logs::{ActivityLog, ActivityType};
+use crate::models::roster::Hall;
+use crate::models::users::Role;
+
+/// Most users one bulk request may touch.
+pub const MAX_BULK_USERS: usize = 1000;
+
+/// The columns every action looks at before deciding what to do.
+#[derive(Queryable)]
+struct Target {
+    id: Uuid,
+    role: Role,
+    is_active: bool,
+    device_id: Option<String>,
+    deleted_at: Option<NaiveDateTime>,
+}
+
+fn result(user_id: Uuid, outcome: BulkOutcome, message: Option<&str>) -> BulkUserResult {
+    BulkUserResult {
+        user_id,
+        outcome,
+        message: message.map(str::to_string),
+    }
+}
+
+async fn select_users(
+    conn: &mut crate::Connection<'_>,
+    payload: &BulkUserRequest,
+) -> Result<Vec<Uuid>, ModuleError> {
+    let mut ids = match (&payload.user_ids, &payload.filter) {
+        (Some(ids), None) => ids.clone(),
+        (None, Some(filter)) => {
+            let fields = crate::services::custom_fields::load(conn).await?;
+            crate::services::users::filter_users(filter, &fields)?
+                .select(schema::users::id)
+                .order(schema::users::id.asc())
+                .limit(MAX_BULK_USERS as i64 + 1)
+                .load::<Uuid>(conn)
+                .await?
+        }
+        _ => {
+            return Err(ModuleError::BadRequest(
+                "Give either user_ids or filter".into(),
+            ));
+        }
+    };
+    let mut seen = std::collections::HashSet::new();
+    ids.retain(|id| seen.insert(*id));
+    if ids.is_empty() {
+        return Err(ModuleError::BadRequest("No users selected".into()));
+    }
+    if ids.len() > MAX_BULK_USERS {
+        return Err(ModuleError::BadRequest(
+            format!("At most {MAX_BULK_USERS} users can be changed at once").into(),
+        ));
+    }
+    Ok(ids)
+}
+
+/// Applies `action` to one user. Decisions that leave the user alone come back as results;
+/// database errors abort the whole request.
+async fn apply(
+    conn: &mut crate::AsyncPgConnection,
+    action: &BulkUserAction,
+    target: &Target,
+    active_roster: Option<Uuid>,
+    performer_id: Uuid,
+) -> Result<BulkUserResult, ModuleError> {
+    use schema::users;
+
+    let id = target.id;
+    if target.deleted_at.is_some() {
+        return Ok(result(id, BulkOutcome::Skipped, Some("User is deleted")));
+    }
+    let changes_own_access = matches!(
+        action,
+        BulkUserAction::Deactivate | BulkUserAction::ChangeRole { .. } | BulkUserAction::Delete
+    );
+    if changes_own_access && id == performer_id {
+        return Ok(result(
+            id,
+            BulkOutcome::Skipped,
+            Some("You cannot change your own account this way"),
+        ));
+    }
+
+    match action {
+        BulkUserAction::Activate | BulkUserAction::Deactivate => {
+            let active = matches!(action, BulkUserAction::Activate);
+            if target.is_active == active {
+                return Ok(result(id, BulkOutcome::Unchanged, None));
+            }
+            diesel::update(users::table.find(id))
+                .set(users::is_active.eq(active))
+                .execute(conn)
+                .await?;
+        }
+        BulkUserAction::ChangeRole { role } => {
+            if &target.role == role {
+                return Ok(result(id, BulkOutcome::Unchanged, None));
+            }
+            diesel::update(users::table.find(id))
+                .set(users::role.eq(role))
+                .execute(conn)
+                .await?;
+        }
+        BulkUserAction::ResetDevice => {
+            if target.device_id.is_none() {
+                return Ok(result(id, BulkOutcome::Unchanged, None));
+            }
+            diesel::update(users::table.find(id))
+                .set(users::device_id.eq(None::<String>))
+                .execute(conn)
+                .await?;
+        }
+        BulkUserAction::ChangeHall { hall } => {
+            let Some(roster_id) = active_roster else {
+                return Ok(result(
+                    id,
+                    BulkOutcome::Skipped,
+                    Some("No roster is active"),
+                ));
+            };
+            let assignment = schema::users_rosters::table
+                .filter(schema::users_rosters::user_id.eq(id))
+                .filter(schema::users_rosters::roster_id.eq(roster_id))
+                .select((schema::users_rosters::id, schema::users_rosters::hall))
+                .first::<(Uuid, Hall)>(conn)
+                .await
+                .optional()?;
+            let Some((assignment_id, current)) = assignment else {
+                return Ok(result(
+                    id,
+                    BulkOutcome::Skipped,
+                    Some("Not allocated in the active roster"),
+                ));
+            };
+            if &current == hall {
+                return Ok(result(id, BulkOutcome::Unchanged, None));
+            }
+            diesel::update(schema::users_rosters::table.find(assignment_id))
+                .set(schema::users_rosters::hall.eq(hall))
+                .execute(conn)
+                .await?;
+            diesel::update(users::table.find(id))
+                .set(users::current_roster_hall.eq(hall))
+                .execute(conn)
+                .await?;
+        }
+        BulkUserAction::Delete => {
+            diesel::update(users::table.find(id))
+                .set((
+                    users::deleted_at.eq(chrono::Local::now().naive_local()),
+                    users::deleted_by.eq(performer_id),
+                ))
+                .execute(conn)
+                .await?;
+            crate::services::sessions::revoke_all_sessions(conn, id, None).await?;
+        }
+    }
+    Ok(result(id, BulkOutcome::Done, None))
+}
+
+/// Runs one action over many users in a single transaction and records it as one
+/// `BulkUserAction` log entry.
+pub async fn bulk_update(
+    pool: Arc<Pool>,
+    payload: BulkUserRequest,
+    performer_id: Uuid,
+) -> Result<BulkUserReport, ModuleError> {
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|_| ModuleError::InternalError(POOL_ERROR_MSG.into()))?;
+
+    let ids = select_users(&mut conn, &payload).await?;
+    let action = payload.action.clone();
+    let results = conn
+        .build_transaction()
+        .run(|conn| {
+            Box::pin(async move {
+                let mut targets: HashMap<Uuid, Target> = schema::users::table
+                    .filter(schema::users::id.eq_any(&ids))
+                    .select((
+                        schema::users::id,
+                        schema::users::role,
+                        schema::users::is_active,
+                        schema::users::device_id,
+                        schema::users::deleted_at,
+                    ))
+                    .for_update()
+                    .load::<Target>(conn)
+                    .await?
+                    .into_iter()
+                    .map(|target| (target.id, target))
+                    .collect();
+                let active_roster = schema::rosters::table
+                    .filter(schema::rosters::is_active.eq(true))
+                    .select(schema::rosters::id)
+                    .first::<Uuid>(conn)
+                    .await
+                    .optional()?;
+
+                let mut results = Vec::with_capacity(ids.len());
+                for id in ids {
+                    let outcome = match targets.remove(&id) {
+                        Some(target) => {
+                            apply(conn, &action, &target, active_roster, performer_id).await?
+                        }
+                        None => result(id, BulkOutcome::Skipped, Some("User not found")),
+                    };
+                    results.push(outcome);
+                }
+                Ok::<_, ModuleError>(results)
+            })
+        })
+        .await?;
+
+    let count = |outcome: BulkOutcome| {
+        results
+            .iter()
+            .filter(|result| result.outcome == outcome)
+            .count()
+    };
+    let report = BulkUserReport {
+        action: payload.action,
+        done: count(BulkOutcome::Done),
+        unchanged: count(BulkOutcome::Unchanged),
+        skipped: count(BulkOutcome::Skipped),
+        results,
+    };
+
+    let done: Vec<Uuid> = report
+        .results
+        .iter()
+        .filter(|result| result.outcome == BulkOutcome::Done)
+        .map(|result| result.user_id)
+        .collect();
+    let skipped: Vec<&BulkUserResult> = report
+        .results
+        .iter()
+        .filter(|result| result.outcome == BulkOutcome::Skipped)
+        .collect();
+    let log = ActivityLog::new(ActivityType::BulkUserAction, performer_id)
+        .set_target_type("User".into())
+        .set_details(serde_json::json!({
+            "action": report.action,
+            "filter": payload.filter,
+            "done": done,
+            "unchanged": report.unchanged,
+            "skipped": skipped,
+        }))
+        .finish();
+    crate::services::activity_logs::emit_log(log, &mut conn).await?;
+
+    Ok(report)
+}
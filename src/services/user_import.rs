@@ -0,0 +1,683 @@
+use std::collections::{HashMap, HashSet};
+
+use axum::extract::Multipart;
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::Serialize;
+
+use super::*;
+use crate::dto::imports::*;
+use crate::models::activity_logs::{ActivityLog, ActivityType};
+use crate::models::import_profiles::ImportKind;
+use crate::models::users::User;
+use crate::services::imports::{ColumnMapper, Upload, read_table, read_upload};
+
+/// Used when no date format could be detected, so the rows report what was expected.
+const DEFAULT_DOB_FORMAT: &str = "%m/%d/%y";
+
+/// An uploaded file, checked row by row.
+struct Analysis {
+    headers: csv::StringRecord,
+    date_format: Option<String>,
+    rows: Vec<AnalysedRow>,
+}
+
+struct AnalysedRow {
+    record: csv::StringRecord,
+    report: ImportRowReport,
+    /// The row's reg_no column, which update imports match users by.
+    file_reg_no: String,
+}
+
+impl AnalysedRow {
+    fn is_valid(&self) -> bool {
+        self.report.user.is_some() && self.report.errors.is_empty()
+    }
+
+    fn is_changed(&self, field: &str) -> bool {
+        self.report
+            .changes
+            .iter()
+            .any(|change| change.field == field)
+    }
+}
+
+/// Validates an upload without writing anything. Every row is reported with the user read
+/// from it, what importing it would do and what is wrong with it.
+pub async fn dry_run(
+    pool: Arc<Pool>,
+    settings: &crate::SettingsCache,
+    multipart: Multipart,
+    query: ImportUsersQuery,
+) -> Result<ImportReport, ModuleError> {
+    let reg_no_format = settings.read().await.reg_no_format.clone();
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|_| ModuleError::InternalError(POOL_ERROR_MSG.into()))?;
+
+    let upload = read_upload(multipart).await?;
+    let analysis = analyse(&mut conn, &upload, &query, &reg_no_format).await?;
+
+    let count = |action: ImportAction| {
+        analysis
+            .rows
+            .iter()
+            .filter(|row| row.report.action == Some(action))
+            .count()
+    };
+    let (created, updated, unchanged) = (
+        count(ImportAction::Create),
+        count(ImportAction::Update),
+        count(ImportAction::Unchanged),
+    );
+    let valid = analysis.rows.iter().filter(|row| row.is_valid()).count();
+    let rows: Vec<ImportRowReport> = analysis.rows.into_iter().map(|row| row.report).collect();
+    Ok(ImportReport {
+        date_format: analysis.date_format,
+        total: rows.len(),
+        valid,
+        rejected: rows.len() - valid,
+        created,
+        updated,
+        unchanged,
+        rows,
+    })
+}
+
+/// Imports the valid rows of an upload and returns the rejected ones as a CSV file, with an
+/// `errors` column added. `X-Imported-Count`, `X-Updated-Count` and `X-Rejected-Count` carry
+/// the totals.
+pub async fn commit(
+    pool: Arc<Pool>,
+    settings: &crate::SettingsCache,
+    multipart: Multipart,
+    query: ImportUsersQuery,
+    performer_id: Uuid,
+) -> Result<(axum::http::HeaderMap, Vec<u8>), ModuleError> {
+    let reg_no_format = settings.read().await.reg_no_format.clone();
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|_| ModuleError::InternalError(POOL_ERROR_MSG.into()))?;
+
+    let upload = read_upload(multipart).await?;
+    let analysis = analyse(&mut conn, &upload, &query, &reg_no_format).await?;
+
+    let password_hash = crate::helpers::password_hasher(crate::helpers::PLACEHOLDER_PASSWORD)?;
+    let mut users: Vec<User> = Vec::new();
+    let mut updates: Vec<AnalysedRow> = Vec::new();
+    let mut rejected: Vec<AnalysedRow> = Vec::new();
+    for row in analysis.rows {
+        if !row.is_valid() {
+            rejected.push(row);
+            continue;
+        }
+        match row.report.action {
+            Some(ImportAction::Create) => {
+                let Some(user) = row.report.user else {
+                    continue;
+                };
+                users.push(user.into_user(password_hash.clone()));
+            }
+            Some(ImportAction::Update) => updates.push(row),
+            _ => {}
+        }
+    }
+
+    let total_imported = users.len();
+    let update_logs: Vec<ActivityLog> = updates
+        .iter()
+        .filter_map(|row| {
+            row.report
+                .user_id
+                .map(|id| update_log(id, row, performer_id))
+        })
+        .collect();
+    let total_updated = updates.len();
+    if !users.is_empty() || !updates.is_empty() {
+        conn.build_transaction()
+            .run(|conn| {
+                Box::pin(async move {
+                    let mut users = users;
+                    assign_reg_nos(conn, &mut users, &reg_no_format).await?;
+                    diesel::insert_into(schema::users::table)
+                        .values(&users)
+                        .execute(conn)
+                        .await?;
+                    for row in &updates {
+                        apply_changes(conn, row).await?;
+                    }
+                    Ok::<(), ModuleError>(())
+                })
+            })
+            .await?;
+    }
+
+    for log in update_logs {
+        crate::services::activity_logs::emit_log(log, &mut conn).await?;
+    }
+    let log = ActivityLog::new(ActivityType::UserImported, performer_id)
+        .set_details(serde_json::json!({
+            "total_imported": total_imported,
+            "total_updated": total_updated,
+            "total_rejected": rejected.len(),
+            "profile_id": query.profile_id,
+        }))
+        .finish();
+    crate::services::activity_logs::emit_log(log, &mut conn).await?;
+
+    let mut wtr = csv::Writer::from_writer(vec![]);
+    let mut header = analysis.headers.clone();
+    header.push_field("errors");
+    wtr.write_record(&header)
+        .map_err(|e| ModuleError::InternalError(e.to_string().into()))?;
+    for row in &rejected {
+        let mut record = row.record.clone();
+        record.push_field(&row.report.errors.join("; "));
+        wtr.write_record(&record)
+            .map_err(|e| ModuleError::InternalError(e.to_string().into()))?;
+    }
+    let data = wtr
+        .into_inner()
+        .map_err(|e| ModuleError::InternalError(e.to_string().into()))?;
+
+    let mut headers = axum::http::HeaderMap::new();
+    headers.insert(
+        axum::http::header::CONTENT_DISPOSITION,
+        "attachment; filename=\"rejected_users.csv\""
+            .parse()
+            .unwrap(),
+    );
+    headers.insert(
+        axum::http::header::CONTENT_TYPE,
+        "text/csv".parse().unwrap(),
+    );
+    headers.insert("x-imported-count", total_imported.into());
+    headers.insert("x-updated-count", total_updated.into());
+    headers.insert("x-rejected-count", rejected.len().into());
+    Ok((headers, data))
+}
+
+async fn analyse(
+    conn: &mut crate::Connection<'_>,
+    upload: &Upload,
+    query: &ImportUsersQuery,
+    reg_no_format: &str,
+) -> Result<Analysis, ModuleError> {
+    let profile =
+        crate::services::import_profiles::resolve(conn, query.profile_id, ImportKind::Users)
+            .await?;
+    let table = read_table(upload)?;
+    let headers = table.headers;
+    let mapper = ColumnMapper::new(profile.clone(), &headers)?;
+
+    let mut records = Vec::new();
+    let mut rows = Vec::new();
+    for (index, result) in table.records.into_iter().enumerate() {
+        match result {
+            Ok(record) => records.push(record),
+            Err(error) => rows.push(AnalysedRow {
+                record: csv::StringRecord::new(),
+                report: rejected_row(index + 2, error),
+                file_reg_no: String::new(),
+            }),
+        }
+    }
+
+    let date_format = mapper.date_format(&records, "dob");
+    let dob_format = date_format.as_deref().unwrap_or(DEFAULT_DOB_FORMAT);
+    for record in records {
+        let line = record
+            .position()
+            .map_or(0, |position| position.line() as usize);
+        let report = parse_user(line, &mapper, &record, dob_format);
+        let file_reg_no = mapper.value(&record, "reg_no");
+        rows.push(AnalysedRow {
+            record,
+            report,
+            file_reg_no,
+        });
+    }
+    rows.sort_by_key(|row| row.report.row);
+
+    match_existing(conn, &mut rows, &profile, query.update_existing).await?;
+    for row in rows.iter_mut() {
+        if row.report.action == Some(ImportAction::Create) {
+            require_new_user_fields(&mut row.report);
+        }
+    }
+    preview_reg_nos(conn, &mut rows, reg_no_format).await?;
+    for row in rows.iter_mut().filter(|row| !row.is_valid()) {
+        row.report.action = None;
+        row.report.reg_no = None;
+    }
+    Ok(Analysis {
+        headers,
+        date_format,
+        rows,
+    })
+}
+
+fn rejected_row(line: usize, error: String) -> ImportRowReport {
+    ImportRowReport {
+        row: line,
+        user: None,
+        reg_no: None,
+        action: None,
+        user_id: None,
+        changes: vec![],
+        errors: vec![error],
+        warnings: vec![],
+    }
+}
+
+/// Reads a row. Empty required fields are only errors for new users, so they are checked once
+/// the row is known not to match an existing one.
+fn parse_user(
+    line: usize,
+    mapper: &ColumnMapper,
+    record: &csv::StringRecord,
+    dob_format: &str,
+) -> ImportRowReport {
+    let mut errors = Vec::new();
+    let mut warnings = Vec::new();
+
+    let first_name = mapper.value(record, "first_name");
+    let last_name = mapper.value(record, "last_name");
+
+    let email = mapper.value(record, "email").to_lowercase();
+    if !email.is_empty() && !email.contains('@') {
+        errors.push(format!("Invalid email '{email}'"));
+    }
+
+    let dob = mapper.value(record, "dob");
+    let dob = if dob.is_empty() {
+        warnings.push("No date of birth".to_string());
+        None
+    } else {
+        match NaiveDate::parse_from_str(&dob, dob_format) {
+            Ok(date) => Some(NaiveDateTime::from(date)),
+            Err(_) => {
+                errors.push(format!(
+                    "Invalid date of birth '{dob}', expected format {dob_format}"
+                ));
+                None
+            }
+        }
+    };
+
+    let year_joined = mapper.value(record, "year_joined");
+    if !year_joined.is_empty() && year_joined.parse::<i32>().is_err() {
+        errors.push(format!("Invalid year joined '{year_joined}'"));
+    }
+
+    let gender = mapper.value(record, "gender");
+    let gender = match normalise_gender(&gender) {
+        Some(gender) => Some(gender.to_string()),
+        None if gender.is_empty() => {
+            warnings.push("No gender".to_string());
+            None
+        }
+        None => {
+            warnings.push(format!("Unknown gender '{gender}', left empty"));
+            None
+        }
+    };
+
+    let phone = Some(mapper.value(record, "phone")).filter(|phone| !phone.is_empty());
+
+    ImportRowReport {
+        row: line,
+        user: Some(ImportedUserDto {
+            first_name,
+            last_name,
+            email,
+            dob,
+            year_joined,
+            gender,
+            phone,
+        }),
+        reg_no: None,
+        action: None,
+        user_id: None,
+        changes: vec![],
+        errors,
+        warnings,
+    }
+}
+
+fn normalise_gender(gender: &str) -> Option<&'static str> {
+    match gender.trim().to_lowercase().as_str() {
+        "male" | "m" => Some("Male"),
+        "female" | "f" => Some("Female"),
+        _ => None,
+    }
+}
+
+fn require_new_user_fields(report: &mut ImportRowReport) {
+    let Some(user) = report.user.as_ref() else {
+        return;
+    };
+    let mut errors = Vec::new();
+    if user.first_name.is_empty() || user.last_name.is_empty() {
+        errors.push("First and last name are required".to_string());
+    }
+    if user.email.is_empty() {
+        errors.push("Email is required".to_string());
+    }
+    if user.year_joined.is_empty() {
+        errors.push("Year joined is required".to_string());
+    }
+    report.errors.splice(0..0, errors);
+}
+
+/// Decides what each row does. Emails repeated within the file are rejected (every
+/// occurrence after the first). Without `update_existing`, rows whose email already has an
+/// account are rejected too; with it, rows matching a user by email or reg_no update them.
+/// Rows matching a deleted user are rejected either way.
+async fn match_existing(
+    conn: &mut crate::Connection<'_>,
+    rows: &mut [AnalysedRow],
+    profile: &ImportProfileDto,
+    update_existing: bool,
+) -> Result<(), ModuleError> {
+    let emails: Vec<String> = rows
+        .iter()
+        .filter_map(|row| row.report.user.as_ref())
+        .map(|user| user.email.clone())
+        .filter(|email| !email.is_empty())
+        .collect();
+    let reg_nos: Vec<String> = rows
+        .iter()
+        .filter(|_| update_existing)
+        .map(|row| row.file_reg_no.clone())
+        .filter(|reg_no| !reg_no.is_empty())
+        .collect();
+    let existing = schema::users::table
+        .filter(
+            super::lower(schema::users::email)
+                .eq_any(&emails)
+                .or(schema::users::reg_no.eq_any(&reg_nos)),
+        )
+        .select(User::as_select())
+        .load::<User>(conn)
+        .await?;
+    let by_email: HashMap<String, &User> = existing
+        .iter()
+        .map(|user| (user.email.to_lowercase(), user))
+        .collect();
+    let by_reg_no: HashMap<&str, &User> = existing
+        .iter()
+        .map(|user| (user.reg_no.as_str(), user))
+        .collect();
+    let deleted: HashSet<Uuid> = schema::users::table
+        .filter(schema::users::id.eq_any(existing.iter().map(|user| user.id)))
+        .filter(schema::users::deleted_at.is_not_null())
+        .select(schema::users::id)
+        .load::<Uuid>(conn)
+        .await?
+        .into_iter()
+        .collect();
+
+    let mut seen_emails: HashMap<String, usize> = HashMap::new();
+    let mut seen_users: HashMap<Uuid, usize> = HashMap::new();
+    for row in rows.iter_mut() {
+        let Some(user) = row.report.user.as_ref() else {
+            continue;
+        };
+        let email = user.email.clone();
+        if !email.is_empty() {
+            match seen_emails.get(&email) {
+                Some(first) => row
+                    .report
+                    .errors
+                    .push(format!("Email '{email}' is also used on row {first}")),
+                None => {
+                    seen_emails.insert(email.clone(), row.report.row);
+                }
+            }
+        }
+
+        let email_match = by_email.get(&email).copied();
+        if !update_existing {
+            match email_match {
+                Some(existing) if deleted.contains(&existing.id) => row.report.errors.push(
+                    format!("Email '{email}' belongs to a deleted user; restore them instead"),
+                ),
+                Some(_) => row
+                    .report
+                    .errors
+                    .push(format!("A user with email '{email}' already exists")),
+                None => {}
+            }
+            row.report.action = Some(ImportAction::Create);
+            continue;
+        }
+
+        let reg_no_match = by_reg_no.get(row.file_reg_no.as_str()).copied();
+        let existing = match (email_match, reg_no_match) {
+            (Some(by_email), Some(by_reg_no)) if by_email.id != by_reg_no.id => {
+                row.report.errors.push(format!(
+                    "Email '{email}' belongs to {} but reg_no '{}' to another user",
+                    by_email.reg_no, row.file_reg_no
+                ));
+                continue;
+            }
+            (by_email, by_reg_no) => by_email.or(by_reg_no),
+        };
+        let Some(existing) = existing else {
+            if !row.file_reg_no.is_empty() {
+                row.report.warnings.push(format!(
+                    "No user has reg_no '{}', a new one is allocated",
+                    row.file_reg_no
+                ));
+            }
+            row.report.action = Some(ImportAction::Create);
+            continue;
+        };
+        if let Some(first) = seen_users.get(&existing.id) {
+            row.report.errors.push(format!(
+                "Row {first} also matches the user with reg_no {}",
+                existing.reg_no
+            ));
+            continue;
+        }
+        seen_users.insert(existing.id, row.report.row);
+        if deleted.contains(&existing.id) {
+            row.report.errors.push(format!(
+                "The user with reg_no {} is deleted; restore them before updating",
+                existing.reg_no
+            ));
+            continue;
+        }
+
+        let changes = diff(existing, user, profile);
+        row.report.user_id = Some(existing.id);
+        row.report.reg_no = Some(existing.reg_no.clone());
+        row.report.action = Some(if changes.is_empty() {
+            ImportAction::Unchanged
+        } else {
+            ImportAction::Update
+        });
+        row.report.changes = changes;
+    }
+    Ok(())
+}
+
+/// The values of `imported` that the profile's overwrite rules let replace `existing`'s.
+fn diff(
+    existing: &User,
+    imported: &ImportedUserDto,
+    profile: &ImportProfileDto,
+) -> Vec<FieldChange> {
+    fn non_empty(value: &str) -> Option<String> {
+        Some(value.to_string()).filter(|value| !value.is_empty())
+    }
+
+    let mut changes = Vec::new();
+    let mut compare = |field: &str, before: Option<String>, after: Option<String>| {
+        push_change(&mut changes, profile, field, before, after)
+    };
+    compare(
+        "first_name",
+        non_empty(&existing.first_name),
+        non_empty(&imported.first_name),
+    );
+    compare(
+        "last_name",
+        non_empty(&existing.last_name),
+        non_empty(&imported.last_name),
+    );
+    compare(
+        "email",
+        non_empty(&existing.email.to_lowercase()),
+        non_empty(&imported.email),
+    );
+    compare(
+        "year_joined",
+        non_empty(&existing.year_joined),
+        non_empty(&imported.year_joined),
+    );
+    // Stored genders predate the normalisation, so compare them normalised.
+    compare(
+        "gender",
+        existing
+            .gender
+            .as_deref()
+            .map(|gender| normalise_gender(gender).map_or(gender.to_string(), str::to_string)),
+        imported.gender.clone(),
+    );
+    compare(
+        "phone",
+        existing.phone.as_deref().and_then(non_empty),
+        imported.phone.clone(),
+    );
+    push_change(&mut changes, profile, "dob", existing.dob, imported.dob);
+    changes
+}
+
+fn push_change<T: PartialEq + Serialize>(
+    changes: &mut Vec<FieldChange>,
+    profile: &ImportProfileDto,
+    field: &str,
+    before: Option<T>,
+    after: Option<T>,
+) {
+    let Some(after) = after else {
+        return;
+    };
+    match profile.overwrite_rule(field) {
+        OverwriteRule::Never => return,
+        OverwriteRule::IfEmpty if before.is_some() => return,
+        _ => {}
+    }
+    if before.as_ref() == Some(&after) {
+        return;
+    }
+    changes.push(FieldChange {
+        field: field.to_string(),
+        before: serde_json::json!(before),
+        after: serde_json::json!(after),
+    });
+}
+
+async fn apply_changes(
+    conn: &mut crate::AsyncPgConnection,
+    row: &AnalysedRow,
+) -> Result<(), ModuleError> {
+    let (Some(id), Some(user)) = (row.report.user_id, row.report.user.as_ref()) else {
+        return Ok(());
+    };
+    diesel::update(schema::users::table.find(id))
+        .set((
+            row.is_changed("first_name")
+                .then(|| schema::users::first_name.eq(&user.first_name)),
+            row.is_changed("last_name")
+                .then(|| schema::users::last_name.eq(&user.last_name)),
+            row.is_changed("email")
+                .then(|| schema::users::email.eq(&user.email)),
+            row.is_changed("dob")
+                .then(|| schema::users::dob.eq(user.dob)),
+            row.is_changed("year_joined")
+                .then(|| schema::users::year_joined.eq(&user.year_joined)),
+            row.is_changed("gender")
+                .then(|| schema::users::gender.eq(&user.gender)),
+            row.is_changed("phone")
+                .then(|| schema::users::phone.eq(&user.phone)),
+            schema::users::updated_at.eq(chrono::Local::now().naive_local()),
+        ))
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+fn update_log(user_id: Uuid, row: &AnalysedRow, performer_id: Uuid) -> ActivityLog {
+    let mut before = serde_json::Map::new();
+    let mut after = serde_json::Map::new();
+    for change in &row.report.changes {
+        before.insert(change.field.clone(), change.before.clone());
+        after.insert(change.field.clone(), change.after.clone());
+    }
+    ActivityLog::new(ActivityType::UserUpdated, performer_id)
+        .set_target_id(user_id)
+        .set_target_type("User".into())
+        .set_details(serde_json::json!({
+            "source": "import",
+            "before": before,
+            "after": after,
+        }))
+        .finish()
+}
+
+/// Shows the reg_nos the valid new users would get, in file order, without reserving them;
+/// registrations made before the commit can shift them.
+async fn preview_reg_nos(
+    conn: &mut crate::Connection<'_>,
+    rows: &mut [AnalysedRow],
+    reg_no_format: &str,
+) -> Result<(), ModuleError> {
+    let mut counters: HashMap<String, i64> = HashMap::new();
+    for row in rows.iter_mut() {
+        if !row.is_valid() || row.report.action != Some(ImportAction::Create) {
+            continue;
+        }
+        let Some(user) = row.report.user.as_ref() else {
+            continue;
+        };
+        let year = user.year_joined.clone();
+        let counter = match counters.get_mut(&year) {
+            Some(counter) => counter,
+            None => {
+                let last = crate::services::reg_nos::last_code(conn, &year).await?;
+                counters.entry(year.clone()).or_insert(last)
+            }
+        };
+        *counter += 1;
+        row.report.reg_no = Some(User::format_reg_no(reg_no_format, &year, *counter));
+    }
+    Ok(())
+}
+
+/// Allocates the reg_nos of the new users, in file order within each year.
+async fn assign_reg_nos(
+    conn: &mut crate::AsyncPgConnection,
+    users: &mut [User],
+    reg_no_format: &str,
+) -> Result<(), ModuleError> {
+    let mut years: Vec<String> = users.iter().map(|user| user.year_joined.clone()).collect();
+    years.sort();
+    years.dedup();
+    for year in years {
+        let mut users_of_year: Vec<&mut User> = users
+            .iter_mut()
+            .filter(|user| user.year_joined == year)
+            .collect();
+        let reg_nos =
+            crate::services::reg_nos::allocate(conn, reg_no_format, &year, users_of_year.len())
+                .await?;
+        for (user, reg_no) in users_of_year.iter_mut().zip(reg_nos) {
+            user.reg_no = reg_no;
+        }
+    }
+    Ok(())
+}
@@ -1,10 +1,9 @@
-use std::collections::{HashMap, HashSet};
-
-use axum::extract::Multipart;
-use chrono::Datelike;
+use chrono::{Datelike, NaiveTime};
 
 use super::*;
 use crate::models::activity_logs::{ActivityLog, ActivityType};
+use crate::models::custom_fields::{CustomField, CustomFieldType};
+use crate::services::custom_fields::Viewer;
 use crate::{dto::user::*, models::users::*};
 use diesel::result::DatabaseErrorKind;
 use diesel::result::Error::DatabaseError;
@@ -26,13 +25,13 @@ pub async fn seed_default_admin(pool: Arc<Pool>) -> Result<(), ModuleError> {
 
     tracing::info!("Seeding default admin user...");
 
-    let password_hash = crate::helpers::password_hasher("password")?;
+    let password_hash = crate::helpers::password_hasher(crate::helpers::PLACEHOLDER_PASSWORD)?;
     let year = chrono::Local::now().year().to_string();
 
-    let admin = User {
+    let mut admin = User {
         id: Uuid::now_v7(),
         username: Some("admin".to_string()),
-        reg_no: format!("{}/KUD/001", year),
+        reg_no: String::new(),
         first_name: "Admin".to_string(),
         last_name: "User".to_string(),
         email: "admin@kud.com".to_string(),
@@ -55,12 +54,11 @@ pub async fn seed_default_admin(pool: Arc<Pool>) -> Result<(), ModuleError> {
         country: None,
         phone: None,
         hall_derivation: 0,
+        must_change_password: true,
     };
 
-    diesel::insert_into(schema::users::table)
-        .values(&admin)
-        .execute(&mut conn)
-        .await?;
+    let reg_no_format = crate::services::settings::reg_no_format(&mut conn).await?;
+    insert_user(&mut conn, &mut admin, &reg_no_format).await?;
 
     tracing::info!("Default admin seeded successfully.");
     Ok(())
@@ -68,37 +66,18 @@ pub async fn seed_default_admin(pool: Arc<Pool>) -> Result<(), ModuleError> {
 
 pub async fn register_user(
     pool: Arc<Pool>,
+    settings: &crate::SettingsCache,
     payload: NewUser,
     performer_id: Uuid,
 ) -> Result<Message<()>, ModuleError> {
+    let reg_no_format = settings.read().await.reg_no_format.clone();
     let mut conn = pool
         .get()
         .await
         .map_err(|_| ModuleError::InternalError(POOL_ERROR_MSG.into()))?;
 
     let mut user: User = payload.try_into()?;
-
-    let max_reg_no = schema::users::table
-        .filter(schema::users::year_joined.eq(&user.year_joined))
-        .select(schema::users::reg_no)
-        .order(schema::users::reg_no.desc())
-        .first::<String>(&mut conn)
-        .await
-        .optional()?;
-
-    let next_code = match max_reg_no {
-        Some(reg) => {
-            let last_part = reg.split('/').last().unwrap_or("0");
-            last_part.parse::<i64>().unwrap_or(0) + 1
-        }
-        None => 1,
-    };
-
-    user.set_reg_no(next_code);
-    diesel::insert_into(schema::users::table)
-        .values(&user)
-        .execute(&mut conn)
-        .await?;
+    insert_user(&mut conn, &mut user, &reg_no_format).await?;
 
     let log = ActivityLog::new(ActivityType::UserCreated, performer_id)
         .set_target_id(user.id)
@@ -109,6 +88,24 @@ pub async fn register_user(
     Ok("User registered successfully".into())
 }
 
+/// Allocates the next reg_no for the user's `year_joined` and inserts the row. Takes a bare
+/// connection so it can run inside a transaction.
+pub async fn insert_user(
+    conn: &mut crate::AsyncPgConnection,
+    user: &mut User,
+    reg_no_format: &str,
+) -> Result<(), ModuleError> {
+    let reg_no = crate::services::reg_nos::allocate(conn, reg_no_format, &user.year_joined, 1)
+        .await?
+        .remove(0);
+    user.reg_no = reg_no;
+    diesel::insert_into(schema::users::table)
+        .values(&*user)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
 pub async fn find_user_by_email_or_username(
     conn: &mut crate::Connection<'_>,
     identifier: &str,
@@ -119,6 +116,7 @@ pub async fn find_user_by_email_or_username(
                 .eq(identifier)
                 .or(schema::users::username.eq(identifier)),
         )
+        .filter(schema::users::deleted_at.is_null())
         .select(UserDto::as_select())
         .first::<UserDto>(conn)
         .await
@@ -127,43 +125,171 @@ pub async fn find_user_by_email_or_username(
     Ok(user)
 }
 
-pub async fn get_user(pool: Arc<Pool>, id: Uuid) -> Result<UserDto, ModuleError> {
+/// The user with the custom fields and emergency contacts `viewer` may see.
+pub async fn get_user(
+    pool: Arc<Pool>,
+    id: Uuid,
+    viewer: Viewer,
+) -> Result<UserProfileDto, ModuleError> {
     let mut conn = pool
         .get()
         .await
         .map_err(|_| ModuleError::InternalError(POOL_ERROR_MSG.into()))?;
     let user = schema::users::table
         .find(id)
-        .select(UserDto::as_select())
-        .first::<UserDto>(&mut conn)
+        .select((
+            UserDto::as_select(),
+            schema::users::custom_fields,
+            schema::users::emergency_contacts,
+        ))
+        .first::<(UserDto, serde_json::Value, serde_json::Value)>(&mut conn)
         .await
         .optional()?;
     match user {
-        Some(u) => Ok(u),
+        Some((user, custom_fields, emergency_contacts)) => {
+            let fields = crate::services::custom_fields::load(&mut conn).await?;
+            crate::services::custom_fields::profile(
+                user,
+                custom_fields,
+                emergency_contacts,
+                &fields,
+                viewer,
+            )
+        }
         None => Err(ModuleError::ResourceNotFound("User not found".into())),
     }
 }
 
+/// The users matching every filter in `payload`, unsorted and unpaginated. `fields` are the
+/// custom field definitions.
+pub fn filter_users(
+    payload: &UserFilter,
+    fields: &[CustomField],
+) -> Result<schema::users::BoxedQuery<'static, diesel::pg::Pg>, ModuleError> {
+    use schema::users;
+
+    let mut query = users::table.into_boxed();
+    query = if payload.deleted.unwrap_or(false) {
+        query.filter(users::deleted_at.is_not_null())
+    } else {
+        query.filter(users::deleted_at.is_null())
+    };
+
+    if let Some(search) = &payload.search {
+        query = query.filter(
+            users::first_name
+                .ilike(format!("%{}%", search))
+                .or(users::last_name.ilike(format!("%{}%", search)))
+                .or(users::email.ilike(format!("%{}%", search)))
+                .or(users::username.ilike(format!("%{}%", search))),
+        );
+    }
+    if let Some(role) = payload.role.clone() {
+        query = query.filter(users::role.eq(role));
+    }
+    if let Some(gender) = &payload.gender {
+        query = query.filter(users::gender.ilike(helpers::escape_like(gender.trim())));
+    }
+    if let Some(year_joined) = payload.year_joined.clone() {
+        query = query.filter(users::year_joined.eq(year_joined));
+    }
+    if let Some(is_active) = payload.is_active {
+        query = query.filter(users::is_active.eq(is_active));
+    }
+    if let Some(hall) = payload.hall.clone() {
+        query = query.filter(users::current_roster_hall.eq(hall));
+    }
+    if let Some(from) = payload.last_seen_from {
+        query = query.filter(users::last_seen.ge(from.and_time(NaiveTime::MIN)));
+    }
+    if let Some(to) = payload.last_seen_to {
+        let next_day = to.succ_opt().unwrap_or(to).and_time(NaiveTime::MIN);
+        query = query.filter(users::last_seen.lt(next_day));
+    }
+    for field in payload.missing_fields(fields)? {
+        query = match field {
+            MissingField::Phone => query.filter(users::phone.is_null().or(users::phone.eq(""))),
+            MissingField::Dob => query.filter(users::dob.is_null()),
+            MissingField::Gender => query.filter(users::gender.is_null().or(users::gender.eq(""))),
+            MissingField::Address => {
+                query.filter(users::address.is_null().or(users::address.eq("")))
+            }
+            MissingField::Avatar => query.filter(users::avatar_url.is_null()),
+            MissingField::Hall => query.filter(users::current_roster_hall.is_null()),
+            MissingField::LastSeen => query.filter(users::last_seen.is_null()),
+            MissingField::EmergencyContact => {
+                query.filter(users::emergency_contacts.eq(serde_json::json!([])))
+            }
+            MissingField::Custom(key) => {
+                query.filter(diesel::dsl::not(users::custom_fields.has_key(key)))
+            }
+        };
+    }
+    for (key, value) in payload.custom_values()? {
+        let field = fields
+            .iter()
+            .find(|field| field.key == key)
+            .ok_or(ModuleError::BadRequest(
+                format!("Unknown custom field '{key}'").into(),
+            ))?;
+        let value = crate::services::custom_fields::parse_value(field, &value.into())?.ok_or(
+            ModuleError::BadRequest(format!("No value given for custom field '{key}'").into()),
+        )?;
+        query = match (field.field_type, value) {
+            (CustomFieldType::Text, serde_json::Value::String(text)) => query.filter(
+                users::custom_fields
+                    .retrieve_as_text(key.to_string())
+                    .ilike(helpers::escape_like(&text)),
+            ),
+            (_, value) => {
+                query.filter(users::custom_fields.contains(serde_json::json!({ key: value })))
+            }
+        };
+    }
+    Ok(query)
+}
+
 pub async fn get_all_users(
     pool: Arc<Pool>,
     payload: UserFilter,
-) -> Result<Vec<UserDto>, ModuleError> {
+) -> Result<Vec<UserProfileDto>, ModuleError> {
+    use schema::users;
+
+    let sort_keys = payload.sort_keys()?;
     let mut conn = pool
         .get()
         .await
         .map_err(|_| ModuleError::InternalError(POOL_ERROR_MSG.into()))?;
+    let fields = crate::services::custom_fields::load(&mut conn).await?;
+    let mut query = filter_users(&payload, &fields)?;
 
-    let mut query = schema::users::table.into_boxed();
-
-    if let Some(search) = payload.search {
-        query = query.filter(
-            schema::users::first_name
-                .ilike(format!("%{}%", search))
-                .or(schema::users::last_name.ilike(format!("%{}%", search)))
-                .or(schema::users::email.ilike(format!("%{}%", search)))
-                .or(schema::users::username.ilike(format!("%{}%", search))),
-        );
+    if sort_keys.is_empty() && payload.deleted.unwrap_or(false) {
+        query = query.then_order_by(users::deleted_at.desc());
+    }
+    for (key, descending) in sort_keys {
+        query = match (key, descending) {
+            (UserSortKey::FirstName, false) => query.then_order_by(users::first_name.asc()),
+            (UserSortKey::FirstName, true) => query.then_order_by(users::first_name.desc()),
+            (UserSortKey::LastName, false) => query.then_order_by(users::last_name.asc()),
+            (UserSortKey::LastName, true) => query.then_order_by(users::last_name.desc()),
+            (UserSortKey::RegNo, false) => query.then_order_by(users::reg_no.asc()),
+            (UserSortKey::RegNo, true) => query.then_order_by(users::reg_no.desc()),
+            (UserSortKey::YearJoined, false) => query.then_order_by(users::year_joined.asc()),
+            (UserSortKey::YearJoined, true) => query.then_order_by(users::year_joined.desc()),
+            (UserSortKey::CreatedAt, false) => query.then_order_by(users::created_at.asc()),
+            (UserSortKey::CreatedAt, true) => query.then_order_by(users::created_at.desc()),
+            (UserSortKey::LastSeen, false) => {
+                query.then_order_by(users::last_seen.asc().nulls_last())
+            }
+            (UserSortKey::LastSeen, true) => {
+                query.then_order_by(users::last_seen.desc().nulls_last())
+            }
+            (UserSortKey::Dob, false) => query.then_order_by(users::dob.asc().nulls_last()),
+            (UserSortKey::Dob, true) => query.then_order_by(users::dob.desc().nulls_last()),
+        };
     }
+    // Ties, and unsorted lists, keep a stable order so pages do not overlap.
+    query = query.then_order_by(users::id.asc());
 
     let limit = payload.limit.unwrap_or(300);
     let offset = (payload.page.unwrap_or(1) - 1) * limit;
@@ -171,11 +297,67 @@ pub async fn get_all_users(
     let users = query
         .limit(limit)
         .offset(offset)
-        .select(UserDto::as_select())
-        .load::<UserDto>(&mut conn)
+        .select((
+            UserDto::as_select(),
+            users::custom_fields,
+            users::emergency_contacts,
+        ))
+        .load::<(UserDto, serde_json::Value, serde_json::Value)>(&mut conn)
+        .await?;
+
+    users
+        .into_iter()
+        .map(|(user, custom_fields, emergency_contacts)| {
+            crate::services::custom_fields::profile(
+                user,
+                custom_fields,
+                emergency_contacts,
+                &fields,
+                Viewer::Manager,
+            )
+        })
+        .collect()
+}
+
+/// Active users whose reg_no, first name, last name or full name starts with `q`, for
+/// check-in screens that look people up as the name is typed.
+pub async fn typeahead(
+    pool: Arc<Pool>,
+    query: TypeaheadQuery,
+) -> Result<Vec<UserSuggestion>, ModuleError> {
+    use schema::users;
+
+    let q = query.q.split_whitespace().collect::<Vec<_>>().join(" ");
+    if q.is_empty() {
+        return Ok(Vec::new());
+    }
+    let limit = query.limit.unwrap_or(10).clamp(1, 25);
+    let prefix = format!("{}%", helpers::escape_like(&q));
+
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|_| ModuleError::InternalError(POOL_ERROR_MSG.into()))?;
+    let suggestions = users::table
+        .filter(users::deleted_at.is_null())
+        .filter(users::is_active.eq(true))
+        .filter(
+            users::reg_no
+                .ilike(&prefix)
+                .or(users::first_name.ilike(&prefix))
+                .or(users::last_name.ilike(&prefix))
+                .or(users::first_name
+                    .concat(" ")
+                    .concat(users::last_name)
+                    .ilike(&prefix)),
+        )
+        .order((users::last_name.asc(), users::first_name.asc()))
+        .limit(limit)
+        .select(UserSuggestion::as_select())
+        .load::<UserSuggestion>(&mut conn)
         .await?;
 
-    Ok(users)
+    Ok(suggestions)
 }
 
 pub async fn update_user(
@@ -191,6 +373,14 @@ pub async fn update_user(
     if payload.is_empty() {
         return Err(ModuleError::BadRequest("No fields to update".into()));
     }
+    let (custom_fields, emergency_contacts) = profile_changes(
+        &mut conn,
+        id,
+        payload.custom_fields,
+        payload.emergency_contacts,
+        Viewer::Owner,
+    )
+    .await?;
 
     let target = schema::users::table.filter(schema::users::id.eq(id));
 
@@ -205,6 +395,11 @@ pub async fn update_user(
             payload.city.map(|v| schema::users::city.eq(v)),
             payload.state.map(|v| schema::users::state.eq(v)),
             payload.country.map(|v| schema::users::country.eq(v)),
+            custom_fields.map(|(set, removed)| {
+                schema::users::custom_fields
+                    .eq(schema::users::custom_fields.concat(set).remove(removed))
+            }),
+            emergency_contacts.map(|v| schema::users::emergency_contacts.eq(v)),
         ))
         .execute(&mut conn)
         .await;
@@ -219,15 +414,13 @@ pub async fn update_user(
 
             Ok("User updated successfully".into())
         }
-        Err(DatabaseError(DatabaseErrorKind::UniqueViolation, info)) => {
-            return Err(ModuleError::Error(
-                format!(
-                    "Duplicate value for {}",
-                    info.constraint_name().unwrap_or("field")
-                )
-                .into(),
-            ));
-        }
+        Err(DatabaseError(DatabaseErrorKind::UniqueViolation, info)) => Err(ModuleError::Error(
+            format!(
+                "Duplicate value for {}",
+                info.constraint_name().unwrap_or("field")
+            )
+            .into(),
+        )),
         Err(e) => Err(e.into()),
     }
 }
@@ -245,6 +438,14 @@ pub async fn admin_update_user(
     if payload.is_empty() {
         return Err(ModuleError::BadRequest("No fields to update".into()));
     }
+    let (custom_fields, emergency_contacts) = profile_changes(
+        &mut conn,
+        id,
+        payload.custom_fields.take(),
+        payload.emergency_contacts.take(),
+        Viewer::Manager,
+    )
+    .await?;
 
     let target = schema::users::table.filter(schema::users::id.eq(id));
 
@@ -270,6 +471,11 @@ pub async fn admin_update_user(
                 .year_joined
                 .map(|v| schema::users::year_joined.eq(v)),
             payload.password.map(|v| schema::users::password_hash.eq(v)),
+            custom_fields.map(|(set, removed)| {
+                schema::users::custom_fields
+                    .eq(schema::users::custom_fields.concat(set).remove(removed))
+            }),
+            emergency_contacts.map(|v| schema::users::emergency_contacts.eq(v)),
         ))
         .execute(&mut conn)
         .await;
@@ -284,19 +490,63 @@ pub async fn admin_update_user(
 
             Ok("User updated successfully".into())
         }
-        Err(DatabaseError(DatabaseErrorKind::UniqueViolation, info)) => {
-            return Err(ModuleError::Error(
-                format!(
-                    "Duplicate value for {}",
-                    info.constraint_name().unwrap_or("field")
-                )
-                .into(),
-            ));
-        }
+        Err(DatabaseError(DatabaseErrorKind::UniqueViolation, info)) => Err(ModuleError::Error(
+            format!(
+                "Duplicate value for {}",
+                info.constraint_name().unwrap_or("field")
+            )
+            .into(),
+        )),
         Err(e) => Err(e.into()),
     }
 }
 
+/// Checks the custom field and emergency contact parts of an update made by `viewer`. Custom
+/// fields come back as the values to merge in and the keys to remove, so concurrent updates
+/// of other fields are kept.
+async fn profile_changes(
+    conn: &mut crate::Connection<'_>,
+    id: Uuid,
+    custom_fields: Option<serde_json::Map<String, serde_json::Value>>,
+    emergency_contacts: Option<Vec<EmergencyContact>>,
+    viewer: Viewer,
+) -> Result<
+    (
+        Option<(serde_json::Value, Vec<String>)>,
+        Option<serde_json::Value>,
+    ),
+    ModuleError,
+> {
+    let custom_fields = match custom_fields {
+        Some(changes) => {
+            let current = schema::users::table
+                .find(id)
+                .select(schema::users::custom_fields)
+                .first::<serde_json::Value>(conn)
+                .await
+                .optional()?
+                .ok_or(ModuleError::Error("User not found".into()))?;
+            let current = match current {
+                serde_json::Value::Object(values) => values,
+                _ => serde_json::Map::new(),
+            };
+            let fields = crate::services::custom_fields::load(conn).await?;
+            let (set, removed) =
+                crate::services::custom_fields::apply_changes(&fields, &current, changes, viewer)?;
+            Some((serde_json::Value::Object(set), removed))
+        }
+        None => None,
+    };
+    let emergency_contacts = emergency_contacts
+        .map(crate::services::custom_fields::validate_contacts)
+        .transpose()?
+        .map(serde_json::to_value)
+        .transpose()?;
+    Ok((custom_fields, emergency_contacts))
+}
+
+/// Hides the user from lists, sign-in and allocation and signs them out everywhere. Their
+/// attendance, check-ins and events stay; `restore_user` brings the account back.
 pub async fn delete_user(
     pool: Arc<Pool>,
     id: Uuid,
@@ -306,17 +556,134 @@ pub async fn delete_user(
         .get()
         .await
         .map_err(|_| ModuleError::InternalError(POOL_ERROR_MSG.into()))?;
+    if id == performer_id {
+        return Err(ModuleError::BadRequest(
+            "You cannot delete your own account".into(),
+        ));
+    }
+
+    let deleted = diesel::update(
+        schema::users::table
+            .find(id)
+            .filter(schema::users::deleted_at.is_null()),
+    )
+    .set((
+        schema::users::deleted_at.eq(chrono::Local::now().naive_local()),
+        schema::users::deleted_by.eq(performer_id),
+    ))
+    .execute(&mut conn)
+    .await?;
+    if deleted == 0 {
+        return Err(ModuleError::ResourceNotFound("User not found".into()));
+    }
+    let revoked = crate::services::sessions::revoke_all_sessions(&mut conn, id, None).await?;
+
+    let log = ActivityLog::new(ActivityType::UserDeleted, performer_id)
+        .set_target_id(id)
+        .set_target_type("User".into())
+        .set_details(serde_json::json!({ "session_ids": revoked }))
+        .finish();
+    crate::services::activity_logs::emit_log(log, &mut conn).await?;
+
+    Ok("User deleted successfully".into())
+}
+
+pub async fn restore_user(
+    pool: Arc<Pool>,
+    id: Uuid,
+    performer_id: Uuid,
+) -> Result<Message<()>, ModuleError> {
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|_| ModuleError::InternalError(POOL_ERROR_MSG.into()))?;
+
+    let anonymised = diesel::select(diesel::dsl::exists(
+        schema::users::table
+            .find(id)
+            .filter(schema::users::anonymised_at.is_not_null()),
+    ))
+    .get_result::<bool>(&mut conn)
+    .await?;
+    if anonymised {
+        return Err(ModuleError::BadRequest(
+            "Anonymised users cannot be restored".into(),
+        ));
+    }
+
+    let restored = diesel::update(
+        schema::users::table
+            .find(id)
+            .filter(schema::users::deleted_at.is_not_null()),
+    )
+    .set((
+        schema::users::deleted_at.eq(None::<NaiveDateTime>),
+        schema::users::deleted_by.eq(None::<Uuid>),
+    ))
+    .execute(&mut conn)
+    .await?;
+    if restored == 0 {
+        return Err(ModuleError::ResourceNotFound(
+            "No deleted user with this id".into(),
+        ));
+    }
+
+    let log = ActivityLog::new(ActivityType::UserRestored, performer_id)
+        .set_target_id(id)
+        .set_target_type("User".into())
+        .finish();
+    crate::services::activity_logs::emit_log(log, &mut conn).await?;
+
+    Ok("User restored successfully".into())
+}
+
+/// Removes a deleted user for good, together with everything that cascades from them:
+/// attendance, check-ins, sessions and the events they created, and their avatar files.
+/// Only users already deleted can be purged, so this never happens in one step.
+pub async fn purge_user(
+    pool: Arc<Pool>,
+    storage: Arc<dyn crate::storage::Storage>,
+    id: Uuid,
+    performer_id: Uuid,
+) -> Result<Message<()>, ModuleError> {
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|_| ModuleError::InternalError(POOL_ERROR_MSG.into()))?;
+
+    let (email, reg_no, deleted_at) = schema::users::table
+        .find(id)
+        .select((
+            schema::users::email,
+            schema::users::reg_no,
+            schema::users::deleted_at,
+        ))
+        .first::<(String, String, Option<NaiveDateTime>)>(&mut conn)
+        .await
+        .optional()?
+        .ok_or(ModuleError::ResourceNotFound("User not found".into()))?;
+    if deleted_at.is_none() {
+        return Err(ModuleError::BadRequest(
+            "Only deleted users can be purged; delete the user first".into(),
+        ));
+    }
+
     diesel::delete(schema::users::table.find(id))
         .execute(&mut conn)
         .await?;
+    crate::services::avatars::delete_files(storage.as_ref(), id).await?;
 
-    let log = ActivityLog::new(ActivityType::UserUpdated, performer_id) // Using UserUpdated for delete as well, or should I add UserDeleted?
+    let log = ActivityLog::new(ActivityType::UserPurged, performer_id)
         .set_target_id(id)
         .set_target_type("User".into())
+        .set_details(serde_json::json!({
+            "email": email,
+            "reg_no": reg_no,
+        }))
         .finish();
     crate::services::activity_logs::emit_log(log, &mut conn).await?;
 
-    Ok("User deleted successfully".into())
+    Ok("User purged permanently".into())
 }
 
 pub async fn deactivate_user(
@@ -392,113 +759,48 @@ pub async fn update_user_role(
     Ok("User role updated successfully".into())
 }
 
-pub async fn import_users(
-    pool: Arc<Pool>,
-    mut multipart: Multipart,
-    performer_id: Uuid,
-) -> Result<Message<()>, ModuleError> {
-    let mut conn = pool.get().await?;
-
-    let mut year_counter: HashMap<String, i64> = HashMap::new();
-    let mut total_imported = 0;
-    let mut users = Vec::new();
-
-    let mut temp_years = HashSet::new();
-    let mut temp_users = Vec::new();
-
-    while let Some(field) = multipart
-        .next_field()
-        .await
-        .map_err(|e| ModuleError::InternalError(e.to_string().into()))?
-    {
-        let data = field
-            .bytes()
-            .await
-            .map_err(|e| ModuleError::InternalError(e.to_string().into()))?;
-        let body = String::from_utf8(data.to_vec())
-            .map_err(|e| ModuleError::InternalError(e.to_string().into()))?;
-        let mut rdr = csv::Reader::from_reader(body.as_bytes());
-
-        for result in rdr.deserialize() {
-            let mut user: crate::dto::user::CsvUser =
-                result.map_err(|e| ModuleError::InternalError(e.to_string().into()))?;
-
-            user.validate();
-            let new_user: User = user.to_new_user()?.try_into()?;
-            temp_years.insert(new_user.year_joined.to_string());
-            temp_users.push(new_user);
-        }
-    }
-    // Get starting counts for each year from database based on max existing reg_no
-    for year in temp_years {
-        let max_reg_no = schema::users::table
-            .filter(schema::users::year_joined.eq(&year))
-            .select(schema::users::reg_no)
-            .order(schema::users::reg_no.desc())
-            .first::<String>(&mut conn)
-            .await
-            .optional()?;
-
-        let next_code = match max_reg_no {
-            Some(reg) => {
-                let last_part = reg.split('/').last().unwrap_or("0");
-                last_part.parse::<i64>().unwrap_or(0)
-            }
-            None => 0,
-        };
-        year_counter.insert(year, next_code);
-    }
-
-    for mut user in temp_users {
-        let year_key = user.year_joined.to_string();
-        // Get or initialize counter for this year
-        let counter = year_counter.entry(year_key.clone()).or_insert(0);
-        // Increment counter and set reg_no
-        *counter += 1;
-        user.set_reg_no(*counter);
-        users.push(user);
-        total_imported += 1;
-    }
-
-    // Insert all users
-    let tx: Result<(), ModuleError> = conn
-        .build_transaction()
-        .run(|conn| {
-            Box::pin(async move {
-                diesel::insert_into(schema::users::table)
-                    .values(&users)
-                    .execute(conn)
-                    .await?;
-                Ok(())
-            })
-        })
-        .await;
-
-    match tx {
-        Ok(_) => {
-            let log = ActivityLog::new(ActivityType::UserImported, performer_id)
-                .set_details(serde_json::json!({ "total_imported": total_imported }))
-                .finish();
-            crate::services::activity_logs::emit_log(log, &mut conn).await?;
-
-            Ok("User registered successfully".into())
-        }
-        Err(e) => Err(e),
-    }
-}
-
 pub async fn export_users(
     pool: Arc<Pool>,
+    format: crate::dto::exports::ExportFormat,
 ) -> Result<(axum::http::HeaderMap, Vec<u8>), ModuleError> {
+    use crate::services::exports::{Cell, Sheet};
+
     let mut conn = pool.get().await?;
 
     let users = schema::users::table
-        .select(UserDto::as_select())
-        .load::<UserDto>(&mut conn)
+        .filter(schema::users::deleted_at.is_null())
+        .select((
+            UserDto::as_select(),
+            schema::users::custom_fields,
+            schema::users::emergency_contacts,
+        ))
+        .load::<(UserDto, serde_json::Value, serde_json::Value)>(&mut conn)
         .await?;
-
-    let mut wtr = csv::Writer::from_writer(vec![]);
-    wtr.write_record(&[
+    let fields = crate::services::custom_fields::load(&mut conn).await?;
+
+    let rows = users
+        .into_iter()
+        .map(|(user, custom_fields, emergency_contacts)| {
+            let mut row = vec![
+                Cell::Text(user.reg_no),
+                Cell::Text(user.first_name),
+                Cell::Text(user.last_name),
+                Cell::Text(user.email),
+                user.phone.into(),
+                user.dob.map_or(Cell::Empty, |dob| Cell::Date(dob.date())),
+                user.gender.into(),
+                Cell::Text(user.year_joined),
+            ];
+            row.extend(fields.iter().map(|field| {
+                crate::services::custom_fields::cell(field, custom_fields.get(&field.key))
+            }));
+            let contacts: Vec<EmergencyContact> =
+                serde_json::from_value(emergency_contacts).unwrap_or_default();
+            row.push(crate::services::custom_fields::contacts_cell(&contacts));
+            row
+        })
+        .collect();
+    let mut headers = [
         "Usher No",
         "First Name",
         "Last Name",
@@ -507,59 +809,61 @@ pub async fn export_users(
         "Date of Birth",
         "Gender",
         "Year of Entry",
-    ])
-    .map_err(|e| ModuleError::InternalError(e.to_string().into()))?;
-    for user in users {
-        let row = vec![
-            user.reg_no,
-            user.first_name,
-            user.last_name,
-            user.email,
-            user.phone.unwrap_or("N/A".to_string()),
-            user.dob.map(|d| d.to_string()).unwrap_or("N/A".to_string()),
-            user.gender.unwrap_or("N/A".to_string()),
-            user.year_joined,
-        ];
-        // write_record expects &[&str]
-        let row_refs: Vec<&str> = row.iter().map(|s| s.as_str()).collect();
-        wtr.write_record(&row_refs)
-            .map_err(|e| ModuleError::InternalError(e.to_string().into()))?;
-    }
-    let data = wtr
-        .into_inner()
-        .map_err(|e| ModuleError::InternalError(e.to_string().into()))?;
-    let mut headers = axum::http::HeaderMap::new();
-    headers.insert(
-        axum::http::header::CONTENT_DISPOSITION,
-        "attachment; filename=\"users.csv\"".parse().unwrap(),
-    );
-    headers.insert(
-        axum::http::header::CONTENT_TYPE,
-        "text/csv".parse().unwrap(),
-    );
-    Ok((headers, data))
+    ]
+    .map(String::from)
+    .to_vec();
+    headers.extend(fields.iter().map(|field| field.label.clone()));
+    headers.push("Emergency Contacts".to_string());
+    let sheet = Sheet {
+        name: "Users".to_string(),
+        headers,
+        rows,
+    };
+    crate::services::exports::export(format, "users", vec![sheet])
 }
 
+/// Replaces the caller's own password once they have confirmed the current one, which also
+/// clears `must_change_password`.
 pub async fn change_password(
     pool: Arc<Pool>,
+    settings: &crate::SettingsCache,
     payload: ChangePasswordRequest,
     performer_id: Uuid,
 ) -> Result<Message<()>, ModuleError> {
-    let mut conn = pool.get().await?;
-    let password_hash = crate::helpers::password_hasher(&payload.password)?;
-    let user_id = schema::users::table
-        .filter(schema::users::email.eq(&payload.email))
-        .select(schema::users::id)
-        .first::<Uuid>(&mut conn)
-        .await?;
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|_| ModuleError::InternalError(POOL_ERROR_MSG.into()))?;
+    let current_hash = schema::users::table
+        .find(performer_id)
+        .select(schema::users::password_hash)
+        .first::<String>(&mut conn)
+        .await
+        .optional()?
+        .ok_or(ModuleError::ResourceNotFound("User not found".into()))?;
+    if !crate::helpers::password_verfier(&payload.current_password, &current_hash) {
+        return Err(ModuleError::BadRequest(
+            "Current password is incorrect".into(),
+        ));
+    }
+    if payload.password == payload.current_password {
+        return Err(ModuleError::BadRequest(
+            "New password must differ from the current one".into(),
+        ));
+    }
+    crate::helpers::check_password_policy(&payload.password, &*settings.read().await)?;
 
-    diesel::update(schema::users::table.find(user_id))
-        .set(schema::users::password_hash.eq(password_hash))
+    let password_hash = crate::helpers::password_hasher(&payload.password)?;
+    diesel::update(schema::users::table.find(performer_id))
+        .set((
+            schema::users::password_hash.eq(password_hash),
+            schema::users::must_change_password.eq(false),
+        ))
         .execute(&mut conn)
         .await?;
 
     let log = ActivityLog::new(ActivityType::PasswordChanged, performer_id)
-        .set_target_id(user_id)
+        .set_target_id(performer_id)
         .set_target_type("User".into())
         .finish();
     crate::services::activity_logs::emit_log(log, &mut conn).await?;
@@ -567,6 +871,45 @@ pub async fn change_password(
     Ok("Password changed successfully".into())
 }
 
+/// Sets another user's password for them. They have to change it at their next sign-in.
+pub async fn set_user_password(
+    pool: Arc<Pool>,
+    settings: &crate::SettingsCache,
+    id: Uuid,
+    payload: SetPasswordRequest,
+    performer_id: Uuid,
+) -> Result<Message<()>, ModuleError> {
+    crate::helpers::check_password_policy(&payload.password, &*settings.read().await)?;
+
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|_| ModuleError::InternalError(POOL_ERROR_MSG.into()))?;
+    let password_hash = crate::helpers::password_hasher(&payload.password)?;
+    let updated = diesel::update(
+        schema::users::table
+            .find(id)
+            .filter(schema::users::deleted_at.is_null()),
+    )
+    .set((
+        schema::users::password_hash.eq(password_hash),
+        schema::users::must_change_password.eq(true),
+    ))
+    .execute(&mut conn)
+    .await?;
+    if updated == 0 {
+        return Err(ModuleError::ResourceNotFound("User not found".into()));
+    }
+
+    let log = ActivityLog::new(ActivityType::PasswordChanged, performer_id)
+        .set_target_id(id)
+        .set_target_type("User".into())
+        .finish();
+    crate::services::activity_logs::emit_log(log, &mut conn).await?;
+
+    Ok("Password set successfully".into())
+}
+
 pub async fn reset_user_device_id(
     user_id: Uuid,
     pool: Arc<Pool>,
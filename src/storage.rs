@@ -0,0 +1,91 @@
+//! Where uploaded files live.
+//!
+//! Services only see the `Storage` trait, so files can move to an object store without
+//! touching them. `LocalStorage`, the default, writes under `UPLOAD_DIR` (default `uploads`),
+//! which `main` serves at `/uploads`. `UPLOADS_BASE_URL` (default `/uploads`) is the public
+//! address of that route, or of whatever else serves the directory.
+
+use std::path::{Component, Path, PathBuf};
+
+use crate::ModuleError;
+
+#[async_trait::async_trait]
+pub trait Storage: Send + Sync {
+    /// Stores `data` under `key`, replacing whatever was there, and returns its public URL.
+    async fn put(
+        &self,
+        key: &str,
+        data: Vec<u8>,
+        content_type: &str,
+    ) -> Result<String, ModuleError>;
+
+    /// Removes the file under `key`. A missing file is not an error.
+    async fn delete(&self, key: &str) -> Result<(), ModuleError>;
+}
+
+pub struct LocalStorage {
+    root: PathBuf,
+    base_url: String,
+}
+
+impl LocalStorage {
+    pub fn new(root: impl Into<PathBuf>, base_url: impl Into<String>) -> Self {
+        Self {
+            root: root.into(),
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        Self::new(
+            std::env::var("UPLOAD_DIR").unwrap_or("uploads".to_string()),
+            std::env::var("UPLOADS_BASE_URL").unwrap_or("/uploads".to_string()),
+        )
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Keys are relative paths; anything that could step out of the root is refused.
+    fn path(&self, key: &str) -> Result<PathBuf, ModuleError> {
+        let relative = Path::new(key);
+        if key.is_empty()
+            || !relative
+                .components()
+                .all(|component| matches!(component, Component::Normal(_)))
+        {
+            return Err(ModuleError::InternalError(
+                format!("Invalid storage key '{key}'").into(),
+            ));
+        }
+        Ok(self.root.join(relative))
+    }
+}
+
+#[async_trait::async_trait]
+impl Storage for LocalStorage {
+    async fn put(
+        &self,
+        key: &str,
+        data: Vec<u8>,
+        _content_type: &str,
+    ) -> Result<String, ModuleError> {
+        let path = self.path(key)?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        // Write beside the target and rename, so readers never see half a file.
+        let partial = path.with_extension("partial");
+        tokio::fs::write(&partial, data).await?;
+        tokio::fs::rename(&partial, &path).await?;
+        Ok(format!("{}/{key}", self.base_url))
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), ModuleError> {
+        match tokio::fs::remove_file(self.path(key)?).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
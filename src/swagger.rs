@@ -8,7 +8,23 @@ use utoipa_swagger_ui::SwaggerUi;
 #[openapi(
     paths(
         handlers::auth::auth,
+        handlers::auth::refresh,
+        handlers::auth::logout,
+        handlers::auth::forgot_password,
+        handlers::auth::reset_password,
+        handlers::auth::accept_invite,
+        handlers::auth::verify_mfa,
+        handlers::auth::setup_mfa,
+        handlers::auth::enable_mfa,
+        handlers::auth::disable_mfa,
+        handlers::auth::regenerate_recovery_codes,
+        handlers::auth::google_start,
+        handlers::auth::google_callback,
         handlers::users::register_user,
+        handlers::users::invite_user,
+        handlers::users::get_invites,
+        handlers::users::resend_invite,
+        handlers::users::import_users,
         handlers::users::get_user,
         handlers::users::get_all_users,
         handlers::users::update_user,
@@ -18,11 +34,34 @@ use utoipa_swagger_ui::SwaggerUi;
         handlers::users::change_password,
         handlers::users::reset_user_device_id,
         handlers::users::admin_update_user,
+        handlers::users::upload_avatar,
+        handlers::users::remove_avatar,
+        handlers::users::admin_upload_avatar,
+        handlers::users::admin_remove_avatar,
+        handlers::users::typeahead,
+        handlers::users::bulk_update_users,
+        handlers::users::preview_renumber,
+        handlers::users::renumber,
         handlers::users::delete_user,
+        handlers::users::restore_user,
+        handlers::users::purge_user,
+        handlers::users::set_user_password,
+        handlers::users::anonymise_user,
+        handlers::users::export_personal_data,
+        handlers::users::admin_export_personal_data,
+        handlers::users::get_my_sessions,
+        handlers::users::revoke_my_session,
+        handlers::users::revoke_other_sessions,
+        handlers::users::admin_get_user_sessions,
+        handlers::users::admin_revoke_user_sessions,
+        handlers::users::admin_revoke_user_session,
+        handlers::users::unlock_user,
+        handlers::users::reset_user_mfa,
         handlers::user_attendance::sign_attendance,
         handlers::user_attendance::admin_sign_attendance,
         handlers::user_attendance::get_attendance_on_day,
         handlers::user_attendance::revoke_attendance,
+        handlers::user_attendance::export_attendance,
         handlers::events::create_event,
         handlers::events::update_event,
         handlers::events::delete_event,
@@ -51,17 +90,102 @@ use utoipa_swagger_ui::SwaggerUi;
         handlers::roster::get_user_roster_history,
         handlers::logs::get_logs,
         handlers::logs::get_user_activity,
+        handlers::permissions::get_permissions,
+        handlers::permissions::update_role_permissions,
+        handlers::settings::get_settings,
+        handlers::settings::update_settings,
+        handlers::api_keys::get_api_keys,
+        handlers::api_keys::create_api_key,
+        handlers::api_keys::revoke_api_key,
+        handlers::keys::jwks,
+        handlers::keys::get_signing_keys,
+        handlers::keys::rotate_signing_key,
+        handlers::impersonation::start_impersonation,
+        handlers::impersonation::get_active_impersonations,
+        handlers::impersonation::end_impersonation,
+        handlers::registrations::submit_application,
+        handlers::registrations::get_applications,
+        handlers::registrations::approve_application,
+        handlers::registrations::reject_application,
+        handlers::import_profiles::get_import_profiles,
+        handlers::import_profiles::create_import_profile,
+        handlers::import_profiles::update_import_profile,
+        handlers::import_profiles::delete_import_profile,
+        handlers::custom_fields::get_custom_fields,
+        handlers::custom_fields::create_custom_field,
+        handlers::custom_fields::update_custom_field,
+        handlers::custom_fields::delete_custom_field,
     ),
     components(
         schemas(
             crate::auth::dto::LoginPayload,
+            crate::auth::dto::ForgotPasswordRequest,
+            crate::auth::dto::ResetPasswordRequest,
+            crate::auth::dto::ResetMethod,
+            crate::auth::dto::MfaChallenge,
+            crate::auth::dto::LoginResponse,
+            dto::mfa::MfaSetupDto,
+            dto::mfa::MfaCodeRequest,
+            dto::mfa::RecoveryCodesDto,
+            dto::settings::OrganisationSettings,
+            dto::settings::UpdateSettingsRequest,
+            dto::api_keys::CreateApiKeyRequest,
+            dto::api_keys::ApiKeyDto,
+            dto::api_keys::CreatedApiKeyDto,
+            crate::auth::keys::SigningKeyDto,
+            dto::impersonation::StartImpersonationRequest,
+            dto::impersonation::ImpersonationDto,
+            dto::impersonation::ImpersonationSessionDto,
+            dto::registrations::RegistrationRequest,
+            dto::registrations::RejectApplicationRequest,
+            models::registration_applications::RegistrationApplication,
+            models::registration_applications::ApplicationStatus,
             dto::user::UserDto,
+            dto::user::UserProfileDto,
+            dto::user::PersonalDataExport,
+            dto::user::EmergencyContact,
             dto::user::NewUser,
+            dto::invites::InviteUserRequest,
+            dto::invites::AcceptInviteRequest,
+            dto::invites::InviteStatus,
+            dto::invites::InviteDto,
+            dto::exports::ExportFormat,
+            dto::exports::PersonalDataFormat,
+            dto::exports::PersonalDataQuery,
+            dto::imports::ImportMode,
+            dto::imports::ImportedUserDto,
+            dto::imports::ImportRowReport,
+            dto::imports::ImportReport,
+            dto::imports::ImportAction,
+            dto::imports::FieldChange,
+            dto::imports::ValueTransform,
+            dto::imports::OverwriteRule,
+            dto::imports::ImportProfileRequest,
+            dto::imports::ImportProfileDto,
+            models::import_profiles::ImportKind,
+            dto::custom_fields::CustomFieldRequest,
+            models::custom_fields::CustomField,
+            models::custom_fields::CustomFieldType,
+            models::custom_fields::FieldVisibility,
             dto::user::UserFilter,
             dto::user::UpdateUserRequest,
             dto::user::UpdateUserRoleRequest,
+            dto::user::AvatarDto,
+            dto::user::TypeaheadQuery,
+            dto::user::UserSuggestion,
+            dto::user::RenumberRequest,
+            dto::user::BulkUserAction,
+            dto::user::BulkUserRequest,
+            dto::user::BulkOutcome,
+            dto::user::BulkUserResult,
+            dto::user::BulkUserReport,
+            dto::user::RegNoChange,
+            dto::user::RenumberReport,
+            dto::user::AvatarThumbnail,
             dto::user::AdminUpdateUserRequest,
             dto::user::ChangePasswordRequest,
+            dto::user::SetPasswordRequest,
+            dto::session::SessionDto,
             models::users::Role,
             dto::attendance::UserAttendanceDto,
             dto::attendance::AttendanceWithUser,
@@ -91,6 +215,10 @@ use utoipa_swagger_ui::SwaggerUi;
             dto::pagination::Metadata,
             models::activity_logs::ActivityLog,
             models::activity_logs::ActivityLogResponse,
+            models::permissions::Permission,
+            dto::permissions::RolePermissionsDto,
+            dto::permissions::PermissionCatalogueDto,
+            dto::permissions::UpdateRolePermissionsRequest,
             models::activity_logs::ActivityType,
             dto::MessageEmpty,
             dto::MessageString,
@@ -114,6 +242,14 @@ use utoipa_swagger_ui::SwaggerUi;
         (name = "analytics", description = "Analytics and reporting endpoints"),
         (name = "roster", description = "Roster management endpoints"),
         (name = "logs", description = "Activity logging endpoints"),
+        (name = "permissions", description = "Role permission management endpoints"),
+        (name = "settings", description = "Organisation settings endpoints"),
+        (name = "api-keys", description = "API key management endpoints"),
+        (name = "keys", description = "Token signing key endpoints"),
+        (name = "registrations", description = "Self-registration and approval endpoints"),
+        (name = "impersonation", description = "Admin \"view as user\" endpoints"),
+        (name = "import-profiles", description = "Saved layouts for user and roster imports"),
+        (name = "custom-fields", description = "Admin-defined user profile fields"),
     ),
     modifiers(&SecurityAddon)
 )]
@@ -146,7 +282,16 @@ impl utoipa::Modify for SecurityAddon {
                         utoipa::openapi::security::ApiKeyValue::new("access_token"),
                     ),
                 ),
-            )
+            );
+            // Access tokens and API keys can also be sent as `Authorization: Bearer`.
+            components.add_security_scheme(
+                "jwt",
+                utoipa::openapi::security::SecurityScheme::Http(
+                    utoipa::openapi::security::HttpBuilder::new()
+                        .scheme(utoipa::openapi::security::HttpAuthScheme::Bearer)
+                        .build(),
+                ),
+            );
         }
     }
 }